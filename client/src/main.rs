@@ -3,17 +3,21 @@
 
 use std::{
     collections::HashSet,
-    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    path::PathBuf,
+    time::Duration,
 };
 
-use bytes::{Buf, Bytes};
 use clap::Parser;
 use formatters::{
-    format_caa, format_character_string, format_domain, format_hinfo, format_ipv4, format_ipv6,
-    format_soa, format_svcb,
+    format_caa, format_character_string, format_domain, format_edns, format_hinfo, format_ipv4,
+    format_ipv6, format_soa, format_srv, format_svcb,
+};
+use types::{Domain, Question, RData, RecordClass, RecordType};
+use utils::{
+    make_request_with_options, parse_spki_pin, read_ca_certificates, HttpsOptions, RequestOptions,
+    SpkiPin, TlsOptions, Transport, DNS_PORT, HTTPS_PORT, QUIC_PORT, TLS_PORT,
 };
-use types::{Domain, Question, RecordClass, RecordType};
-use utils::{make_request, Transport};
 
 mod formatters;
 
@@ -63,7 +67,9 @@ record_type! {
         A,
         NS,
         MX,
+        PTR,
         SOA,
+        SRV,
         ANY,
         TXT,
         CAA,
@@ -71,6 +77,7 @@ record_type! {
         AAAA,
         HTTPS,
         CNAME,
+        DNAME,
         HINFO,
     }
 }
@@ -93,85 +100,122 @@ struct Cli {
         long = "tcp",
         conflicts_with = "udp",
         conflicts_with = "tls",
-        conflicts_with = "https"
+        conflicts_with = "https",
+        conflicts_with = "quic"
     )]
     tcp: bool,
     #[clap(
         long = "udp",
         conflicts_with = "tcp",
         conflicts_with = "tls",
-        conflicts_with = "https"
+        conflicts_with = "https",
+        conflicts_with = "quic"
     )]
     udp: bool,
     #[clap(
         long = "tls",
         conflicts_with = "udp",
         conflicts_with = "tcp",
-        conflicts_with = "https"
+        conflicts_with = "https",
+        conflicts_with = "quic"
     )]
     tls: bool,
     #[clap(
         long = "https",
         conflicts_with = "tcp",
         conflicts_with = "tls",
-        conflicts_with = "udp"
+        conflicts_with = "udp",
+        conflicts_with = "quic"
     )]
     https: bool,
+    #[clap(
+        long = "quic",
+        conflicts_with = "tcp",
+        conflicts_with = "tls",
+        conflicts_with = "udp",
+        conflicts_with = "https"
+    )]
+    quic: bool,
+
+    #[clap(
+        long = "doh-template",
+        requires = "https",
+        value_parser = HttpsOptions::from_template,
+        help = "DoH URI template, like https://dns.example/dns-query{?dns}. Uses GET when it has {?dns}"
+    )]
+    doh_template: Option<HttpsOptions>,
+
+    #[clap(
+        long = "tls-ca",
+        help = "PEM file of CAs to trust instead of the web PKI roots, for --tls, --https and --quic"
+    )]
+    tls_ca: Option<PathBuf>,
+
+    #[clap(
+        long = "tls-name",
+        help = "Name the DoT or DoQ server's certificate is for, also sent as SNI. Defaults to its IP address"
+    )]
+    tls_name: Option<String>,
+
+    #[clap(
+        long = "tls-pin",
+        value_parser = parse_spki_pin,
+        help = "Base64 SHA-256 of a public key the server's certificate has to have (RFC 7858 section 4.2). Can be given more than once"
+    )]
+    tls_pin: Vec<SpkiPin>,
+
+    #[clap(
+        long = "tls-opportunistic",
+        conflicts_with = "tls_ca",
+        conflicts_with = "tls_pin",
+        help = "Encrypt without authenticating the server"
+    )]
+    tls_opportunistic: bool,
+
+    #[clap(
+        long = "tls-keylog",
+        help = "Write TLS session keys to the file in SSLKEYLOGFILE"
+    )]
+    tls_keylog: bool,
+
+    #[clap(
+        long = "timeout",
+        help = "Seconds to wait for each attempt at the nameserver"
+    )]
+    timeout: Option<u64>,
+
+    #[clap(long = "retries", help = "Times to resend an unanswered UDP query")]
+    retries: Option<u32>,
+
+    #[clap(
+        long = "randomize-case",
+        help = "Randomize the case of the name (DNS 0x20) and check the response kept it"
+    )]
+    randomize_case: bool,
 
     #[clap(long = "no-color")]
     no_color: bool,
 }
 
-fn format_data(
-    rtype: RecordType,
-    mut data: Bytes,
-    _no_color: bool,
-    domains: Option<Vec<Domain>>,
-    after_ptr: Option<usize>,
-) -> Option<String> {
-    match rtype {
-        RecordType::CNAME | RecordType::NS => Some(format_domain(
-            domains
-                .expect("This is garunteed to be Some(...) by the parser")
-                .first()
-                .expect("This is garunteed to be Some(...) by the parser"),
-            true,
-        )),
-        RecordType::MX => Some(format!(
+fn format_data(data: &RData, _no_color: bool) -> Option<String> {
+    match data {
+        RData::CNAME(domain) | RData::DNAME(domain) | RData::NS(domain) | RData::PTR(domain) => {
+            Some(format_domain(domain, true))
+        }
+        RData::MX(mx) => Some(format!(
             "{} {}",
-            data.get_u16(),
-            format_domain(
-                domains
-                    .expect("This is garunteed to be Some(...) by the parser")
-                    .first()
-                    .expect("This is garunteed to be Some(...) by the parser"),
-                true
-            )
-        )),
-        RecordType::SOA => Some(format_soa(
-            data,
-            domains.expect("This is garunteed to be Some(...) by the parser"),
-            after_ptr.expect("This is garunteed to be Some(...) by the parser"),
+            mx.preference,
+            format_domain(&mx.exchange, true)
         )),
-        RecordType::AAAA => Some(format_ipv6(Ipv6Addr::new(
-            data.get_u16(),
-            data.get_u16(),
-            data.get_u16(),
-            data.get_u16(),
-            data.get_u16(),
-            data.get_u16(),
-            data.get_u16(),
-            data.get_u16(),
-        ))),
-        RecordType::A => Some(format_ipv4(Ipv4Addr::new(
-            data[0], data[1], data[2], data[3],
-        ))),
-        RecordType::TXT => {
+        RData::SOA(soa) => Some(format_soa(soa)),
+        RData::AAAA(ip) => Some(format_ipv6(*ip)),
+        RData::A(ip) => Some(format_ipv4(*ip)),
+        RData::TXT(strings) => {
             let mut res = "\"\x1b[0;32m".to_string();
-            while !data.is_empty() {
-                match format_character_string(&mut data) {
+            for string in strings {
+                match format_character_string(string) {
                     Ok(data) => {
-                        res += &data;
+                        res += data;
                     }
                     Err(err) => return Some(err.to_string()),
                 }
@@ -179,10 +223,11 @@ fn format_data(
 
             Some(res + "\x1b[0m\"")
         }
-        RecordType::SVCB | RecordType::HTTPS => format_svcb(data),
-        RecordType::CAA => Some(format_caa(data)),
-        RecordType::HINFO => Some(format_hinfo(data)),
-        _ => None,
+        RData::SVCB(svcb) | RData::HTTPS(svcb) => format_svcb(svcb),
+        RData::CAA(caa) => Some(format_caa(caa)),
+        RData::HINFO(hinfo) => Some(format_hinfo(hinfo)),
+        RData::SRV(srv) => Some(format_srv(srv)),
+        RData::Unknown(_) => None,
     }
 }
 
@@ -212,6 +257,8 @@ fn main() {
 
     let transport = if cli.https {
         Transport::Https
+    } else if cli.quic {
+        Transport::Quic
     } else if cli.tls {
         Transport::Tls
     } else if cli.udp {
@@ -236,9 +283,10 @@ fn main() {
     }
 
     let default_port = match transport {
-        Transport::Udp | Transport::Tcp | Transport::Unspecified => 53,
-        Transport::Tls => 853,
-        Transport::Https => 443,
+        Transport::Udp | Transport::Tcp | Transport::Unspecified => DNS_PORT,
+        Transport::Tls => TLS_PORT,
+        Transport::Https => HTTPS_PORT,
+        Transport::Quic => QUIC_PORT,
         _ => unreachable!(),
     };
 
@@ -254,7 +302,36 @@ fn main() {
         IpAddr::V6(v6) => SocketAddr::V6(SocketAddrV6::new(v6, port, 0, 0)),
     };
 
-    make_req(domain, qtype, source, transport, no_color);
+    let ca_certificates = match cli.tls_ca.as_deref().map(read_ca_certificates) {
+        Some(Ok(certificates)) => certificates,
+        Some(Err(err)) => {
+            eprintln!("{err}");
+            return;
+        }
+        None => vec![],
+    };
+
+    let defaults = RequestOptions::default();
+    let attempt_timeout = cli
+        .timeout
+        .map_or(defaults.attempt_timeout, Duration::from_secs);
+    let options = RequestOptions {
+        https: cli.doh_template.unwrap_or_default(),
+        tls: TlsOptions {
+            server_name: cli.tls_name,
+            ca_certificates,
+            spki_pins: cli.tls_pin,
+            opportunistic: cli.tls_opportunistic,
+            key_log: cli.tls_keylog,
+        },
+        attempt_timeout,
+        // A long timeout shouldn't get cut short by the overall one
+        overall_timeout: defaults.overall_timeout.max(attempt_timeout),
+        udp_retries: cli.retries.unwrap_or(defaults.udp_retries),
+        randomize_case: cli.randomize_case,
+    };
+
+    make_req(domain, qtype, source, transport, &options, no_color);
 }
 
 fn make_req(
@@ -262,9 +339,10 @@ fn make_req(
     qtype: RecordType,
     source: SocketAddr,
     transport: Transport,
+    options: &RequestOptions,
     no_color: bool,
 ) {
-    let res = match make_request(
+    let res = match make_request_with_options(
         Question {
             name: Domain(domain),
             qtype,
@@ -272,6 +350,7 @@ fn make_req(
         },
         source,
         transport,
+        options,
     ) {
         Ok(res) => res,
         Err(err) => {
@@ -281,6 +360,10 @@ fn make_req(
         }
     };
 
+    if let Some(edns) = &res.edns {
+        println!("{}", format_edns(edns));
+    }
+
     let mut records = vec![];
 
     for record in res.answers {
@@ -312,13 +395,7 @@ fn make_req(
 
         //hackclub.com.           3789    IN      HINFO   "RFC8482" ""
         //google.com.             300     IN      A       74.125.142.139
-        if let Some(display) = format_data(
-            record.rtype,
-            record.data,
-            no_color,
-            record.domain_data,
-            record.after_ptr,
-        ) {
+        if let Some(display) = format_data(&record.data, no_color) {
             println!(
                 "{section} \x1b[0;96m{:<23}\x1b[0;93m {:<8}\x1b[0m {:<7} {:<7} {}",
                 record.name.idna_to_string(),
@@ -30,6 +30,25 @@ macro_rules! useful_enum {
             }
         }
 
+        impl $name {
+            /// Looks up a known variant by its name, ignoring case
+            pub fn from_name(name: &str) -> Option<Self> {
+                $(if name.eq_ignore_ascii_case(stringify!($field)) {
+                    return Some(Self::$field);
+                })*
+
+                None
+            }
+
+            /// Name of a known variant
+            pub fn name(&self) -> Option<&'static str> {
+                match self {
+                    $($name::$field => Some(stringify!($field)),)*
+                    $name::$unknown(_) => None,
+                }
+            }
+        }
+
         impl core::fmt::Display for $name {
             fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 match self {
@@ -81,6 +100,9 @@ useful_enum! {
         MX = 15,    // mail exchange
         TXT = 16,   // text strings
         AAAA = 28, // ipv6
+        SRV = 33,  // service locator
+        DNAME = 39, // delegation of a whole subtree (RFC 6672)
+        OPT = 41,  // EDNS(0) pseudo-record
         SVCB = 64,
         HTTPS = 65,
 
@@ -1,9 +1,12 @@
 pub mod parser;
 pub mod parser2;
 pub mod serializer;
+pub mod zone;
 
 mod enums;
+mod rdata;
 mod structs;
 
 pub use enums::*;
+pub use rdata::*;
 pub use structs::*;
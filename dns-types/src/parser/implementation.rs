@@ -1,4 +1,5 @@
 use core::str::Utf8Error;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 use bytes::{Buf, Bytes};
 use thiserror::Error;
@@ -6,7 +7,10 @@ use thiserror::Error;
 use crate::{OpCode, RecordClass, RecordType, ResCode};
 
 use crate::parser::Parsable;
-use crate::{Domain, Header, Message, Question, ResourceRecord};
+use crate::{
+    Caa, Domain, Edns, EdnsOption, Header, Hinfo, Message, Mx, Question, RData, ResourceRecord,
+    Soa, Srv, SvcParam, Svcb,
+};
 
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum ParserError {
@@ -14,6 +18,17 @@ pub enum ParserError {
     InvalidAscii(AsciiError),
     #[error("Not enough bytes, expected {expected:?} bytes, got {recieved:?}")]
     NotEnoughBytes { expected: usize, recieved: usize },
+    #[error(
+        "Compression pointer in the name at {position:?} doesn't point before it (to {pointer:?})"
+    )]
+    ForwardPointer { pointer: usize, position: usize },
+    #[error("Record data for {rtype} had {remaining:?} unused bytes")]
+    TrailingData { rtype: RecordType, remaining: usize },
+    #[error("Invalid record data for {rtype}: {reason}")]
+    InvalidRecordData {
+        rtype: RecordType,
+        reason: &'static str,
+    },
     // Right now there are no unknown errors
     // #[error("Unknown error")]
     // Unknown,
@@ -31,20 +46,20 @@ pub enum AsciiError {
 pub struct BytesBuf {
     original: Bytes,
     pub(crate) in_use: Bytes,
+    /// Offset of the end of the in_use buffer into the original message. Reading only ever
+    /// takes bytes off the front, so this stays put while the position moves up to it
+    end: usize,
 }
 
 impl BytesBuf {
     pub fn new(data: Vec<u8>) -> BytesBuf {
-        let data: Bytes = data.into();
-        BytesBuf {
-            original: data.clone(),
-            in_use: data,
-        }
+        BytesBuf::from_bytes(data.into())
     }
 
     pub fn from_bytes(data: Bytes) -> BytesBuf {
         BytesBuf {
             original: data.clone(),
+            end: data.len(),
             in_use: data,
         }
     }
@@ -58,11 +73,59 @@ impl BytesBuf {
     pub fn take(self) -> Bytes {
         self.in_use
     }
+
+    /// Offset of the in_use buffer into the original message
+    pub(crate) fn position(&self) -> usize {
+        self.end - self.in_use.len()
+    }
+
+    /// Splits the next `len` bytes off into their own buffer, which still
+    /// resolves compression pointers against the original message
+    pub(crate) fn split_to(&mut self, len: usize) -> BytesBuf {
+        let end = self.position() + len;
+        BytesBuf {
+            original: self.original.clone(),
+            in_use: self.in_use.split_to(len),
+            end,
+        }
+    }
+
+    /// Buffer at `ptr` in the original message, for a compression pointer in the name starting
+    /// at `name_start`. Only allowing pointers to before the name also rules out pointer loops
+    pub(crate) fn follow_pointer(
+        &self,
+        ptr: usize,
+        name_start: usize,
+    ) -> Result<BytesBuf, ParserError> {
+        if ptr >= name_start {
+            return Err(ParserError::ForwardPointer {
+                pointer: ptr,
+                position: name_start,
+            });
+        }
+
+        let mut original = BytesBuf::from_bytes(self.get_original());
+        original.in_use.advance(ptr);
+        Ok(original)
+    }
+
+    /// Errors if there are less than `len` bytes left
+    pub(crate) fn expect_remaining(&self, len: usize) -> Result<(), ParserError> {
+        if self.in_use.remaining() < len {
+            return Err(ParserError::NotEnoughBytes {
+                expected: len,
+                recieved: self.in_use.remaining(),
+            });
+        }
+
+        Ok(())
+    }
 }
 
 impl Parsable for Domain {
     type Error = ParserError;
     fn parse(buf: &mut BytesBuf) -> Result<Self, Self::Error> {
+        let start = buf.position();
         let mut result = vec![];
         loop {
             if buf.in_use.remaining() == 0 {
@@ -80,14 +143,14 @@ impl Parsable for Domain {
             }
 
             if (len >> 6) == 0b11 {
+                buf.expect_remaining(1)?;
+
                 let left = len as u16 ^ 0xC0; // 0b11000000
                 let right = buf.in_use.get_u8() as u16;
 
                 let ptr = ((left << 8) + right) as usize;
 
-                let mut original = BytesBuf::from_bytes(buf.get_original());
-                original.in_use.advance(ptr);
-
+                let mut original = buf.follow_pointer(ptr, start)?;
                 let mut compressed = Domain::parse(&mut original)?;
                 result.append(&mut compressed.0);
 
@@ -232,46 +295,296 @@ impl Parsable for ResourceRecord {
             });
         }
 
-        let data = buf.in_use.slice(0..data_len);
+        // Data that doesn't decode the way its type says is still kept, as is
+        let data = buf.split_to(data_len);
+        let data = match RData::parse(rtype, &mut data.clone()) {
+            Ok(data) => data,
+            Err(_) => RData::Unknown(data.take()),
+        };
+
+        Ok(ResourceRecord {
+            name,
+            rtype,
+            rclass,
+            ttl,
+            data,
+        })
+    }
+}
+
+fn parse_character_string(buf: &mut BytesBuf) -> Result<Bytes, ParserError> {
+    buf.expect_remaining(1)?;
+    let len: usize = buf.in_use.get_u8().into();
 
-        let mut after_ptr = None;
+    buf.expect_remaining(len)?;
+    Ok(buf.in_use.split_to(len))
+}
 
-        let domain_data = match rtype {
-            // TODO: deal with the case of a CNAME having more data after the name but the buffer isnt properly advanced
-            // TODO: deal with name.len() >= data.len()
-            // TODO: add tests for this
-            RecordType::NS | RecordType::CNAME => Some(vec![Domain::parse(buf)?]),
+impl RData {
+    /// Parses the data of a `rtype` record, `buf` should only contain the record data
+    pub fn parse(rtype: RecordType, buf: &mut BytesBuf) -> Result<Self, ParserError> {
+        let data = match rtype {
+            RecordType::A => {
+                buf.expect_remaining(4)?;
+                RData::A(Ipv4Addr::from(buf.in_use.get_u32()))
+            }
+            RecordType::AAAA => {
+                buf.expect_remaining(16)?;
+                RData::AAAA(Ipv6Addr::from(buf.in_use.get_u128()))
+            }
+            RecordType::NS => RData::NS(Domain::parse(buf)?),
+            RecordType::CNAME => RData::CNAME(Domain::parse(buf)?),
+            RecordType::DNAME => RData::DNAME(Domain::parse(buf)?),
+            RecordType::PTR => RData::PTR(Domain::parse(buf)?),
             RecordType::MX => {
-                buf.in_use.get_u16();
-                Some(vec![Domain::parse(buf)?])
+                buf.expect_remaining(2)?;
+                let preference = buf.in_use.get_u16();
+
+                RData::MX(Mx {
+                    preference,
+                    exchange: Domain::parse(buf)?,
+                })
             }
             RecordType::SOA => {
-                let ptr = buf.in_use.remaining();
+                let mname = Domain::parse(buf)?;
+                let rname = Domain::parse(buf)?;
+
+                buf.expect_remaining(20)?;
+
+                RData::SOA(Soa {
+                    mname,
+                    rname,
+                    serial: buf.in_use.get_u32(),
+                    refresh: buf.in_use.get_u32(),
+                    retry: buf.in_use.get_u32(),
+                    expire: buf.in_use.get_u32(),
+                    minimum: buf.in_use.get_u32(),
+                })
+            }
+            RecordType::TXT => {
+                // There has to be at least one string, even if it is empty
+                let mut strings = vec![parse_character_string(buf)?];
+                while buf.in_use.has_remaining() {
+                    strings.push(parse_character_string(buf)?);
+                }
 
-                let mut tmp_buf = buf.clone();
+                RData::TXT(strings)
+            }
+            RecordType::HINFO => RData::HINFO(Hinfo {
+                cpu: parse_character_string(buf)?,
+                os: parse_character_string(buf)?,
+            }),
+            RecordType::CAA => {
+                buf.expect_remaining(1)?;
+                let flags = buf.in_use.get_u8();
+
+                let tag = parse_character_string(buf)?;
+                if tag.is_empty() {
+                    return Err(ParserError::InvalidRecordData {
+                        rtype,
+                        reason: "CAA tag can't be empty",
+                    });
+                }
 
-                let domains = vec![Domain::parse(&mut tmp_buf)?, Domain::parse(&mut tmp_buf)?];
+                let tag = match core::str::from_utf8(&tag) {
+                    Ok(tag) if tag.is_ascii() => tag.to_string(),
+                    Ok(_) => return Err(ParserError::InvalidAscii(AsciiError::NotAscii)),
+                    Err(err) => {
+                        return Err(ParserError::InvalidAscii(AsciiError::InvalidUtf8(err)))
+                    }
+                };
 
-                after_ptr = Some(ptr - tmp_buf.in_use.remaining());
+                RData::CAA(Caa {
+                    flags,
+                    tag,
+                    value: buf.in_use.split_to(buf.in_use.len()),
+                })
+            }
+            RecordType::SVCB => RData::SVCB(Svcb::parse(rtype, buf)?),
+            RecordType::HTTPS => RData::HTTPS(Svcb::parse(rtype, buf)?),
+            RecordType::SRV => {
+                buf.expect_remaining(6)?;
+                let priority = buf.in_use.get_u16();
+                let weight = buf.in_use.get_u16();
+                let port = buf.in_use.get_u16();
+
+                RData::SRV(Srv {
+                    priority,
+                    weight,
+                    port,
+                    target: Domain::parse(buf)?,
+                })
+            }
+            _ => RData::Unknown(buf.in_use.split_to(buf.in_use.len())),
+        };
+
+        if buf.in_use.has_remaining() {
+            return Err(ParserError::TrailingData {
+                rtype,
+                remaining: buf.in_use.remaining(),
+            });
+        }
+
+        Ok(data)
+    }
+}
+
+impl Svcb {
+    fn parse(rtype: RecordType, buf: &mut BytesBuf) -> Result<Self, ParserError> {
+        buf.expect_remaining(2)?;
+        let priority = buf.in_use.get_u16();
+
+        let target = Domain::parse(buf)?;
 
-                buf.in_use.advance(data_len);
-                Some(domains)
+        let mut params: Vec<SvcParam> = vec![];
+        while buf.in_use.has_remaining() {
+            buf.expect_remaining(4)?;
+            let key = buf.in_use.get_u16();
+            let len: usize = buf.in_use.get_u16().into();
+
+            if params.last().is_some_and(|last| last.key() >= key) {
+                return Err(ParserError::InvalidRecordData {
+                    rtype,
+                    reason: "SvcParamKeys have to be in strictly increasing order",
+                });
             }
-            _ => {
-                buf.in_use.advance(data_len);
 
-                None
+            buf.expect_remaining(len)?;
+            params.push(SvcParam::parse(rtype, key, buf.split_to(len))?);
+        }
+
+        Ok(Svcb {
+            priority,
+            target,
+            params,
+        })
+    }
+}
+
+impl SvcParam {
+    fn parse(rtype: RecordType, key: u16, mut buf: BytesBuf) -> Result<Self, ParserError> {
+        let invalid = |reason| ParserError::InvalidRecordData { rtype, reason };
+
+        let param = match key {
+            0 => {
+                if buf.in_use.is_empty() || !buf.in_use.len().is_multiple_of(2) {
+                    return Err(invalid("mandatory has to be a non-empty list of keys"));
+                }
+
+                let mut keys = vec![];
+                while buf.in_use.has_remaining() {
+                    keys.push(buf.in_use.get_u16());
+                }
+
+                SvcParam::Mandatory(keys)
             }
+            1 => {
+                let mut ids = vec![];
+                while buf.in_use.has_remaining() {
+                    let id = parse_character_string(&mut buf)?;
+                    if id.is_empty() {
+                        return Err(invalid("alpn ids can't be empty"));
+                    }
+
+                    ids.push(id);
+                }
+
+                if ids.is_empty() {
+                    return Err(invalid("alpn has to have at least one id"));
+                }
+
+                SvcParam::Alpn(ids)
+            }
+            2 => {
+                if !buf.in_use.is_empty() {
+                    return Err(invalid("no-default-alpn can't have a value"));
+                }
+
+                SvcParam::NoDefaultAlpn
+            }
+            3 => {
+                if buf.in_use.len() != 2 {
+                    return Err(invalid("port has to be exactly 2 bytes"));
+                }
+
+                SvcParam::Port(buf.in_use.get_u16())
+            }
+            4 => {
+                if buf.in_use.is_empty() || !buf.in_use.len().is_multiple_of(4) {
+                    return Err(invalid("ipv4hint has to be a non-empty list of addresses"));
+                }
+
+                let mut hints = vec![];
+                while buf.in_use.has_remaining() {
+                    hints.push(Ipv4Addr::from(buf.in_use.get_u32()));
+                }
+
+                SvcParam::Ipv4Hint(hints)
+            }
+            5 => SvcParam::Ech(buf.take()),
+            6 => {
+                if buf.in_use.is_empty() || !buf.in_use.len().is_multiple_of(16) {
+                    return Err(invalid("ipv6hint has to be a non-empty list of addresses"));
+                }
+
+                let mut hints = vec![];
+                while buf.in_use.has_remaining() {
+                    hints.push(Ipv6Addr::from(buf.in_use.get_u128()));
+                }
+
+                SvcParam::Ipv6Hint(hints)
+            }
+            _ => SvcParam::Unknown(key, buf.take()),
         };
 
-        Ok(ResourceRecord {
-            name,
-            rtype,
-            rclass,
-            ttl,
-            data,
-            domain_data,
-            after_ptr,
+        Ok(param)
+    }
+}
+
+impl Edns {
+    /// Reads EDNS information out of an already parsed OPT record
+    pub fn from_record(record: &ResourceRecord) -> Result<Self, ParserError> {
+        let invalid = |reason| ParserError::InvalidRecordData {
+            rtype: RecordType::OPT,
+            reason,
+        };
+
+        if record.rtype != RecordType::OPT {
+            return Err(invalid("Not an OPT record"));
+        }
+
+        if !record.name.0.is_empty() {
+            return Err(invalid("OPT record has to be owned by the root domain"));
+        }
+
+        let RData::Unknown(data) = &record.data else {
+            return Err(invalid("OPT record data wasn't left raw"));
+        };
+
+        let mut buf = BytesBuf::from_bytes(data.clone());
+        let mut options = vec![];
+        while buf.in_use.has_remaining() {
+            buf.expect_remaining(4)?;
+            let code = buf.in_use.get_u16();
+            let len: usize = buf.in_use.get_u16().into();
+
+            buf.expect_remaining(len)?;
+            options.push(EdnsOption {
+                code,
+                data: buf.in_use.split_to(len),
+            });
+        }
+
+        // The TTL field is split up into extended rcode, version and flags
+        let ttl = record.ttl;
+
+        Ok(Edns {
+            udp_payload_size: record.rclass.into(),
+            extended_rcode: (ttl >> 24) as u8,
+            version: (ttl >> 16) as u8,
+            dnssec_ok: (ttl >> 15) & 0b1 == 1,
+            _z: (ttl & 0x7FFF) as u16,
+            options,
         })
     }
 }
@@ -292,6 +605,7 @@ impl Parsable for Message {
                 answers: vec![],
                 authorities: vec![],
                 additional: vec![],
+                edns: None,
             });
         }
 
@@ -311,8 +625,22 @@ impl Parsable for Message {
         }
 
         let mut additional = vec![];
+        let mut edns = None;
         for _ in 0..header.additional_records {
-            additional.push(ResourceRecord::parse(buf)?);
+            let record = ResourceRecord::parse(buf)?;
+
+            if record.rtype == RecordType::OPT {
+                if edns.is_some() {
+                    return Err(ParserError::InvalidRecordData {
+                        rtype: RecordType::OPT,
+                        reason: "There can only be one OPT record",
+                    });
+                }
+
+                edns = Some(Edns::from_record(&record)?);
+            } else {
+                additional.push(record);
+            }
         }
 
         Ok(Message {
@@ -321,6 +649,7 @@ impl Parsable for Message {
             answers,
             authorities,
             additional,
+            edns,
         })
     }
 }
@@ -1,3 +1,5 @@
+use bytes::Buf;
+
 use crate::*;
 use parser::*;
 
@@ -129,3 +131,55 @@ fn invalid_google_decoding() {
         })
     );
 }
+
+#[test]
+fn pointer_to_itself() {
+    // A pointer to itself would be followed forever
+    let mut domain_buf: BytesBuf = BytesBuf::new(vec![0xC0, 0x00]);
+
+    assert_eq!(
+        Domain::parse(&mut domain_buf),
+        Err(ParserError::ForwardPointer {
+            pointer: 0,
+            position: 0
+        })
+    );
+}
+
+#[test]
+fn pointer_loops() {
+    // 1 a, pointer back to the start of the same name
+    let mut domain_buf: BytesBuf = BytesBuf::new(vec![1, 97, 0xC0, 0x00]);
+
+    assert_eq!(
+        Domain::parse(&mut domain_buf),
+        Err(ParserError::ForwardPointer {
+            pointer: 0,
+            position: 0
+        })
+    );
+
+    // 0, then two names pointing at each other
+    let mut domain_buf: BytesBuf = BytesBuf::new(vec![0, 0xC0, 0x03, 0xC0, 0x01]);
+    domain_buf.in_use.advance(1);
+
+    assert_eq!(
+        Domain::parse(&mut domain_buf),
+        Err(ParserError::ForwardPointer {
+            pointer: 3,
+            position: 1
+        })
+    );
+}
+
+#[test]
+fn pointer_to_earlier_name() {
+    // 1 a, 0, then 1 b, pointer to the first name
+    let mut domain_buf: BytesBuf = BytesBuf::new(vec![1, 97, 0, 1, 98, 0xC0, 0x00]);
+    domain_buf.in_use.advance(3);
+
+    assert_eq!(
+        Domain::parse(&mut domain_buf),
+        Ok(Domain(vec!["b".into(), "a".into()]))
+    );
+}
@@ -0,0 +1,90 @@
+use bytes::Bytes;
+
+use crate::*;
+use parser::*;
+
+// Header of a query with a single additional record
+const HEADER: [u8; 12] = [
+    0x00, 0x00, 0x00, 0x00, // id, flags
+    0x00, 0x00, 0x00, 0x00, // no questions or answers
+    0x00, 0x00, 0x00, 0x01, // 1 additional record
+];
+
+#[test]
+fn correct_opt_decoding() {
+    let mut data = HEADER.to_vec();
+    data.extend_from_slice(&[
+        0x00, // domain: `.`
+        0x00, 0x29, // rtype: 41 (OPT)
+        0x04, 0xD0, // udp payload size: 1232
+        0x01, 0x00, // extended rcode: 1, version: 0
+        0x80, 0x00, // DO bit
+        0x00, 0x06, // data len: 6
+        0x00, 0x0A, 0x00, 0x02, // option: 10 (cookie), 2 bytes
+        0xAB, 0xCD, //
+    ]);
+
+    let msg = Message::parse(&mut BytesBuf::new(data)).unwrap();
+
+    assert_eq!(msg.additional, vec![]);
+    assert_eq!(
+        msg.edns,
+        Some(Edns {
+            udp_payload_size: 1232,
+            extended_rcode: 1,
+            version: 0,
+            dnssec_ok: true,
+            _z: 0,
+            options: vec![EdnsOption {
+                code: 10,
+                data: Bytes::from_static(&[0xAB, 0xCD])
+            }]
+        })
+    );
+}
+
+#[test]
+fn duplicate_opt() {
+    let opt = [
+        0x00, // domain: `.`
+        0x00, 0x29, // rtype: 41 (OPT)
+        0x02, 0x00, // udp payload size: 512
+        0x00, 0x00, 0x00, 0x00, // no flags
+        0x00, 0x00, // data len: 0
+    ];
+
+    let mut data = HEADER.to_vec();
+    data[11] = 0x02;
+    data.extend_from_slice(&opt);
+    data.extend_from_slice(&opt);
+
+    assert!(matches!(
+        Message::parse(&mut BytesBuf::new(data)),
+        Err(ParserError::InvalidRecordData {
+            rtype: RecordType::OPT,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn opt_option_too_long() {
+    let mut data = HEADER.to_vec();
+    data.extend_from_slice(&[
+        0x00, // domain: `.`
+        0x00, 0x29, // rtype: 41 (OPT)
+        0x02, 0x00, // udp payload size: 512
+        0x00, 0x00, 0x00, 0x00, // no flags
+        0x00, 0x05, // data len: 5
+        0x00, 0x0A, 0x00, 0x02, // option: 10 (cookie), 2 bytes
+        0xAB, // only 1 byte
+    ]);
+
+    assert_eq!(
+        Message::parse(&mut BytesBuf::new(data)),
+        Err(ParserError::NotEnoughBytes {
+            expected: 2,
+            recieved: 1
+        })
+    );
+}
@@ -35,9 +35,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -49,9 +47,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -64,9 +60,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -80,9 +74,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -97,9 +89,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -115,9 +105,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -134,9 +122,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -154,9 +140,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -175,9 +159,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -197,9 +179,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -220,9 +200,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -244,9 +222,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -269,9 +245,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -295,9 +269,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -322,9 +294,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -350,9 +320,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -379,9 +347,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -409,9 +375,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -440,9 +404,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -472,9 +434,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -505,9 +465,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -539,9 +497,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -574,9 +530,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -610,9 +564,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -647,9 +599,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -685,9 +635,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -724,9 +672,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -764,9 +710,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -805,9 +749,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -847,9 +789,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -890,9 +830,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -934,9 +872,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -979,9 +915,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -1025,9 +959,7 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
             ResourceRecord {
                 name: Domain(vec![
@@ -1072,13 +1004,12 @@ fn test_decode_manypointers() {
                 rtype: RecordType::ANY,
                 rclass: RecordClass::Unknown(254),
                 ttl: 0,
-                data: Bytes::new(),
-                domain_data: None,
-                after_ptr: None,
+                data: RData::Unknown(Bytes::new()),
             },
         ],
         authorities: vec![],
         additional: vec![],
+        edns: None,
     };
 
     assert_eq!(message, expected)
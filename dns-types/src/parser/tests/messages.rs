@@ -34,12 +34,53 @@ fn get_shared_test_case_data() -> Message {
                 rtype: RecordType::TXT,
                 rclass: RecordClass::IN,
                 ttl: 1337,
-                data: Bytes::copy_from_slice(
+                // Not valid character-strings, so it's kept as is
+                data: RData::Unknown(Bytes::copy_from_slice(
                     b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
-                ), domain_data: None, after_ptr: None
+                )),
             }
         ],
-        additional: vec![]
+        additional: vec![],
+        edns: None
+    }
+}
+
+/// A well formed TXT record, which gets decoded
+fn get_txt_test_case_data() -> Message {
+    Message {
+        header: Header {
+            id: 0,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: false,
+            is_truncated: false,
+            should_recurse: false,
+            recursion_available: false,
+            _z: 0,
+            rescode: ResCode::NoError,
+            questions: 1,
+            answer_records: 1,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: vec![Question {
+            name: Domain(vec!["se".into()]),
+            qtype: RecordType::TXT,
+            qclass: RecordClass::IN,
+        }],
+        answers: vec![ResourceRecord {
+            name: Domain(vec!["se".into()]),
+            rtype: RecordType::TXT,
+            rclass: RecordClass::IN,
+            ttl: 1337,
+            data: RData::TXT(vec![
+                Bytes::from_static(b"hello"),
+                Bytes::from_static(b"world"),
+            ]),
+        }],
+        authorities: vec![],
+        additional: vec![],
+        edns: None,
     }
 }
 
@@ -60,3 +101,61 @@ fn test_decode_nopointers() {
 
     assert_eq!(message, get_shared_test_case_data())
 }
+
+#[test]
+fn test_decode_txt() {
+    let bytes = std::fs::read("tests/test-txt-pointers.bin").unwrap();
+    let mut data = BytesBuf::from_bytes(Bytes::copy_from_slice(&bytes));
+    let message = Message::parse(&mut data).unwrap();
+
+    assert_eq!(message, get_txt_test_case_data())
+}
+
+#[test]
+fn test_decode_txt_nopointers() {
+    let bytes = std::fs::read("tests/test-txt-nopointers.bin").unwrap();
+    let mut data = BytesBuf::from_bytes(Bytes::copy_from_slice(&bytes));
+    let message = Message::parse(&mut data).unwrap();
+
+    assert_eq!(message, get_txt_test_case_data())
+}
+
+#[test]
+fn missing_record_after_last_rdata() {
+    // Says there are 2 answers, the only one ends the message
+    let mut data = BytesBuf::new(vec![
+        0x00, 0x01, 0x81, 0x80, // id: 1, response, no error
+        0x00, 0x00, 0x00, 0x02, // no questions, 2 answers
+        0x00, 0x00, 0x00, 0x00, //
+        0x00, // domain: `.`
+        0x00, 0x01, 0x00, 0x01, // A IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x04, // data len: 4
+        192, 0, 2, 1, // data: 192.0.2.1
+    ]);
+
+    assert_eq!(
+        Message::parse(&mut data),
+        Err(ParserError::NotEnoughBytes {
+            expected: 1,
+            recieved: 0
+        })
+    );
+}
+
+#[test]
+fn empty_domain_rdata() {
+    let mut data = BytesBuf::new(vec![
+        0x00, 0x01, 0x81, 0x80, // id: 1, response, no error
+        0x00, 0x00, 0x00, 0x01, // no questions, 1 answer
+        0x00, 0x00, 0x00, 0x00, //
+        0x00, // domain: `.`
+        0x00, 0x02, 0x00, 0x01, // NS IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x00, // data len: 0
+    ]);
+
+    // No room for the name, so it's kept as is
+    let message = Message::parse(&mut data).unwrap();
+    assert_eq!(message.answers[0].data, RData::Unknown(Bytes::new()));
+}
@@ -1,7 +1,9 @@
 mod domain;
+mod edns;
 mod enums;
 mod header;
 mod many_pointers; // Such a big test case it gets its own file
 mod messages;
 mod questions;
+mod rdata;
 mod records;
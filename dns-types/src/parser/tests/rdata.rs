@@ -0,0 +1,239 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+
+use crate::*;
+use parser::*;
+
+#[test]
+fn a_wrong_length() {
+    // A records have to be exactly 4 bytes
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![127, 0, 0]);
+
+    assert_eq!(
+        RData::parse(RecordType::A, &mut data_buf),
+        Err(ParserError::NotEnoughBytes {
+            expected: 4,
+            recieved: 3
+        })
+    );
+
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![127, 0, 0, 1, 1]);
+
+    assert_eq!(
+        RData::parse(RecordType::A, &mut data_buf),
+        Err(ParserError::TrailingData {
+            rtype: RecordType::A,
+            remaining: 1
+        })
+    );
+}
+
+#[test]
+fn correct_aaaa_decoding() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    ]);
+
+    assert_eq!(
+        RData::parse(RecordType::AAAA, &mut data_buf),
+        Ok(RData::AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))
+    );
+}
+
+#[test]
+fn correct_mx_decoding() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, 0x0A, // preference: 10
+        0x04, 109, 97, 105, 108, // mail
+        0x02, 115, 101, // se
+        0x00,
+    ]);
+
+    assert_eq!(
+        RData::parse(RecordType::MX, &mut data_buf),
+        Ok(RData::MX(Mx {
+            preference: 10,
+            exchange: Domain(vec!["mail".into(), "se".into()])
+        }))
+    );
+}
+
+#[test]
+fn correct_soa_decoding() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x02, 110, 115, 0x00, // ns.
+        0x04, 104, 111, 115, 116, 0x00, // host.
+        0x00, 0x00, 0x00, 0x01, // serial: 1
+        0x00, 0x00, 0x00, 0x02, // refresh: 2
+        0x00, 0x00, 0x00, 0x03, // retry: 3
+        0x00, 0x00, 0x00, 0x04, // expire: 4
+        0x00, 0x00, 0x00, 0x05, // minimum: 5
+    ]);
+
+    assert_eq!(
+        RData::parse(RecordType::SOA, &mut data_buf),
+        Ok(RData::SOA(Soa {
+            mname: Domain(vec!["ns".into()]),
+            rname: Domain(vec!["host".into()]),
+            serial: 1,
+            refresh: 2,
+            retry: 3,
+            expire: 4,
+            minimum: 5,
+        }))
+    );
+}
+
+#[test]
+fn correct_txt_decoding() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x02, 104, 105,  // hi
+        0x00, // empty string
+        0x03, 121, 111, 117, // you
+    ]);
+
+    assert_eq!(
+        RData::parse(RecordType::TXT, &mut data_buf),
+        Ok(RData::TXT(vec![
+            Bytes::from_static(b"hi"),
+            Bytes::new(),
+            Bytes::from_static(b"you")
+        ]))
+    );
+}
+
+#[test]
+fn txt_string_too_long() {
+    // Claims to have 5 bytes but only has 2
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![0x05, 104, 105]);
+
+    assert_eq!(
+        RData::parse(RecordType::TXT, &mut data_buf),
+        Err(ParserError::NotEnoughBytes {
+            expected: 5,
+            recieved: 2
+        })
+    );
+}
+
+#[test]
+fn correct_caa_decoding() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x80, // flags: critical
+        0x05, 105, 115, 115, 117, 101, // issue
+        108, 101, 46, 111, 114, 103, // le.org
+    ]);
+
+    let data = RData::parse(RecordType::CAA, &mut data_buf);
+
+    assert_eq!(
+        data,
+        Ok(RData::CAA(Caa {
+            flags: 0x80,
+            tag: "issue".into(),
+            value: Bytes::from_static(b"le.org")
+        }))
+    );
+
+    if let Ok(RData::CAA(caa)) = data {
+        assert!(caa.is_critical());
+    }
+}
+
+#[test]
+fn correct_svcb_decoding() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, 0x01, // priority: 1
+        0x00, // target: `.`
+        0x00, 0x01, 0x00, 0x03, // alpn, 3 bytes
+        0x02, 104, 50, // h2
+        0x00, 0x03, 0x00, 0x02, // port, 2 bytes
+        0x01, 0xBB, // 443
+        0x00, 0x04, 0x00, 0x04, // ipv4hint, 4 bytes
+        0x01, 0x01, 0x01, 0x01, // 1.1.1.1
+        0x04, 0x00, 0x00, 0x01, // key1024, 1 byte
+        0xFF,
+    ]);
+
+    assert_eq!(
+        RData::parse(RecordType::HTTPS, &mut data_buf),
+        Ok(RData::HTTPS(Svcb {
+            priority: 1,
+            target: Domain(vec![]),
+            params: vec![
+                SvcParam::Alpn(vec![Bytes::from_static(b"h2")]),
+                SvcParam::Port(443),
+                SvcParam::Ipv4Hint(vec![Ipv4Addr::new(1, 1, 1, 1)]),
+                SvcParam::Unknown(1024, Bytes::from_static(&[0xFF])),
+            ]
+        }))
+    );
+}
+
+#[test]
+fn svcb_unsorted_keys() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, 0x01, // priority: 1
+        0x00, // target: `.`
+        0x00, 0x03, 0x00, 0x02, // port, 2 bytes
+        0x01, 0xBB, // 443
+        0x00, 0x02, 0x00, 0x00, // no-default-alpn
+    ]);
+
+    assert!(matches!(
+        RData::parse(RecordType::SVCB, &mut data_buf),
+        Err(ParserError::InvalidRecordData {
+            rtype: RecordType::SVCB,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn correct_srv_decoding() {
+    let mut data_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, 0x01, // priority: 1
+        0x00, 0x02, // weight: 2
+        0x14, 0x66, // port: 5222
+        0x02, 120, 99, 0x00, // xc.
+    ]);
+
+    assert_eq!(
+        RData::parse(RecordType::SRV, &mut data_buf),
+        Ok(RData::SRV(Srv {
+            priority: 1,
+            weight: 2,
+            port: 5222,
+            target: Domain(vec!["xc".into()])
+        }))
+    );
+}
+
+#[test]
+fn compressed_cname_decoding() {
+    // `se.` at the start of the message, followed by a CNAME record pointing at `a.se.`
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x02, 115, 101, 0x00, // se.
+        0xC0, 0x00, // domain: pointer to `se.`
+        0x00, 0x05, // rtype: 5 (CNAME)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x04, // data len: 4
+        0x01, 97, 0xC0, 0x00, // a + pointer to `se.`
+    ]);
+    Domain::parse(&mut record_buf).unwrap();
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        Ok(ResourceRecord {
+            name: Domain(vec!["se".into()]),
+            rtype: RecordType::CNAME,
+            rclass: RecordClass::IN,
+            ttl: 0,
+            data: RData::CNAME(Domain(vec!["a".into(), "se".into()])),
+        })
+    );
+}
@@ -1,3 +1,5 @@
+use std::net::Ipv4Addr;
+
 use bytes::Bytes;
 
 use crate::*;
@@ -77,9 +79,7 @@ fn no_record_data() {
             rtype: RecordType::Unknown(0),
             rclass: RecordClass::Unknown(0),
             ttl: 0,
-            data: Bytes::new(),
-            domain_data: None,
-            after_ptr: None
+            data: RData::Unknown(Bytes::new()),
         })
     );
 }
@@ -104,9 +104,7 @@ fn correct_record_data() {
             rtype: RecordType::Unknown(0),
             rclass: RecordClass::Unknown(0),
             ttl: 0,
-            data: Bytes::from_static(&[0x13, 0x37, 0x13, 0x37]),
-            domain_data: None,
-            after_ptr: None
+            data: RData::Unknown(Bytes::from_static(&[0x13, 0x37, 0x13, 0x37])),
         })
     );
 }
@@ -136,9 +134,32 @@ fn correct_total_decoding() {
             rtype: RecordType::A,
             rclass: RecordClass::IN,
             ttl: 0xDEADBEEF,
-            data: Bytes::from_static(&[0xBA, 0xAA, 0xAA, 0xAD]),
-            domain_data: None,
-            after_ptr: None
+            data: RData::A(Ipv4Addr::new(0xBA, 0xAA, 0xAA, 0xAD)),
+        })
+    );
+}
+
+#[test]
+fn undecodable_record_data() {
+    // An A record that's too short is kept as is
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, // domain: `.`
+        0x00, 0x01, // rtype: 1 (A)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x03, // data len: 3
+        0x7F, 0x00, 0x00,
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        Ok(ResourceRecord {
+            name: Domain(vec![]),
+            rtype: RecordType::A,
+            rclass: RecordClass::IN,
+            ttl: 0,
+            data: RData::Unknown(Bytes::from_static(&[0x7F, 0x00, 0x00])),
         })
     );
 }
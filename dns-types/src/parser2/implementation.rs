@@ -5,8 +5,9 @@ use crate::parser::{AsciiError, BytesBuf, ParserError};
 use crate::{OpCode, RecordClass, RecordType, ResCode};
 
 use super::{Parsable, PartialResult};
-use crate::{Domain, Header, Message, Question, ResourceRecord};
+use crate::{Domain, Edns, Header, Message, Mx, Question, RData, ResourceRecord, Soa, Srv};
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum DomainErrorLocation {
     NameLengthTag,
     CompressedTag,
@@ -18,6 +19,7 @@ impl Parsable for Domain {
     type ErrorLocation = DomainErrorLocation;
 
     fn parse(buf: &mut BytesBuf) -> PartialResult<Self, Self::ErrorLocation, Self::Error> {
+        let start = buf.position();
         let mut result = vec![];
         loop {
             if buf.in_use.remaining() == 0 {
@@ -44,13 +46,32 @@ impl Parsable for Domain {
             }
 
             if (len >> 6) == 0b11 {
-                let left = len as u16 ^ 0xC0; // 0b11000000
-                let right = buf.in_use.get_u8() as u16;
+                let original = if buf.in_use.remaining() == 0 {
+                    Err(ParserError::NotEnoughBytes {
+                        expected: 1,
+                        recieved: 0,
+                    })
+                } else {
+                    let left = len as u16 ^ 0xC0; // 0b11000000
+                    let right = buf.in_use.get_u8() as u16;
 
-                let ptr = ((left << 8) + right) as usize;
+                    buf.follow_pointer(((left << 8) + right) as usize, start)
+                };
 
-                let mut original = BytesBuf::from_bytes(buf.get_original());
-                original.in_use.advance(ptr);
+                let mut original = match original {
+                    Ok(original) => original,
+                    Err(err) => {
+                        if result.is_empty() {
+                            return PartialResult::FullErr(err);
+                        } else {
+                            return PartialResult::PartialOk(
+                                Domain(result),
+                                DomainErrorLocation::CompressedTag,
+                                err,
+                            );
+                        }
+                    }
+                };
 
                 let res = Domain::parse(&mut original);
 
@@ -170,6 +191,7 @@ impl Parsable for Header {
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum QuestionErrorLocation {
     AfterDomain,
 }
@@ -218,9 +240,11 @@ impl Parsable for Question {
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum ResourceRecordErrorLocation {
     AfterDomain,
     DataLength,
+    RecordData(RDataErrorLocation),
 }
 
 impl Parsable for ResourceRecord {
@@ -248,9 +272,7 @@ impl Parsable for ResourceRecord {
                     rtype: RecordType::Unknown(0),
                     rclass: RecordClass::Unknown(0),
                     ttl: 0,
-                    data: Bytes::new(),
-                    domain_data: None,
-                    after_ptr: None,
+                    data: RData::Unknown(Bytes::new()),
                 },
                 ResourceRecordErrorLocation::AfterDomain,
                 ParserError::NotEnoughBytes {
@@ -273,9 +295,7 @@ impl Parsable for ResourceRecord {
                     rtype,
                     rclass,
                     ttl,
-                    data: Bytes::new(),
-                    domain_data: None,
-                    after_ptr: None,
+                    data: RData::Unknown(Bytes::new()),
                 },
                 ResourceRecordErrorLocation::DataLength,
                 ParserError::NotEnoughBytes {
@@ -285,38 +305,35 @@ impl Parsable for ResourceRecord {
             );
         }
 
-        let data = buf.in_use.slice(0..data_len);
-
-        // TODO: support these again
-        let after_ptr = None;
-        let domain_data = None;
-        // match rtype {
-        //     // TODO: deal with the case of a CNAME having more data after the name but the buffer isnt properly advanced
-        //     // TODO: deal with name.len() >= data.len()
-        //     // TODO: add tests for this
-        //     RecordType::NS | RecordType::CNAME => Some(vec![Domain::parse(buf)?]),
-        //     RecordType::MX => {
-        //         buf.in_use.get_u16();
-        //         Some(vec![Domain::parse(buf)?])
-        //     }
-        //     RecordType::SOA => {
-        //         let ptr = buf.in_use.remaining();
-
-        //         let mut tmp_buf = buf.clone();
-
-        //         let domains = vec![Domain::parse(&mut tmp_buf)?, Domain::parse(&mut tmp_buf)?];
-
-        //         after_ptr = Some(ptr - tmp_buf.in_use.remaining());
-
-        //         buf.in_use.advance(data_len);
-        //         Some(domains)
-        //     }
-        //     _ => {
-        //         buf.in_use.advance(data_len);
-
-        //         None
-        //     }
-        // };
+        let data = match parse_rdata(rtype, &mut buf.split_to(data_len)) {
+            PartialResult::FullOk(data) => data,
+            PartialResult::PartialOk(data, location, err) => {
+                return PartialResult::PartialOk(
+                    ResourceRecord {
+                        name,
+                        rtype,
+                        rclass,
+                        ttl,
+                        data,
+                    },
+                    ResourceRecordErrorLocation::RecordData(location),
+                    err,
+                );
+            }
+            PartialResult::FullErr(err) => {
+                return PartialResult::PartialOk(
+                    ResourceRecord {
+                        name,
+                        rtype,
+                        rclass,
+                        ttl,
+                        data: RData::Unknown(Bytes::new()),
+                    },
+                    ResourceRecordErrorLocation::RecordData(RDataErrorLocation::Data),
+                    err,
+                );
+            }
+        };
 
         PartialResult::FullOk(ResourceRecord {
             name,
@@ -324,12 +341,121 @@ impl Parsable for ResourceRecord {
             rclass,
             ttl,
             data,
-            domain_data,
-            after_ptr,
         })
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum RDataErrorLocation {
+    /// Failed on the nth domain name in the data
+    Domain(usize),
+    /// Failed on anything other than a domain name
+    Data,
+    /// Everything parsed, but there were bytes left over
+    TrailingData,
+}
+
+fn parse_rdata_domain(
+    buf: &mut BytesBuf,
+    index: usize,
+) -> Result<Domain, (RDataErrorLocation, ParserError)> {
+    match Domain::parse(buf) {
+        PartialResult::FullErr(err) | PartialResult::PartialOk(_, _, err) => {
+            Err((RDataErrorLocation::Domain(index), err))
+        }
+        PartialResult::FullOk(value) => Ok(value),
+    }
+}
+
+fn parse_rdata_inner(
+    rtype: RecordType,
+    buf: &mut BytesBuf,
+) -> Result<RData, (RDataErrorLocation, ParserError)> {
+    let fields = |buf: &BytesBuf, len| {
+        buf.expect_remaining(len)
+            .map_err(|err| (RDataErrorLocation::Data, err))
+    };
+
+    let data = match rtype {
+        RecordType::NS => RData::NS(parse_rdata_domain(buf, 0)?),
+        RecordType::CNAME => RData::CNAME(parse_rdata_domain(buf, 0)?),
+        RecordType::DNAME => RData::DNAME(parse_rdata_domain(buf, 0)?),
+        RecordType::PTR => RData::PTR(parse_rdata_domain(buf, 0)?),
+        RecordType::MX => {
+            fields(buf, 2)?;
+            let preference = buf.in_use.get_u16();
+
+            RData::MX(Mx {
+                preference,
+                exchange: parse_rdata_domain(buf, 0)?,
+            })
+        }
+        RecordType::SOA => {
+            let mname = parse_rdata_domain(buf, 0)?;
+            let rname = parse_rdata_domain(buf, 1)?;
+
+            fields(buf, 20)?;
+
+            RData::SOA(Soa {
+                mname,
+                rname,
+                serial: buf.in_use.get_u32(),
+                refresh: buf.in_use.get_u32(),
+                retry: buf.in_use.get_u32(),
+                expire: buf.in_use.get_u32(),
+                minimum: buf.in_use.get_u32(),
+            })
+        }
+        RecordType::SRV => {
+            fields(buf, 6)?;
+            let priority = buf.in_use.get_u16();
+            let weight = buf.in_use.get_u16();
+            let port = buf.in_use.get_u16();
+
+            RData::SRV(Srv {
+                priority,
+                weight,
+                port,
+                target: parse_rdata_domain(buf, 0)?,
+            })
+        }
+        // Nothing else can contain compressed domains
+        _ => {
+            return RData::parse(rtype, buf).map_err(|err| match err {
+                ParserError::TrailingData { .. } => (RDataErrorLocation::TrailingData, err),
+                _ => (RDataErrorLocation::Data, err),
+            })
+        }
+    };
+
+    if buf.in_use.has_remaining() {
+        return Err((
+            RDataErrorLocation::TrailingData,
+            ParserError::TrailingData {
+                rtype,
+                remaining: buf.in_use.remaining(),
+            },
+        ));
+    }
+
+    Ok(data)
+}
+
+/// Parses the data of a `rtype` record, `buf` should only contain the record data.
+/// If the data is malformed the raw bytes are given back as `RData::Unknown`
+pub fn parse_rdata(
+    rtype: RecordType,
+    buf: &mut BytesBuf,
+) -> PartialResult<RData, RDataErrorLocation, ParserError> {
+    let raw = buf.in_use.clone();
+
+    match parse_rdata_inner(rtype, buf) {
+        Ok(data) => PartialResult::FullOk(data),
+        Err((location, err)) => PartialResult::PartialOk(RData::Unknown(raw), location, err),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum MessageErrorLocation {
     HeaderError,
     QuestionError,
@@ -367,6 +493,7 @@ impl Parsable for Message {
                             answers: vec![],
                             authorities: vec![],
                             additional: vec![],
+                            edns: None,
                         },
                         MessageErrorLocation::QuestionError,
                         err,
@@ -387,6 +514,7 @@ impl Parsable for Message {
                             answers,
                             authorities: vec![],
                             additional: vec![],
+                            edns: None,
                         },
                         MessageErrorLocation::AnswerError,
                         err,
@@ -407,6 +535,7 @@ impl Parsable for Message {
                             answers,
                             authorities,
                             additional: vec![],
+                            edns: None,
                         },
                         MessageErrorLocation::AuthorityError,
                         err,
@@ -417,8 +546,9 @@ impl Parsable for Message {
         }
 
         let mut additional = vec![];
+        let mut edns = None;
         for _ in 0..header.additional_records {
-            match ResourceRecord::parse(buf) {
+            let record = match ResourceRecord::parse(buf) {
                 PartialResult::FullErr(err) | PartialResult::PartialOk(_, _, err) => {
                     return PartialResult::PartialOk(
                         Message {
@@ -427,13 +557,46 @@ impl Parsable for Message {
                             answers,
                             authorities,
                             additional,
+                            edns,
                         },
                         MessageErrorLocation::AdditionalError,
                         err,
                     )
                 }
-                PartialResult::FullOk(value) => additional.push(value),
+                PartialResult::FullOk(value) => value,
             };
+
+            if record.rtype != RecordType::OPT {
+                additional.push(record);
+                continue;
+            }
+
+            let parsed = if edns.is_some() {
+                Err(ParserError::InvalidRecordData {
+                    rtype: RecordType::OPT,
+                    reason: "There can only be one OPT record",
+                })
+            } else {
+                Edns::from_record(&record)
+            };
+
+            match parsed {
+                Ok(value) => edns = Some(value),
+                Err(err) => {
+                    return PartialResult::PartialOk(
+                        Message {
+                            header,
+                            questions,
+                            answers,
+                            authorities,
+                            additional,
+                            edns,
+                        },
+                        MessageErrorLocation::AdditionalError,
+                        err,
+                    )
+                }
+            }
         }
 
         PartialResult::FullOk(Message {
@@ -442,6 +605,7 @@ impl Parsable for Message {
             answers,
             authorities,
             additional,
+            edns,
         })
     }
 }
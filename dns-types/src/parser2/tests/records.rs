@@ -0,0 +1,357 @@
+use std::net::Ipv4Addr;
+
+use bytes::{Buf, Bytes};
+
+use crate::parser::{BytesBuf, ParserError};
+use crate::*;
+use parser2::*;
+
+#[test]
+fn no_data() {
+    // Buf has 1 0x00 so we don't catch the domain NEB error, but the resource record NEB error
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![0x00]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::PartialOk(
+            ResourceRecord {
+                name: Domain(vec![]),
+                rtype: RecordType::Unknown(0),
+                rclass: RecordClass::Unknown(0),
+                ttl: 0,
+                data: RData::Unknown(Bytes::new()),
+            },
+            ResourceRecordErrorLocation::AfterDomain,
+            ParserError::NotEnoughBytes {
+                expected: 8,
+                recieved: 0
+            }
+        )
+    );
+}
+
+#[test]
+fn not_enough_record_data() {
+    // Claims to have 13 bytes of data but has nothing
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, // domain: `.`
+        0x00, 0x00, // rtype: 0
+        0x00, 0x00, // rclass: 0
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x0D, // data len: 13
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::PartialOk(
+            ResourceRecord {
+                name: Domain(vec![]),
+                rtype: RecordType::Unknown(0),
+                rclass: RecordClass::Unknown(0),
+                ttl: 0,
+                data: RData::Unknown(Bytes::new()),
+            },
+            ResourceRecordErrorLocation::DataLength,
+            ParserError::NotEnoughBytes {
+                expected: 13,
+                recieved: 0
+            }
+        )
+    );
+}
+
+#[test]
+fn correct_total_decoding() {
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x03, // length: 3
+        119, 119, 119,  // www
+        0x08, // length 8
+        104, 97, 99, 107, 99, 108, 117, 98,   // hackclub
+        0x03, // length: 3
+        99, 111, 109,  // com
+        0x00, // length 0 - end of domain
+        0x00, 0x01, // rtype: 1 (A)
+        0x00, 0x01, // rclass: 1 (IN)
+        0xDE, 0xAD, // ttl: 0xDEADBEEF
+        0xBE, 0xEF, //
+        0x00, 0x04, // data len: 4
+        0xBA, 0xAA, 0xAA, 0xAD, // data: 0xBAAAAAAD
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::FullOk(ResourceRecord {
+            name: Domain(vec!["www".into(), "hackclub".into(), "com".into()]),
+            rtype: RecordType::A,
+            rclass: RecordClass::IN,
+            ttl: 0xDEADBEEF,
+            data: RData::A(Ipv4Addr::new(0xBA, 0xAA, 0xAA, 0xAD)),
+        })
+    );
+}
+
+#[test]
+fn consecutive_records() {
+    // Two NS records, the data of the first shouldn't be read as part of the second
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x02, 115, 101, 0x00, // se.
+        0x00, 0x02, // rtype: 2 (NS)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x04, // data len: 4
+        0x01, 97, 0xC0, 0x00, // a + pointer to `se.`
+        0xC0, 0x00, // domain: pointer to `se.`
+        0x00, 0x02, // rtype: 2 (NS)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x04, // data len: 4
+        0x01, 98, 0xC0, 0x00, // b + pointer to `se.`
+    ]);
+
+    for ns in ["a", "b"] {
+        assert_eq!(
+            ResourceRecord::parse(&mut record_buf),
+            PartialResult::FullOk(ResourceRecord {
+                name: Domain(vec!["se".into()]),
+                rtype: RecordType::NS,
+                rclass: RecordClass::IN,
+                ttl: 0,
+                data: RData::NS(Domain(vec![ns.into(), "se".into()])),
+            })
+        );
+    }
+}
+
+#[test]
+fn compressed_soa_decoding() {
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x02, 115, 101, 0x00, // se.
+        0xC0, 0x00, // domain: pointer to `se.`
+        0x00, 0x06, // rtype: 6 (SOA)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x1A, // data len: 26
+        0x01, 97, 0xC0, 0x00, // mname: a + pointer to `se.`
+        0xC0, 0x00, // rname: pointer to `se.`
+        0x00, 0x00, 0x00, 0x01, // serial: 1
+        0x00, 0x00, 0x00, 0x02, // refresh: 2
+        0x00, 0x00, 0x00, 0x03, // retry: 3
+        0x00, 0x00, 0x00, 0x04, // expire: 4
+        0x00, 0x00, 0x00, 0x05, // minimum: 5
+    ]);
+    record_buf.in_use.advance(4);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::FullOk(ResourceRecord {
+            name: Domain(vec!["se".into()]),
+            rtype: RecordType::SOA,
+            rclass: RecordClass::IN,
+            ttl: 0,
+            data: RData::SOA(Soa {
+                mname: Domain(vec!["a".into(), "se".into()]),
+                rname: Domain(vec!["se".into()]),
+                serial: 1,
+                refresh: 2,
+                retry: 3,
+                expire: 4,
+                minimum: 5,
+            }),
+        })
+    );
+}
+
+#[test]
+fn soa_missing_fields() {
+    // SOA where the 20 bytes of numbers are missing
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, // domain: `.`
+        0x00, 0x06, // rtype: 6 (SOA)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x02, // data len: 2
+        0x00, 0x00, // mname: `.`, rname: `.`
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::PartialOk(
+            ResourceRecord {
+                name: Domain(vec![]),
+                rtype: RecordType::SOA,
+                rclass: RecordClass::IN,
+                ttl: 0,
+                data: RData::Unknown(Bytes::from_static(&[0x00, 0x00])),
+            },
+            ResourceRecordErrorLocation::RecordData(RDataErrorLocation::Data),
+            ParserError::NotEnoughBytes {
+                expected: 20,
+                recieved: 0
+            }
+        )
+    );
+}
+
+#[test]
+fn mx_name_out_of_bounds() {
+    // The exchange name claims to be longer than the record data, even though
+    // the bytes after the record would make it valid
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, // domain: `.`
+        0x00, 0x0F, // rtype: 15 (MX)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x04, // data len: 4
+        0x00, 0x0A, // preference: 10
+        0x02, 115, // s
+        101, 0x00, // e + end of domain, outside of the record data
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::PartialOk(
+            ResourceRecord {
+                name: Domain(vec![]),
+                rtype: RecordType::MX,
+                rclass: RecordClass::IN,
+                ttl: 0,
+                data: RData::Unknown(Bytes::from_static(&[0x00, 0x0A, 0x02, 115])),
+            },
+            ResourceRecordErrorLocation::RecordData(RDataErrorLocation::Domain(0)),
+            ParserError::NotEnoughBytes {
+                expected: 2,
+                recieved: 1
+            }
+        )
+    );
+}
+
+#[test]
+fn cname_trailing_data() {
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, // domain: `.`
+        0x00, 0x05, // rtype: 5 (CNAME)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x02, // data len: 2
+        0x00, // `.`
+        0xFF, // extra byte
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::PartialOk(
+            ResourceRecord {
+                name: Domain(vec![]),
+                rtype: RecordType::CNAME,
+                rclass: RecordClass::IN,
+                ttl: 0,
+                data: RData::Unknown(Bytes::from_static(&[0x00, 0xFF])),
+            },
+            ResourceRecordErrorLocation::RecordData(RDataErrorLocation::TrailingData),
+            ParserError::TrailingData {
+                rtype: RecordType::CNAME,
+                remaining: 1
+            }
+        )
+    );
+}
+
+#[test]
+fn ns_pointer_loop() {
+    // Pointer pointing at itself
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, // domain: `.`
+        0x00, 0x02, // rtype: 2 (NS)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x02, // data len: 2
+        0xC0, 0x0B, // pointer to 11
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::PartialOk(
+            ResourceRecord {
+                name: Domain(vec![]),
+                rtype: RecordType::NS,
+                rclass: RecordClass::IN,
+                ttl: 0,
+                data: RData::Unknown(Bytes::from_static(&[0xC0, 0x0B])),
+            },
+            ResourceRecordErrorLocation::RecordData(RDataErrorLocation::Domain(0)),
+            ParserError::ForwardPointer {
+                pointer: 11,
+                position: 11
+            }
+        )
+    );
+}
+
+#[test]
+fn ns_pointer_to_own_name() {
+    // Pointer back to the start of the name it's in
+    let mut record_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, // domain: `.`
+        0x00, 0x02, // rtype: 2 (NS)
+        0x00, 0x01, // rclass: 1 (IN)
+        0x00, 0x00, // ttl: 0
+        0x00, 0x00, //
+        0x00, 0x04, // data len: 4
+        0x01, 0x61, // a
+        0xC0, 0x0B, // pointer to 11
+    ]);
+
+    assert_eq!(
+        ResourceRecord::parse(&mut record_buf),
+        PartialResult::PartialOk(
+            ResourceRecord {
+                name: Domain(vec![]),
+                rtype: RecordType::NS,
+                rclass: RecordClass::IN,
+                ttl: 0,
+                data: RData::Unknown(Bytes::from_static(&[0x01, 0x61, 0xC0, 0x0B])),
+            },
+            ResourceRecordErrorLocation::RecordData(RDataErrorLocation::Domain(0)),
+            ParserError::ForwardPointer {
+                pointer: 11,
+                position: 11
+            }
+        )
+    );
+}
+
+#[test]
+fn missing_record_after_last_rdata() {
+    // Says there are 2 answers, the only one ends the message
+    let mut message_buf: BytesBuf = BytesBuf::new(vec![
+        0x00, 0x01, 0x81, 0x80, // id: 1, response, no error
+        0x00, 0x00, 0x00, 0x02, // no questions, 2 answers
+        0x00, 0x00, 0x00, 0x00, //
+        0x00, // domain: `.`
+        0x00, 0x01, 0x00, 0x01, // A IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x04, // data len: 4
+        192, 0, 2, 1, // data: 192.0.2.1
+    ]);
+
+    assert!(matches!(
+        Message::parse(&mut message_buf),
+        PartialResult::PartialOk(
+            _,
+            _,
+            ParserError::NotEnoughBytes {
+                expected: 1,
+                recieved: 0
+            }
+        )
+    ));
+}
@@ -1,5 +1,6 @@
 use crate::parser::BytesBuf;
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum PartialResult<T, S, E> {
     FullErr(E),
     PartialOk(T, S, E),
@@ -0,0 +1,136 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+
+use crate::Domain;
+
+/// MX record data
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mx {
+    /// Lower values are preferred
+    pub preference: u16,
+    /// Host willing to act as a mail exchange
+    pub exchange: Domain,
+}
+
+/// SOA record data
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Soa {
+    /// Primary name server for the zone
+    pub mname: Domain,
+    /// Mailbox of the person responsible for the zone
+    pub rname: Domain,
+    /// Version number of the zone
+    pub serial: u32,
+    /// Seconds before the zone should be refreshed
+    pub refresh: u32,
+    /// Seconds before a failed refresh should be retried
+    pub retry: u32,
+    /// Seconds before the zone is no longer authoritative
+    pub expire: u32,
+    /// Minimum TTL of records in the zone, also used for negative caching
+    pub minimum: u32,
+}
+
+/// HINFO record data
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Hinfo {
+    /// CPU type
+    pub cpu: Bytes,
+    /// Operating system type
+    pub os: Bytes,
+}
+
+/// CAA record data, see RFC 8659
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Caa {
+    /// Raw flags byte, only the issuer critical flag is defined
+    pub flags: u8,
+    /// Property tag, e.g. `issue`
+    pub tag: String,
+    /// Property value
+    pub value: Bytes,
+}
+
+impl Caa {
+    /// Is the issuer critical flag set
+    pub fn is_critical(&self) -> bool {
+        self.flags & 0x80 == 0x80
+    }
+}
+
+/// SRV record data, see RFC 2782
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Srv {
+    /// Lower values are preferred
+    pub priority: u16,
+    /// Relative weight between records with the same priority
+    pub weight: u16,
+    /// Port the service is on
+    pub port: u16,
+    /// Host providing the service
+    pub target: Domain,
+}
+
+/// A single SVCB/HTTPS parameter, see RFC 9460
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SvcParam {
+    Mandatory(Vec<u16>),
+    Alpn(Vec<Bytes>),
+    NoDefaultAlpn,
+    Port(u16),
+    Ipv4Hint(Vec<Ipv4Addr>),
+    Ech(Bytes),
+    Ipv6Hint(Vec<Ipv6Addr>),
+    Unknown(u16, Bytes),
+}
+
+impl SvcParam {
+    /// The SvcParamKey this parameter is stored under
+    pub fn key(&self) -> u16 {
+        match self {
+            SvcParam::Mandatory(_) => 0,
+            SvcParam::Alpn(_) => 1,
+            SvcParam::NoDefaultAlpn => 2,
+            SvcParam::Port(_) => 3,
+            SvcParam::Ipv4Hint(_) => 4,
+            SvcParam::Ech(_) => 5,
+            SvcParam::Ipv6Hint(_) => 6,
+            SvcParam::Unknown(key, _) => *key,
+        }
+    }
+}
+
+/// SVCB and HTTPS record data, see RFC 9460
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Svcb {
+    /// 0 means AliasMode, anything else is ServiceMode
+    pub priority: u16,
+    /// Alias target or alternative endpoint
+    pub target: Domain,
+    /// Parameters, sorted by key
+    pub params: Vec<SvcParam>,
+}
+
+/// Decoded record data, anything not understood is kept as `Unknown`
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum RData {
+    A(Ipv4Addr),
+    AAAA(Ipv6Addr),
+    NS(Domain),
+    CNAME(Domain),
+    /// Target the owner's subtree is redirected to, see RFC 6672
+    DNAME(Domain),
+    PTR(Domain),
+    MX(Mx),
+    SOA(Soa),
+    /// One or more character-strings
+    TXT(Vec<Bytes>),
+    HINFO(Hinfo),
+    CAA(Caa),
+    SVCB(Svcb),
+    HTTPS(Svcb),
+    SRV(Srv),
+    Unknown(Bytes),
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+/// Remembers where domains were written in a message, so later domains
+/// ending in the same labels can point back to them (RFC 1035 section 4.1.4)
+#[derive(Debug, Clone, Default)]
+pub struct NameCompression {
+    enabled: bool,
+    /// Where in the buffer the message starts, pointers are relative to this
+    start: usize,
+    /// Offsets of every suffix written so far
+    suffixes: HashMap<Vec<String>, u16>,
+}
+
+impl NameCompression {
+    /// Pointers can only address the first 14 bits worth of the message
+    const MAX_OFFSET: usize = 0x3FFF;
+
+    /// Compress names in a message that starts at `start` in the buffer
+    pub fn new(start: usize) -> NameCompression {
+        NameCompression {
+            enabled: true,
+            start,
+            suffixes: HashMap::new(),
+        }
+    }
+
+    /// Never compress names, e.g. for DNSSEC canonical form
+    pub fn disabled() -> NameCompression {
+        NameCompression::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Offset of an already written `suffix`, if there is one
+    pub(crate) fn find(&self, suffix: &[String]) -> Option<u16> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.suffixes.get(suffix).copied()
+    }
+
+    /// Remember that `suffix` is being written at `position` in the buffer
+    pub(crate) fn insert(&mut self, suffix: &[String], position: usize) {
+        if !self.enabled || position < self.start {
+            return;
+        }
+
+        let offset = position - self.start;
+        if offset > Self::MAX_OFFSET {
+            return;
+        }
+
+        // Offset has to fit into u16 due to previous check
+        #[allow(clippy::cast_possible_truncation)]
+        self.suffixes
+            .entry(suffix.to_vec())
+            .or_insert(offset as u16);
+    }
+}
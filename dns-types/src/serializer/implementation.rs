@@ -1,8 +1,12 @@
-use bytes::BufMut;
+use bytes::{BufMut, Bytes};
 use thiserror::Error;
 
-use crate::serializer::{InfallibleSerializable, Serializable};
-use crate::{Domain, Header, Message, Question, ResourceRecord};
+use crate::serializer::{
+    CompressedSerializable, InfallibleSerializable, NameCompression, Serializable,
+};
+use crate::{
+    Domain, Edns, Header, Message, Question, RData, RecordType, ResourceRecord, SvcParam, Svcb,
+};
 
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum SerializerError {
@@ -32,7 +36,28 @@ impl Serializable for Domain {
     where
         Self: std::marker::Sized,
     {
-        for part in &self.0 {
+        self.serialize_with_compression(buf, &mut NameCompression::disabled())
+    }
+}
+
+impl CompressedSerializable for Domain {
+    type Error = SerializerError;
+
+    fn serialize_with_compression(
+        &self,
+        buf: &mut bytes::BytesMut,
+        compression: &mut NameCompression,
+    ) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        for (i, part) in self.0.iter().enumerate() {
+            // The rest of the domain was already written, so point to it instead
+            if let Some(offset) = compression.find(&self.0[i..]) {
+                buf.put_u16(0xC000 | offset);
+                return Ok(());
+            }
+
             if !part.is_ascii() {
                 return Err(SerializerError::InvalidAscii(part.clone()));
             }
@@ -47,6 +72,8 @@ impl Serializable for Domain {
                 });
             }
 
+            compression.insert(&self.0[i..], buf.len());
+
             // len has to fit into u8 due to previous check
             buf.put_u8(len as u8);
 
@@ -115,7 +142,22 @@ impl Serializable for Question {
     where
         Self: std::marker::Sized,
     {
-        self.name.serialize(buf)?;
+        self.serialize_with_compression(buf, &mut NameCompression::disabled())
+    }
+}
+
+impl CompressedSerializable for Question {
+    type Error = SerializerError;
+
+    fn serialize_with_compression(
+        &self,
+        buf: &mut bytes::BytesMut,
+        compression: &mut NameCompression,
+    ) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        self.name.serialize_with_compression(buf, compression)?;
 
         buf.reserve(4);
         buf.put_u16(self.qtype.into());
@@ -132,14 +174,35 @@ impl Serializable for ResourceRecord {
     where
         Self: std::marker::Sized,
     {
-        self.name.serialize(buf)?;
+        self.serialize_with_compression(buf, &mut NameCompression::disabled())
+    }
+}
+
+impl CompressedSerializable for ResourceRecord {
+    type Error = SerializerError;
+
+    fn serialize_with_compression(
+        &self,
+        buf: &mut bytes::BytesMut,
+        compression: &mut NameCompression,
+    ) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        self.name.serialize_with_compression(buf, compression)?;
 
         buf.put_u16(self.rtype.into());
         buf.put_u16(self.rclass.into());
 
         buf.put_u32(self.ttl);
 
-        let len_usize = self.data.len();
+        // Length isn't known until the data is written, so fill it in afterwards
+        let len_pos = buf.len();
+        buf.put_u16(0);
+
+        self.data.serialize_with_compression(buf, compression)?;
+
+        let len_usize = buf.len() - len_pos - 2;
         let len: u16 = match len_usize.try_into() {
             Ok(len) => len,
             Err(_) => {
@@ -151,10 +214,228 @@ impl Serializable for ResourceRecord {
             }
         };
 
-        buf.reserve(2 + len_usize);
+        buf[len_pos..len_pos + 2].copy_from_slice(&len.to_be_bytes());
 
-        buf.put_u16(len);
-        buf.put(self.data.clone());
+        Ok(())
+    }
+}
+
+impl Serializable for Edns {
+    type Error = SerializerError;
+
+    /// Writes the full OPT pseudo-record
+    fn serialize(&self, buf: &mut bytes::BytesMut) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        // Owned by the root domain
+        buf.put_u8(0);
+
+        buf.put_u16(RecordType::OPT.into());
+        buf.put_u16(self.udp_payload_size);
+
+        let mut ttl = u32::from(self.extended_rcode) << 24;
+        ttl |= u32::from(self.version) << 16;
+        ttl |= u32::from(self.dnssec_ok) << 15;
+        ttl |= u32::from(self._z & 0x7FFF);
+        buf.put_u32(ttl);
+
+        let len_pos = buf.len();
+        buf.put_u16(0);
+
+        for option in &self.options {
+            let option_len: u16 =
+                option
+                    .data
+                    .len()
+                    .try_into()
+                    .map_err(|_| SerializerError::TooManyBytes {
+                        expected_max: u16::MAX as usize,
+                        recieved: option.data.len(),
+                    })?;
+
+            buf.put_u16(option.code);
+            buf.put_u16(option_len);
+            buf.extend_from_slice(&option.data);
+        }
+
+        let len_usize = buf.len() - len_pos - 2;
+        let len: u16 = len_usize
+            .try_into()
+            .map_err(|_| SerializerError::TooManyBytes {
+                expected_max: u16::MAX as usize,
+                recieved: len_usize,
+            })?;
+
+        buf[len_pos..len_pos + 2].copy_from_slice(&len.to_be_bytes());
+
+        Ok(())
+    }
+}
+
+fn serialize_character_string(
+    data: &Bytes,
+    buf: &mut bytes::BytesMut,
+) -> Result<(), SerializerError> {
+    let len: u8 = data
+        .len()
+        .try_into()
+        .map_err(|_| SerializerError::TooManyBytes {
+            expected_max: u8::MAX as usize,
+            recieved: data.len(),
+        })?;
+
+    buf.reserve(1 + data.len());
+    buf.put_u8(len);
+    buf.put(data.clone());
+
+    Ok(())
+}
+
+impl Serializable for RData {
+    type Error = SerializerError;
+
+    fn serialize(&self, buf: &mut bytes::BytesMut) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        self.serialize_with_compression(buf, &mut NameCompression::disabled())
+    }
+}
+
+/// Only the types from RFC 1035 may have their domains compressed (RFC 3597 section 4),
+/// so DNAME, SRV and SVCB targets are always written in full
+impl CompressedSerializable for RData {
+    type Error = SerializerError;
+
+    fn serialize_with_compression(
+        &self,
+        buf: &mut bytes::BytesMut,
+        compression: &mut NameCompression,
+    ) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        match self {
+            RData::A(ip) => buf.put_slice(&ip.octets()),
+            RData::AAAA(ip) => buf.put_slice(&ip.octets()),
+            RData::NS(domain) | RData::CNAME(domain) | RData::PTR(domain) => {
+                domain.serialize_with_compression(buf, compression)?;
+            }
+            RData::MX(mx) => {
+                buf.put_u16(mx.preference);
+                mx.exchange.serialize_with_compression(buf, compression)?;
+            }
+            RData::SOA(soa) => {
+                soa.mname.serialize_with_compression(buf, compression)?;
+                soa.rname.serialize_with_compression(buf, compression)?;
+
+                buf.reserve(20);
+                buf.put_u32(soa.serial);
+                buf.put_u32(soa.refresh);
+                buf.put_u32(soa.retry);
+                buf.put_u32(soa.expire);
+                buf.put_u32(soa.minimum);
+            }
+            RData::TXT(strings) => {
+                for string in strings {
+                    serialize_character_string(string, buf)?;
+                }
+            }
+            RData::HINFO(hinfo) => {
+                serialize_character_string(&hinfo.cpu, buf)?;
+                serialize_character_string(&hinfo.os, buf)?;
+            }
+            RData::CAA(caa) => {
+                if !caa.tag.is_ascii() {
+                    return Err(SerializerError::InvalidAscii(caa.tag.clone()));
+                }
+
+                buf.put_u8(caa.flags);
+                serialize_character_string(&Bytes::copy_from_slice(caa.tag.as_bytes()), buf)?;
+                buf.put(caa.value.clone());
+            }
+            RData::DNAME(domain) => domain.serialize(buf)?,
+            RData::SVCB(svcb) | RData::HTTPS(svcb) => svcb.serialize(buf)?,
+            RData::SRV(srv) => {
+                buf.reserve(6);
+                buf.put_u16(srv.priority);
+                buf.put_u16(srv.weight);
+                buf.put_u16(srv.port);
+                srv.target.serialize(buf)?;
+            }
+            RData::Unknown(data) => buf.put(data.clone()),
+        }
+
+        Ok(())
+    }
+}
+
+impl Serializable for Svcb {
+    type Error = SerializerError;
+
+    fn serialize(&self, buf: &mut bytes::BytesMut) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        buf.put_u16(self.priority);
+        self.target.serialize(buf)?;
+
+        for param in &self.params {
+            param.serialize(buf)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serializable for SvcParam {
+    type Error = SerializerError;
+
+    fn serialize(&self, buf: &mut bytes::BytesMut) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        buf.put_u16(self.key());
+
+        let len_pos = buf.len();
+        buf.put_u16(0);
+
+        match self {
+            SvcParam::Mandatory(keys) => {
+                for key in keys {
+                    buf.put_u16(*key);
+                }
+            }
+            SvcParam::Alpn(ids) => {
+                for id in ids {
+                    serialize_character_string(id, buf)?;
+                }
+            }
+            SvcParam::NoDefaultAlpn => {}
+            SvcParam::Port(port) => buf.put_u16(*port),
+            SvcParam::Ipv4Hint(hints) => {
+                for hint in hints {
+                    buf.put_slice(&hint.octets());
+                }
+            }
+            SvcParam::Ipv6Hint(hints) => {
+                for hint in hints {
+                    buf.put_slice(&hint.octets());
+                }
+            }
+            SvcParam::Ech(data) | SvcParam::Unknown(_, data) => buf.put(data.clone()),
+        }
+
+        let len_usize = buf.len() - len_pos - 2;
+        let len: u16 = len_usize
+            .try_into()
+            .map_err(|_| SerializerError::TooManyBytes {
+                expected_max: u16::MAX as usize,
+                recieved: len_usize,
+            })?;
+
+        buf[len_pos..len_pos + 2].copy_from_slice(&len.to_be_bytes());
 
         Ok(())
     }
@@ -164,6 +445,21 @@ impl Serializable for Message {
     type Error = SerializerError;
 
     fn serialize(&self, buf: &mut bytes::BytesMut) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized,
+    {
+        self.serialize_with_compression(buf, &mut NameCompression::new(buf.len()))
+    }
+}
+
+impl CompressedSerializable for Message {
+    type Error = SerializerError;
+
+    fn serialize_with_compression(
+        &self,
+        buf: &mut bytes::BytesMut,
+        compression: &mut NameCompression,
+    ) -> Result<(), Self::Error>
     where
         Self: std::marker::Sized,
     {
@@ -196,31 +492,36 @@ impl Serializable for Message {
                     recieved: self.authorities.len(),
                 })?;
 
+        // The OPT record is sent as part of the additional section
+        let additional_len = self.additional.len() + usize::from(self.edns.is_some());
         header.additional_records =
-            self.additional
-                .len()
+            additional_len
                 .try_into()
                 .map_err(|_| SerializerError::TooManyRecords {
                     expected_max: u16::MAX as usize,
-                    recieved: self.additional.len(),
+                    recieved: additional_len,
                 })?;
 
         header.serialize_infallible(buf);
 
         for question in &self.questions {
-            question.serialize(buf)?;
+            question.serialize_with_compression(buf, compression)?;
         }
 
         for answer in &self.answers {
-            answer.serialize(buf)?;
+            answer.serialize_with_compression(buf, compression)?;
         }
 
         for authority in &self.authorities {
-            authority.serialize(buf)?;
+            authority.serialize_with_compression(buf, compression)?;
         }
 
         for additional in &self.additional {
-            additional.serialize(buf)?;
+            additional.serialize_with_compression(buf, compression)?;
+        }
+
+        if let Some(edns) = &self.edns {
+            edns.serialize(buf)?;
         }
 
         Ok(())
@@ -1,9 +1,10 @@
+mod compression;
 mod implementation;
 mod traits;
 
 #[cfg(test)]
 mod tests;
-#[cfg(test)]
-pub use implementation::*;
 
+pub use compression::*;
+pub use implementation::SerializerError;
 pub use traits::*;
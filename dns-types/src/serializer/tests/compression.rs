@@ -0,0 +1,198 @@
+use std::net::Ipv4Addr;
+
+use bytes::BytesMut;
+
+use crate::parser::{BytesBuf, Parsable};
+use crate::*;
+use serializer::*;
+
+fn record(name: &[&str], data: RData) -> ResourceRecord {
+    ResourceRecord {
+        name: Domain(name.iter().map(ToString::to_string).collect()),
+        rtype: match data {
+            RData::A(_) => RecordType::A,
+            RData::MX(_) => RecordType::MX,
+            RData::SRV(_) => RecordType::SRV,
+            RData::DNAME(_) => RecordType::DNAME,
+            _ => RecordType::Unknown(0),
+        },
+        rclass: RecordClass::IN,
+        ttl: 300,
+        data,
+    }
+}
+
+fn message(answers: Vec<ResourceRecord>) -> Message {
+    Message {
+        header: Header {
+            id: 0,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: false,
+            is_truncated: false,
+            should_recurse: false,
+            recursion_available: false,
+            _z: 0,
+            rescode: ResCode::NoError,
+            questions: 1,
+            answer_records: 0,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: vec![Question {
+            name: Domain(vec!["hackclub".into(), "com".into()]),
+            qtype: RecordType::ANY,
+            qclass: RecordClass::IN,
+        }],
+        answers,
+        authorities: vec![],
+        additional: vec![],
+        edns: None,
+    }
+}
+
+#[test]
+fn compresses_owner_names() {
+    let msg = message(vec![
+        record(&["hackclub", "com"], RData::A(Ipv4Addr::new(1, 1, 1, 1))),
+        record(&["hackclub", "com"], RData::A(Ipv4Addr::new(1, 0, 0, 1))),
+    ]);
+
+    let mut buf = BytesMut::new();
+    assert_eq!(msg.serialize(&mut buf), Ok(()));
+
+    let result_buf: &[u8] = &[
+        0x00, 0x00, 0x80, 0x00, // id, flags
+        0x00, 0x01, 0x00, 0x02, // 1 question, 2 answers
+        0x00, 0x00, 0x00, 0x00, // no authorities or additional
+        0x08, 104, 97, 99, 107, 99, 108, 117, 98, // hackclub
+        0x03, 99, 111, 109, 0x00, // com
+        0x00, 0xFF, 0x00, 0x01, // ANY, IN
+        0xC0, 0x0C, // pointer to `hackclub.com.`
+        0x00, 0x01, 0x00, 0x01, // A, IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x04, 0x01, 0x01, 0x01, 0x01, // 1.1.1.1
+        0xC0, 0x0C, // pointer to `hackclub.com.`
+        0x00, 0x01, 0x00, 0x01, // A, IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x04, 0x01, 0x00, 0x00, 0x01, // 1.0.0.1
+    ];
+
+    assert_eq!(buf, result_buf);
+}
+
+#[test]
+fn compresses_suffixes_in_rdata() {
+    let msg = message(vec![record(
+        &["hackclub", "com"],
+        RData::MX(Mx {
+            preference: 10,
+            exchange: Domain(vec!["mail".into(), "hackclub".into(), "com".into()]),
+        }),
+    )]);
+
+    let mut buf = BytesMut::new();
+    assert_eq!(msg.serialize(&mut buf), Ok(()));
+
+    // Everything after the question
+    let result_buf: &[u8] = &[
+        0xC0, 0x0C, // pointer to `hackclub.com.`
+        0x00, 0x0F, 0x00, 0x01, // MX, IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x09, // data len: 9
+        0x00, 0x0A, // preference: 10
+        0x04, 109, 97, 105, 108, // mail
+        0xC0, 0x0C, // pointer to `hackclub.com.`
+    ];
+
+    assert_eq!(&buf[30..], result_buf);
+    assert_eq!(
+        Message::parse(&mut BytesBuf::new(buf.to_vec())).map(|parsed| parsed.answers),
+        Ok(msg.answers)
+    );
+}
+
+#[test]
+fn never_compresses_srv_target() {
+    let msg = message(vec![record(
+        &["hackclub", "com"],
+        RData::SRV(Srv {
+            priority: 0,
+            weight: 0,
+            port: 443,
+            target: Domain(vec!["hackclub".into(), "com".into()]),
+        }),
+    )]);
+
+    let mut buf = BytesMut::new();
+    assert_eq!(msg.serialize(&mut buf), Ok(()));
+
+    // Everything after the question
+    let result_buf: &[u8] = &[
+        0xC0, 0x0C, // pointer to `hackclub.com.`
+        0x00, 0x21, 0x00, 0x01, // SRV, IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x14, // data len: 20
+        0x00, 0x00, 0x00, 0x00, 0x01, 0xBB, // priority, weight, port
+        0x08, 104, 97, 99, 107, 99, 108, 117, 98, // hackclub
+        0x03, 99, 111, 109, 0x00, // com
+    ];
+
+    assert_eq!(&buf[30..], result_buf);
+}
+
+#[test]
+fn never_compresses_dname_target() {
+    let msg = message(vec![record(
+        &["hackclub", "com"],
+        RData::DNAME(Domain(vec!["hackclub".into(), "com".into()])),
+    )]);
+
+    let mut buf = BytesMut::new();
+    assert_eq!(msg.serialize(&mut buf), Ok(()));
+
+    // Everything after the question
+    let result_buf: &[u8] = &[
+        0xC0, 0x0C, // pointer to `hackclub.com.`
+        0x00, 0x27, 0x00, 0x01, // DNAME, IN
+        0x00, 0x00, 0x01, 0x2C, // ttl: 300
+        0x00, 0x0E, // data len: 14
+        0x08, 104, 97, 99, 107, 99, 108, 117, 98, // hackclub
+        0x03, 99, 111, 109, 0x00, // com
+    ];
+
+    assert_eq!(&buf[30..], result_buf);
+}
+
+#[test]
+fn disabled_compression() {
+    let msg = message(vec![record(
+        &["hackclub", "com"],
+        RData::A(Ipv4Addr::new(1, 1, 1, 1)),
+    )]);
+
+    let mut buf = BytesMut::new();
+    assert_eq!(
+        msg.serialize_with_compression(&mut buf, &mut NameCompression::disabled()),
+        Ok(())
+    );
+
+    // Owner name is written in full after the question
+    assert_eq!(
+        &buf[30..44],
+        &[0x08, 104, 97, 99, 107, 99, 108, 117, 98, 0x03, 99, 111, 109, 0x00]
+    );
+}
+
+#[test]
+fn pointers_relative_to_message_start() {
+    // e.g. a TCP length prefix written before the message
+    let mut buf = BytesMut::from(&[0xAB, 0xCD][..]);
+    let msg = message(vec![record(
+        &["hackclub", "com"],
+        RData::A(Ipv4Addr::new(1, 1, 1, 1)),
+    )]);
+
+    assert_eq!(msg.serialize(&mut buf), Ok(()));
+    assert_eq!(&buf[32..34], &[0xC0, 0x0C]);
+}
@@ -0,0 +1,72 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::parser::{BytesBuf, Parsable};
+use crate::*;
+use serializer::*;
+
+#[test]
+fn encode_opt() {
+    let edns = Edns {
+        udp_payload_size: 1232,
+        extended_rcode: 0,
+        version: 0,
+        dnssec_ok: true,
+        _z: 0,
+        options: vec![EdnsOption {
+            code: 10,
+            data: Bytes::from_static(&[0xAB, 0xCD]),
+        }],
+    };
+
+    let mut buf = BytesMut::new();
+    assert_eq!(edns.serialize(&mut buf), Ok(()));
+
+    let result_buf: &[u8] = &[
+        0x00, // domain: `.`
+        0x00, 0x29, // rtype: 41 (OPT)
+        0x04, 0xD0, // udp payload size: 1232
+        0x00, 0x00, 0x80, 0x00, // DO bit
+        0x00, 0x06, // data len: 6
+        0x00, 0x0A, 0x00, 0x02, // option: 10 (cookie), 2 bytes
+        0xAB, 0xCD, //
+    ];
+
+    assert_eq!(buf, result_buf);
+}
+
+#[test]
+fn message_round_trip() {
+    let msg = Message {
+        header: Header {
+            id: 0,
+            is_response: false,
+            opcode: OpCode::Query,
+            is_authoritative: false,
+            is_truncated: false,
+            should_recurse: true,
+            recursion_available: false,
+            _z: 0,
+            rescode: ResCode::NoError,
+            questions: 1,
+            answer_records: 0,
+            authority_records: 0,
+            additional_records: 1,
+        },
+        questions: vec![Question {
+            name: Domain(vec!["hackclub".into(), "com".into()]),
+            qtype: RecordType::A,
+            qclass: RecordClass::IN,
+        }],
+        answers: vec![],
+        authorities: vec![],
+        additional: vec![],
+        edns: Some(Edns::new(1232)),
+    };
+
+    let mut buf = BytesMut::new();
+    assert_eq!(msg.serialize(&mut buf), Ok(()));
+
+    // OPT record is counted in the additional section
+    assert_eq!(&buf[10..12], &[0x00, 0x01]);
+    assert_eq!(Message::parse(&mut BytesBuf::new(buf.to_vec())), Ok(msg));
+}
@@ -4,10 +4,8 @@ use crate::*;
 use bytes::{Bytes, BytesMut};
 use serializer::*;
 
-#[test]
-fn test_encode() {
-    let mut buf = BytesMut::new();
-    assert_eq!(Message{
+fn get_shared_test_case_data() -> Message {
+    Message{
         header: Header {
             id: 0,
             is_response: false,
@@ -37,18 +35,96 @@ fn test_encode() {
                 rtype: RecordType::TXT,
                 rclass: RecordClass::IN,
                 ttl: 1337,
-                data: Bytes::copy_from_slice(
+                // Not valid character-strings, so it's kept as is
+                data: RData::Unknown(Bytes::copy_from_slice(
                     b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
-                ),
-                domain_data: None, after_ptr: None
+                )),
             }
         ],
-        additional: vec![]
-    }.serialize(&mut buf), Ok(()));
+        additional: vec![],
+        edns: None
+    }
+}
+
+/// A well formed TXT record, which gets decoded
+fn get_txt_test_case_data() -> Message {
+    Message {
+        header: Header {
+            id: 0,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: false,
+            is_truncated: false,
+            should_recurse: false,
+            recursion_available: false,
+            _z: 0,
+            rescode: ResCode::NoError,
+            questions: 1,
+            answer_records: 1,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: vec![Question {
+            name: Domain(vec!["se".into()]),
+            qtype: RecordType::TXT,
+            qclass: RecordClass::IN,
+        }],
+        answers: vec![ResourceRecord {
+            name: Domain(vec!["se".into()]),
+            rtype: RecordType::TXT,
+            rclass: RecordClass::IN,
+            ttl: 1337,
+            data: RData::TXT(vec![
+                Bytes::from_static(b"hello"),
+                Bytes::from_static(b"world"),
+            ]),
+        }],
+        authorities: vec![],
+        additional: vec![],
+        edns: None,
+    }
+}
+
+#[test]
+fn test_encode() {
+    let mut buf = BytesMut::new();
+    assert_eq!(get_shared_test_case_data().serialize(&mut buf), Ok(()));
+
+    assert_eq!(buf, fs::read("tests/test-pointers.bin").unwrap());
+}
+
+#[test]
+fn test_encode_nopointers() {
+    let mut buf = BytesMut::new();
+    assert_eq!(
+        get_shared_test_case_data()
+            .serialize_with_compression(&mut buf, &mut NameCompression::disabled()),
+        Ok(())
+    );
 
     assert_eq!(buf, fs::read("tests/test-nopointers.bin").unwrap());
 }
 
+#[test]
+fn test_encode_txt() {
+    let mut buf = BytesMut::new();
+    assert_eq!(get_txt_test_case_data().serialize(&mut buf), Ok(()));
+
+    assert_eq!(buf, fs::read("tests/test-txt-pointers.bin").unwrap());
+}
+
+#[test]
+fn test_encode_txt_nopointers() {
+    let mut buf = BytesMut::new();
+    assert_eq!(
+        get_txt_test_case_data()
+            .serialize_with_compression(&mut buf, &mut NameCompression::disabled()),
+        Ok(())
+    );
+
+    assert_eq!(buf, fs::read("tests/test-txt-nopointers.bin").unwrap());
+}
+
 #[test]
 fn too_many_questions_error() {
     let mut msg = Box::new(Message {
@@ -71,6 +147,7 @@ fn too_many_questions_error() {
         answers: vec![],
         authorities: vec![],
         additional: vec![],
+        edns: None,
     });
 
     for _ in 0..(u16::MAX as usize + 1) {
@@ -113,6 +190,7 @@ fn too_many_recordss_error() {
         answers: vec![],
         authorities: vec![],
         additional: vec![],
+        edns: None,
     });
 
     let data = Bytes::new();
@@ -123,9 +201,7 @@ fn too_many_recordss_error() {
             rtype: RecordType::Unknown(0),
             rclass: RecordClass::Unknown(0),
             ttl: 0,
-            data: data.clone(),
-            domain_data: None,
-            after_ptr: None,
+            data: RData::Unknown(data.clone()),
         });
     }
 
@@ -1,5 +1,8 @@
+mod compression;
 mod domain;
+mod edns;
 mod header;
 mod message;
 mod question;
+mod rdata;
 mod record;
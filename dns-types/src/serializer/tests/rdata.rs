@@ -0,0 +1,112 @@
+use std::net::Ipv6Addr;
+
+use bytes::{Bytes, BytesMut};
+
+use crate::parser::{BytesBuf, Parsable};
+use crate::*;
+use serializer::*;
+
+#[test]
+fn correct_mx_encoding() {
+    let mut buf = BytesMut::new();
+    assert_eq!(
+        RData::MX(Mx {
+            preference: 10,
+            exchange: Domain(vec!["mail".into(), "se".into()])
+        })
+        .serialize(&mut buf),
+        Ok(())
+    );
+
+    let result_buf: &[u8] = &[
+        0x00, 0x0A, // preference: 10
+        0x04, 109, 97, 105, 108, // mail
+        0x02, 115, 101, // se
+        0x00,
+    ];
+
+    assert_eq!(buf, result_buf);
+}
+
+#[test]
+fn correct_svcb_encoding() {
+    let mut buf = BytesMut::new();
+    assert_eq!(
+        RData::SVCB(Svcb {
+            priority: 1,
+            target: Domain(vec![]),
+            params: vec![
+                SvcParam::Alpn(vec![Bytes::from_static(b"h2")]),
+                SvcParam::NoDefaultAlpn,
+                SvcParam::Port(443),
+            ]
+        })
+        .serialize(&mut buf),
+        Ok(())
+    );
+
+    let result_buf: &[u8] = &[
+        0x00, 0x01, // priority: 1
+        0x00, // target: `.`
+        0x00, 0x01, 0x00, 0x03, // alpn, 3 bytes
+        0x02, 104, 50, // h2
+        0x00, 0x02, 0x00, 0x00, // no-default-alpn
+        0x00, 0x03, 0x00, 0x02, // port, 2 bytes
+        0x01, 0xBB, // 443
+    ];
+
+    assert_eq!(buf, result_buf);
+}
+
+#[test]
+fn txt_string_too_long_error() {
+    let mut buf = BytesMut::new();
+    assert_eq!(
+        RData::TXT(vec![Bytes::from_static(&[0x61; 256])]).serialize(&mut buf),
+        Err(SerializerError::TooManyBytes {
+            expected_max: 255,
+            recieved: 256
+        })
+    );
+}
+
+#[test]
+fn record_round_trip() {
+    let record = ResourceRecord {
+        name: Domain(vec!["www".into(), "hackclub".into(), "com".into()]),
+        rtype: RecordType::SOA,
+        rclass: RecordClass::IN,
+        ttl: 1337,
+        data: RData::SOA(Soa {
+            mname: Domain(vec!["ns".into(), "hackclub".into(), "com".into()]),
+            rname: Domain(vec!["hostmaster".into(), "hackclub".into(), "com".into()]),
+            serial: 2024061501,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 300,
+        }),
+    };
+
+    let mut buf = BytesMut::new();
+    assert_eq!(record.serialize(&mut buf), Ok(()));
+    assert_eq!(
+        ResourceRecord::parse(&mut BytesBuf::new(buf.to_vec())),
+        Ok(record)
+    );
+
+    let record = ResourceRecord {
+        name: Domain(vec!["hackclub".into(), "com".into()]),
+        rtype: RecordType::AAAA,
+        rclass: RecordClass::IN,
+        ttl: 60,
+        data: RData::AAAA(Ipv6Addr::LOCALHOST),
+    };
+
+    let mut buf = BytesMut::new();
+    assert_eq!(record.serialize(&mut buf), Ok(()));
+    assert_eq!(
+        ResourceRecord::parse(&mut BytesBuf::new(buf.to_vec())),
+        Ok(record)
+    );
+}
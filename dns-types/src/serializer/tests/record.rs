@@ -1,3 +1,5 @@
+use std::net::Ipv4Addr;
+
 use bytes::{Bytes, BytesMut};
 
 use crate::*;
@@ -12,9 +14,7 @@ fn correct_record_data() {
             rtype: RecordType::Unknown(0),
             rclass: RecordClass::Unknown(0),
             ttl: 0,
-            data: Bytes::from_static(&[0x13, 0x37, 0x13, 0x37]),
-            domain_data: None,
-            after_ptr: None
+            data: RData::Unknown(Bytes::from_static(&[0x13, 0x37, 0x13, 0x37])),
         }
         .serialize(&mut buf),
         Ok(())
@@ -43,9 +43,7 @@ fn correct_total_encoding() {
             rtype: RecordType::A,
             rclass: RecordClass::IN,
             ttl: 0xDEADBEEF,
-            data: Bytes::from_static(&[0xBA, 0xAA, 0xAA, 0xAD]),
-            domain_data: None,
-            after_ptr: None
+            data: RData::A(Ipv4Addr::new(0xBA, 0xAA, 0xAA, 0xAD)),
         }
         .serialize(&mut buf),
         Ok(())
@@ -79,9 +77,7 @@ fn buf_too_long_error() {
             rtype: RecordType::A,
             rclass: RecordClass::IN,
             ttl: 0xDEADBEEF,
-            data: Bytes::from_static(&[0x00; (u16::MAX as usize) + 1]),
-            domain_data: None,
-            after_ptr: None,
+            data: RData::Unknown(Bytes::from_static(&[0x00; (u16::MAX as usize) + 1])),
         }
         .serialize(&mut buf),
         Err(SerializerError::TooManyBytes {
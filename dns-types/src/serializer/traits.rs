@@ -1,5 +1,7 @@
 use bytes::BytesMut;
 
+use super::NameCompression;
+
 pub trait Serializable {
     type Error;
 
@@ -9,6 +11,19 @@ pub trait Serializable {
         Self: std::marker::Sized;
 }
 
+pub trait CompressedSerializable {
+    type Error;
+
+    /// Serializes type into `data`, pointing back to domains already in `compression` where allowed
+    fn serialize_with_compression(
+        &self,
+        buf: &mut BytesMut,
+        compression: &mut NameCompression,
+    ) -> Result<(), Self::Error>
+    where
+        Self: std::marker::Sized;
+}
+
 pub trait InfallibleSerializable {
     /// Serializes type into `data`
     fn serialize_infallible(&self, buf: &mut BytesMut)
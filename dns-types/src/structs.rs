@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use bytes::Bytes;
 
-use super::{OpCode, RecordClass, RecordType, ResCode};
+use super::{OpCode, RData, RecordClass, RecordType, ResCode};
 
 /// DNS Domain
 #[derive(Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord)]
@@ -16,7 +16,17 @@ impl Display for Domain {
         }
 
         for part in &self.0 {
-            f.write_str(part)?;
+            // Escape anything that would change the meaning of the name in a zone file
+            for byte in part.bytes() {
+                match byte {
+                    b'.' | b'\\' | b'"' | b';' | b'(' | b')' | b'@' | b'$' => {
+                        write!(f, "\\{}", char::from(byte))?;
+                    }
+                    0x21..=0x7E => write!(f, "{}", char::from(byte))?,
+                    _ => write!(f, "\\{byte:03}")?,
+                }
+            }
+
             f.write_str(".")?;
         }
 
@@ -25,6 +35,16 @@ impl Display for Domain {
 }
 
 impl Domain {
+    /// Domains are case insensitive, so this is used when comparing or storing them
+    pub fn to_lowercase(&self) -> Domain {
+        Domain(
+            self.0
+                .iter()
+                .map(|label| label.to_ascii_lowercase())
+                .collect(),
+        )
+    }
+
     pub fn idna_to_string(&self) -> String {
         if self.0.is_empty() {
             return String::from(".");
@@ -83,7 +103,7 @@ pub struct Header {
 }
 
 /// A singular question
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Question {
     /// Domain to lookup
     pub name: Domain,
@@ -94,7 +114,7 @@ pub struct Question {
 }
 
 /// One singular Resource Record
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ResourceRecord {
     /// Domain this record refers to
     pub name: Domain,
@@ -105,9 +125,47 @@ pub struct ResourceRecord {
     /// Suggested record TTL
     pub ttl: u32,
     /// Actual record data
+    pub data: RData,
+}
+
+/// A single EDNS(0) option
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EdnsOption {
+    /// Option code, see https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-11
+    pub code: u16,
+    /// Raw option data
     pub data: Bytes,
-    pub domain_data: Option<Vec<Domain>>,
-    pub after_ptr: Option<usize>,
+}
+
+/// EDNS(0) information, sent as an OPT pseudo-record in the additional section (RFC 6891)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Edns {
+    /// Largest UDP payload the sender can reassemble
+    pub udp_payload_size: u16,
+    /// Upper 8 bits of the 12 bit result code
+    pub extended_rcode: u8,
+    /// EDNS version, only 0 exists
+    pub version: u8,
+    /// Can the sender handle DNSSEC records
+    pub dnssec_ok: bool,
+    /// Should be zeros, but kept so messages round trip
+    pub _z: u16,
+    /// Options included with the message
+    pub options: Vec<EdnsOption>,
+}
+
+impl Edns {
+    /// EDNS version 0 advertising `udp_payload_size`, with nothing else set
+    pub fn new(udp_payload_size: u16) -> Edns {
+        Edns {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            _z: 0,
+            options: vec![],
+        }
+    }
 }
 
 /// A full DNS Message
@@ -117,5 +175,7 @@ pub struct Message {
     pub questions: Vec<Question>,
     pub answers: Vec<ResourceRecord>,
     pub authorities: Vec<ResourceRecord>,
+    /// Additional records, excluding the OPT record which is stored in `edns`
     pub additional: Vec<ResourceRecord>,
+    pub edns: Option<Edns>,
 }
@@ -0,0 +1,812 @@
+use std::{
+    fmt::Display,
+    net::{Ipv4Addr, Ipv6Addr},
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use thiserror::Error;
+
+use super::lexer::{tokenize, unescape, Entry, Token};
+use crate::parser::BytesBuf;
+use crate::{
+    Caa, Domain, Hinfo, Mx, RData, RecordClass, RecordType, ResourceRecord, Soa, Srv, SvcParam,
+    Svcb,
+};
+
+/// How deep `$INCLUDE`s can be nested, mostly to stop files including themselves
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ZoneErrorKind {
+    #[error("Quoted string is missing its closing quote")]
+    UnterminatedString,
+    #[error("Parentheses aren't balanced")]
+    UnbalancedParentheses,
+    #[error("Invalid escape sequence")]
+    InvalidEscape,
+    #[error("Missing {0}")]
+    MissingField(&'static str),
+    #[error("Unexpected extra data: {0}")]
+    TrailingData(String),
+    #[error("Invalid number: {0}")]
+    InvalidNumber(String),
+    #[error("Invalid TTL: {0}")]
+    InvalidTtl(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid domain name: {0}")]
+    InvalidName(&'static str),
+    #[error("Character string is longer than 255 bytes")]
+    StringTooLong,
+    #[error("Unknown record type: {0}")]
+    UnknownType(String),
+    #[error("Record type {0} can only be written in the generic `\\#` format")]
+    UnsupportedType(RecordType),
+    #[error("Unknown directive: {0}")]
+    UnknownDirective(String),
+    #[error("Relative name used without an origin")]
+    NoOrigin,
+    #[error("First record doesn't have an owner name")]
+    NoOwner,
+    #[error("Record has no TTL and no default was set with $TTL")]
+    NoTtl,
+    #[error("Invalid record data: {0}")]
+    InvalidRecordData(&'static str),
+    #[error("$INCLUDEs are nested more than {MAX_INCLUDE_DEPTH} deep")]
+    IncludeDepth,
+    #[error("Couldn't read file: {0}")]
+    Io(String),
+}
+
+/// Something went wrong while reading a zone file
+#[derive(Debug, PartialEq, Eq)]
+pub struct ZoneError {
+    /// Set when the error came from a file rather than a string
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub kind: ZoneErrorKind,
+}
+
+impl ZoneError {
+    pub(crate) fn at(line: usize, column: usize, kind: ZoneErrorKind) -> ZoneError {
+        ZoneError {
+            file: None,
+            line,
+            column,
+            kind,
+        }
+    }
+
+    fn token(token: &Token, kind: ZoneErrorKind) -> ZoneError {
+        ZoneError::at(token.line, token.column, kind)
+    }
+}
+
+impl Display for ZoneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ZoneError {}
+
+fn lossy(token: &Token) -> String {
+    String::from_utf8_lossy(&token.raw).into_owned()
+}
+
+fn parse_number<T: std::str::FromStr>(token: &Token) -> Result<T, ZoneError> {
+    std::str::from_utf8(&token.raw)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .ok_or_else(|| ZoneError::token(token, ZoneErrorKind::InvalidNumber(lossy(token))))
+}
+
+/// Parses a TTL, either plain seconds or with units like `1h30m`
+fn parse_ttl(token: &Token) -> Result<u32, ZoneError> {
+    let invalid = || ZoneError::token(token, ZoneErrorKind::InvalidTtl(lossy(token)));
+
+    if token.raw.iter().all(u8::is_ascii_digit) {
+        return parse_number(token).map_err(|_| invalid());
+    }
+
+    let mut total: u32 = 0;
+    let mut current: Option<u32> = None;
+    for byte in &token.raw {
+        if byte.is_ascii_digit() {
+            let value = current.unwrap_or(0);
+            current = Some(
+                value
+                    .checked_mul(10)
+                    .and_then(|value| value.checked_add(u32::from(byte - b'0')))
+                    .ok_or_else(invalid)?,
+            );
+            continue;
+        }
+
+        let multiplier = match byte.to_ascii_lowercase() {
+            b's' => 1,
+            b'm' => 60,
+            b'h' => 60 * 60,
+            b'd' => 60 * 60 * 24,
+            b'w' => 60 * 60 * 24 * 7,
+            _ => return Err(invalid()),
+        };
+
+        let value = current.take().ok_or_else(invalid)?;
+        total = value
+            .checked_mul(multiplier)
+            .and_then(|value| total.checked_add(value))
+            .ok_or_else(invalid)?;
+    }
+
+    if current.is_some() {
+        return Err(invalid());
+    }
+
+    Ok(total)
+}
+
+fn parse_class(token: &Token) -> Option<RecordClass> {
+    let text = std::str::from_utf8(&token.raw).ok()?;
+
+    if let Some(class) = RecordClass::from_name(text) {
+        return Some(class);
+    }
+
+    // Generic form from RFC 3597, e.g. `CLASS32`
+    let number = text
+        .get(..5)?
+        .eq_ignore_ascii_case("class")
+        .then(|| &text[5..])?;
+    number.parse::<u16>().ok().map(RecordClass::from)
+}
+
+fn parse_type(token: &Token) -> Result<RecordType, ZoneError> {
+    let text = std::str::from_utf8(&token.raw).unwrap_or_default();
+    let unknown = || ZoneError::token(token, ZoneErrorKind::UnknownType(lossy(token)));
+
+    if let Some(rtype) = RecordType::from_name(text) {
+        return Ok(rtype);
+    }
+
+    // Generic form from RFC 3597, e.g. `TYPE65280`
+    match text.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("type") => text[4..]
+            .parse::<u16>()
+            .map(RecordType::from)
+            .map_err(|_| unknown()),
+        _ => Err(unknown()),
+    }
+}
+
+/// Parses a possibly relative domain name, `@` stands for the origin
+fn parse_name(token: &Token, origin: Option<&Domain>) -> Result<Domain, ZoneError> {
+    let error = |kind| ZoneError::token(token, kind);
+
+    if token.raw == b"@" {
+        return origin
+            .cloned()
+            .ok_or_else(|| error(ZoneErrorKind::NoOrigin));
+    }
+
+    if token.raw == b"." {
+        return Ok(Domain(vec![]));
+    }
+
+    // Split on dots that aren't escaped
+    let mut labels = vec![];
+    let mut current = vec![];
+    let mut absolute = false;
+    let mut iter = token.raw.iter().copied().peekable();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'\\' => {
+                current.push(byte);
+                if let Some(escaped) = iter.next() {
+                    current.push(escaped);
+                }
+            }
+            b'.' => {
+                if current.is_empty() {
+                    return Err(error(ZoneErrorKind::InvalidName("Empty label")));
+                }
+
+                labels.push(std::mem::take(&mut current));
+                absolute = iter.peek().is_none();
+            }
+            _ => current.push(byte),
+        }
+    }
+
+    if !current.is_empty() {
+        labels.push(current);
+    }
+
+    let mut domain = Domain(vec![]);
+    for label in labels {
+        let label = unescape(&label).map_err(error)?;
+
+        if label.len() > 63 {
+            return Err(error(ZoneErrorKind::InvalidName(
+                "Label is longer than 63 bytes",
+            )));
+        }
+
+        if !label.is_ascii() {
+            return Err(error(ZoneErrorKind::InvalidName(
+                "Label contains non-ASCII bytes",
+            )));
+        }
+
+        // ASCII is always valid UTF-8
+        domain.0.push(String::from_utf8(label).unwrap_or_default());
+    }
+
+    if !absolute {
+        let origin = origin.ok_or_else(|| error(ZoneErrorKind::NoOrigin))?;
+        domain.0.extend(origin.0.iter().cloned());
+    }
+
+    let wire_len: usize = domain.0.iter().map(|label| label.len() + 1).sum::<usize>() + 1;
+    if wire_len > 255 {
+        return Err(error(ZoneErrorKind::InvalidName(
+            "Name is longer than 255 bytes",
+        )));
+    }
+
+    Ok(domain)
+}
+
+fn parse_character_string(token: &Token) -> Result<Bytes, ZoneError> {
+    let data = unescape(&token.raw).map_err(|kind| ZoneError::token(token, kind))?;
+
+    if data.len() > 255 {
+        return Err(ZoneError::token(token, ZoneErrorKind::StringTooLong));
+    }
+
+    Ok(data.into())
+}
+
+/// Walks through the record data tokens of a single entry
+struct Fields<'a> {
+    tokens: &'a [Token],
+    /// Where to point errors at when a field is missing
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn next(&mut self, field: &'static str) -> Result<&'a Token, ZoneError> {
+        let Some((token, rest)) = self.tokens.split_first() else {
+            return Err(ZoneError::at(
+                self.line,
+                self.column,
+                ZoneErrorKind::MissingField(field),
+            ));
+        };
+
+        self.tokens = rest;
+        self.line = token.line;
+        self.column = token.column + token.raw.len();
+
+        Ok(token)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    fn finish(&self) -> Result<(), ZoneError> {
+        match self.tokens.first() {
+            Some(token) => Err(ZoneError::token(
+                token,
+                ZoneErrorKind::TrailingData(lossy(token)),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_ipv4(token: &Token) -> Result<Ipv4Addr, ZoneError> {
+    std::str::from_utf8(&token.raw)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .ok_or_else(|| ZoneError::token(token, ZoneErrorKind::InvalidAddress(lossy(token))))
+}
+
+fn parse_ipv6(token: &Token) -> Result<Ipv6Addr, ZoneError> {
+    std::str::from_utf8(&token.raw)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .ok_or_else(|| ZoneError::token(token, ZoneErrorKind::InvalidAddress(lossy(token))))
+}
+
+/// RFC 3597 generic record data, e.g. `\# 4 0A000001`
+fn parse_generic(rtype: RecordType, fields: &mut Fields) -> Result<RData, ZoneError> {
+    let len_token = fields.next("generic data length")?;
+    let len: usize = parse_number(len_token)?;
+
+    let mut data = vec![];
+    while !fields.is_empty() {
+        let token = fields.next("generic data")?;
+        let invalid = || ZoneError::token(token, ZoneErrorKind::InvalidRecordData("Invalid hex"));
+
+        if token.raw.len() % 2 != 0 {
+            return Err(invalid());
+        }
+
+        for pair in token.raw.chunks(2) {
+            let text = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            data.push(u8::from_str_radix(text, 16).map_err(|_| invalid())?);
+        }
+    }
+
+    if data.len() != len {
+        return Err(ZoneError::token(
+            len_token,
+            ZoneErrorKind::InvalidRecordData("Generic data length doesn't match the data"),
+        ));
+    }
+
+    // Known types still get decoded so the record ends up the same as if it was written normally
+    RData::parse(rtype, &mut BytesBuf::new(data)).map_err(|_| {
+        ZoneError::token(
+            len_token,
+            ZoneErrorKind::InvalidRecordData("Generic data isn't valid for this record type"),
+        )
+    })
+}
+
+fn parse_svc_key(text: &str) -> Option<u16> {
+    // See https://www.iana.org/assignments/dns-svcb/dns-svcb.xhtml
+    match text {
+        "mandatory" => Some(0),
+        "alpn" => Some(1),
+        "no-default-alpn" => Some(2),
+        "port" => Some(3),
+        "ipv4hint" => Some(4),
+        "ech" => Some(5),
+        "ipv6hint" => Some(6),
+        _ => text.strip_prefix("key")?.parse().ok(),
+    }
+}
+
+/// Splits a value on commas that aren't escaped, then unescapes each item
+fn split_list(raw: &[u8]) -> Result<Vec<Vec<u8>>, ZoneErrorKind> {
+    let mut items = vec![];
+    let mut current = vec![];
+    let mut iter = raw.iter().copied();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'\\' => {
+                current.push(byte);
+                if let Some(escaped) = iter.next() {
+                    current.push(escaped);
+                }
+            }
+            b',' => items.push(unescape(&std::mem::take(&mut current))?),
+            _ => current.push(byte),
+        }
+    }
+
+    items.push(unescape(&current)?);
+
+    Ok(items)
+}
+
+fn parse_svc_param(token: &Token) -> Result<SvcParam, ZoneError> {
+    let error = |reason| ZoneError::token(token, ZoneErrorKind::InvalidRecordData(reason));
+
+    let (key, value) = match token.raw.iter().position(|byte| *byte == b'=') {
+        Some(pos) => (&token.raw[..pos], Some(&token.raw[pos + 1..])),
+        None => (&token.raw[..], None),
+    };
+
+    let key = std::str::from_utf8(key)
+        .ok()
+        .and_then(parse_svc_key)
+        .ok_or_else(|| error("Unknown SvcParam key"))?;
+
+    let list = |value: Option<&[u8]>| match value {
+        Some(value) if !value.is_empty() => {
+            split_list(value).map_err(|kind| ZoneError::token(token, kind))
+        }
+        _ => Err(error("SvcParam is missing its value")),
+    };
+
+    let text_items = |value| {
+        list(value)?
+            .into_iter()
+            .map(|item| String::from_utf8(item).map_err(|_| error("Invalid SvcParam value")))
+            .collect::<Result<Vec<String>, ZoneError>>()
+    };
+
+    Ok(match key {
+        0 => SvcParam::Mandatory(
+            text_items(value)?
+                .iter()
+                .map(|key| parse_svc_key(key).ok_or_else(|| error("Unknown SvcParam key")))
+                .collect::<Result<_, _>>()?,
+        ),
+        1 => SvcParam::Alpn(list(value)?.into_iter().map(Bytes::from).collect()),
+        2 => {
+            if value.is_some() {
+                return Err(error("no-default-alpn doesn't take a value"));
+            }
+
+            SvcParam::NoDefaultAlpn
+        }
+        3 => SvcParam::Port(
+            text_items(value)?
+                .first()
+                .and_then(|port| port.parse().ok())
+                .ok_or_else(|| error("Invalid port"))?,
+        ),
+        4 => SvcParam::Ipv4Hint(
+            text_items(value)?
+                .iter()
+                .map(|ip| ip.parse().map_err(|_| error("Invalid IPv4 hint")))
+                .collect::<Result<_, _>>()?,
+        ),
+        5 => SvcParam::Ech(
+            value
+                .and_then(|value| STANDARD.decode(value).ok())
+                .ok_or_else(|| error("Invalid base64 in ech"))?
+                .into(),
+        ),
+        6 => SvcParam::Ipv6Hint(
+            text_items(value)?
+                .iter()
+                .map(|ip| ip.parse().map_err(|_| error("Invalid IPv6 hint")))
+                .collect::<Result<_, _>>()?,
+        ),
+        key => SvcParam::Unknown(
+            key,
+            unescape(value.unwrap_or_default())
+                .map_err(|kind| ZoneError::token(token, kind))?
+                .into(),
+        ),
+    })
+}
+
+fn parse_svcb(fields: &mut Fields, origin: Option<&Domain>) -> Result<Svcb, ZoneError> {
+    let priority = parse_number(fields.next("priority")?)?;
+    let target = parse_name(fields.next("target")?, origin)?;
+
+    let mut params = vec![];
+    while !fields.is_empty() {
+        let token = fields.next("SvcParam")?;
+        let param = parse_svc_param(token)?;
+
+        if params
+            .iter()
+            .any(|other: &SvcParam| other.key() == param.key())
+        {
+            return Err(ZoneError::token(
+                token,
+                ZoneErrorKind::InvalidRecordData("Duplicate SvcParam key"),
+            ));
+        }
+
+        params.push(param);
+    }
+
+    // Keys have to be in increasing order on the wire
+    params.sort_by_key(SvcParam::key);
+
+    Ok(Svcb {
+        priority,
+        target,
+        params,
+    })
+}
+
+fn parse_rdata(
+    rtype: RecordType,
+    fields: &mut Fields,
+    origin: Option<&Domain>,
+) -> Result<RData, ZoneError> {
+    if fields
+        .tokens
+        .first()
+        .is_some_and(|token| token.raw == b"\\#")
+    {
+        fields.next("generic data")?;
+        return parse_generic(rtype, fields);
+    }
+
+    let data = match rtype {
+        RecordType::A => RData::A(parse_ipv4(fields.next("address")?)?),
+        RecordType::AAAA => RData::AAAA(parse_ipv6(fields.next("address")?)?),
+        RecordType::NS => RData::NS(parse_name(fields.next("name server")?, origin)?),
+        RecordType::CNAME => RData::CNAME(parse_name(fields.next("canonical name")?, origin)?),
+        RecordType::DNAME => RData::DNAME(parse_name(fields.next("target")?, origin)?),
+        RecordType::PTR => RData::PTR(parse_name(fields.next("pointer")?, origin)?),
+        RecordType::MX => RData::MX(Mx {
+            preference: parse_number(fields.next("preference")?)?,
+            exchange: parse_name(fields.next("exchange")?, origin)?,
+        }),
+        RecordType::SOA => RData::SOA(Soa {
+            mname: parse_name(fields.next("primary name server")?, origin)?,
+            rname: parse_name(fields.next("responsible mailbox")?, origin)?,
+            serial: parse_number(fields.next("serial")?)?,
+            refresh: parse_ttl(fields.next("refresh")?)?,
+            retry: parse_ttl(fields.next("retry")?)?,
+            expire: parse_ttl(fields.next("expire")?)?,
+            minimum: parse_ttl(fields.next("minimum")?)?,
+        }),
+        RecordType::TXT => {
+            let mut strings = vec![parse_character_string(fields.next("text")?)?];
+            while !fields.is_empty() {
+                strings.push(parse_character_string(fields.next("text")?)?);
+            }
+
+            RData::TXT(strings)
+        }
+        RecordType::HINFO => RData::HINFO(Hinfo {
+            cpu: parse_character_string(fields.next("cpu")?)?,
+            os: parse_character_string(fields.next("os")?)?,
+        }),
+        RecordType::CAA => {
+            let flags = parse_number(fields.next("flags")?)?;
+            let tag_token = fields.next("tag")?;
+            let tag = String::from_utf8(tag_token.raw.clone())
+                .ok()
+                .filter(|tag| !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric()))
+                .ok_or_else(|| {
+                    ZoneError::token(tag_token, ZoneErrorKind::InvalidRecordData("Invalid tag"))
+                })?;
+
+            let value_token = fields.next("value")?;
+            let value = unescape(&value_token.raw)
+                .map_err(|kind| ZoneError::token(value_token, kind))?
+                .into();
+
+            RData::CAA(Caa { flags, tag, value })
+        }
+        RecordType::SRV => RData::SRV(Srv {
+            priority: parse_number(fields.next("priority")?)?,
+            weight: parse_number(fields.next("weight")?)?,
+            port: parse_number(fields.next("port")?)?,
+            target: parse_name(fields.next("target")?, origin)?,
+        }),
+        RecordType::SVCB => RData::SVCB(parse_svcb(fields, origin)?),
+        RecordType::HTTPS => RData::HTTPS(parse_svcb(fields, origin)?),
+        rtype => {
+            return Err(ZoneError::at(
+                fields.line,
+                fields.column,
+                ZoneErrorKind::UnsupportedType(rtype),
+            ))
+        }
+    };
+
+    fields.finish()?;
+
+    Ok(data)
+}
+
+/// State carried between entries of a zone file
+#[derive(Clone)]
+struct ZoneParser {
+    file: Option<PathBuf>,
+    origin: Option<Domain>,
+    /// Set by `$TTL`
+    default_ttl: Option<u32>,
+    /// Last TTL written out explicitly, used when there is no `$TTL`
+    last_ttl: Option<u32>,
+    last_class: RecordClass,
+    last_owner: Option<Domain>,
+    depth: usize,
+}
+
+impl ZoneParser {
+    fn parse(&mut self, input: &[u8], records: &mut Vec<ResourceRecord>) -> Result<(), ZoneError> {
+        for entry in tokenize(input)? {
+            if !entry.leading_blank && entry.tokens[0].raw.starts_with(b"$") {
+                self.directive(&entry, records)?;
+            } else {
+                records.push(self.record(&entry)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn directive(
+        &mut self,
+        entry: &Entry,
+        records: &mut Vec<ResourceRecord>,
+    ) -> Result<(), ZoneError> {
+        // Entries always have at least one token
+        let directive = &entry.tokens[0];
+        let rest = &entry.tokens[1..];
+        let mut fields = Fields {
+            tokens: rest,
+            line: directive.line,
+            column: directive.column + directive.raw.len(),
+        };
+
+        match directive.raw.to_ascii_uppercase().as_slice() {
+            b"$ORIGIN" => {
+                let origin = parse_name(fields.next("origin")?, self.origin.as_ref())?;
+                self.origin = Some(origin);
+            }
+            b"$TTL" => {
+                self.default_ttl = Some(parse_ttl(fields.next("TTL")?)?);
+            }
+            b"$INCLUDE" => {
+                let file_token = fields.next("file name")?;
+                let file_name =
+                    unescape(&file_token.raw).map_err(|kind| ZoneError::token(file_token, kind))?;
+                let file_name = PathBuf::from(String::from_utf8_lossy(&file_name).into_owned());
+
+                let origin = if fields.is_empty() {
+                    self.origin.clone()
+                } else {
+                    Some(parse_name(fields.next("origin")?, self.origin.as_ref())?)
+                };
+                fields.finish()?;
+
+                if self.depth >= MAX_INCLUDE_DEPTH {
+                    return Err(ZoneError::token(file_token, ZoneErrorKind::IncludeDepth));
+                }
+
+                // Relative paths are relative to the including file
+                let path = match self.file.as_deref().and_then(Path::parent) {
+                    Some(parent) => parent.join(file_name),
+                    None => file_name,
+                };
+
+                let input = std::fs::read(&path).map_err(|err| {
+                    ZoneError::token(file_token, ZoneErrorKind::Io(err.to_string()))
+                })?;
+
+                // The included file can't change the origin or owner of this one
+                let mut included = ZoneParser {
+                    file: Some(path),
+                    origin,
+                    depth: self.depth + 1,
+                    ..self.clone()
+                };
+
+                return included
+                    .parse(&input, records)
+                    .map_err(|err| included.locate(err));
+            }
+            _ => {
+                return Err(ZoneError::token(
+                    directive,
+                    ZoneErrorKind::UnknownDirective(lossy(directive)),
+                ))
+            }
+        }
+
+        fields.finish()
+    }
+
+    fn record(&mut self, entry: &Entry) -> Result<ResourceRecord, ZoneError> {
+        let mut fields = Fields {
+            tokens: &entry.tokens,
+            line: entry.line,
+            column: 1,
+        };
+
+        let name = if entry.leading_blank {
+            self.last_owner
+                .clone()
+                .ok_or_else(|| ZoneError::at(entry.line, 1, ZoneErrorKind::NoOwner))?
+        } else {
+            parse_name(fields.next("owner")?, self.origin.as_ref())?
+        };
+
+        // TTL and class can come in either order, and both are optional
+        let mut ttl = None;
+        let mut class = None;
+        for _ in 0..2 {
+            let Some(token) = fields.tokens.first() else {
+                break;
+            };
+
+            if ttl.is_none() && token.raw.first().is_some_and(u8::is_ascii_digit) {
+                ttl = Some(parse_ttl(token)?);
+            } else if let (None, Some(parsed)) = (class, parse_class(token)) {
+                class = Some(parsed);
+            } else {
+                break;
+            }
+
+            fields.next("record type")?;
+        }
+
+        let rtype = parse_type(fields.next("record type")?)?;
+        let data = parse_rdata(rtype, &mut fields, self.origin.as_ref())?;
+
+        if ttl.is_some() {
+            self.last_ttl = ttl;
+        }
+
+        let ttl = ttl
+            .or(self.default_ttl)
+            .or(self.last_ttl)
+            .ok_or_else(|| ZoneError::at(entry.line, 1, ZoneErrorKind::NoTtl))?;
+
+        let rclass = class.unwrap_or(self.last_class);
+        self.last_class = rclass;
+        self.last_owner = Some(name.clone());
+
+        Ok(ResourceRecord {
+            name,
+            rtype,
+            rclass,
+            ttl,
+            data,
+        })
+    }
+
+    /// Errors from the lexer don't know which file they came from
+    fn locate(&self, mut err: ZoneError) -> ZoneError {
+        if err.file.is_none() {
+            err.file.clone_from(&self.file);
+        }
+
+        err
+    }
+}
+
+/// Parses the contents of a zone file (RFC 1035 section 5), relative names
+/// without an `$ORIGIN` are relative to `origin`
+pub fn parse_zone(input: &str, origin: Option<&Domain>) -> Result<Vec<ResourceRecord>, ZoneError> {
+    let mut parser = ZoneParser {
+        file: None,
+        origin: origin.cloned(),
+        default_ttl: None,
+        last_ttl: None,
+        last_class: RecordClass::IN,
+        last_owner: None,
+        depth: 0,
+    };
+
+    let mut records = vec![];
+    parser.parse(input.as_bytes(), &mut records)?;
+
+    Ok(records)
+}
+
+/// Reads and parses a zone file, `$INCLUDE`s are relative to its directory
+pub fn read_zone_file(
+    path: impl AsRef<Path>,
+    origin: Option<&Domain>,
+) -> Result<Vec<ResourceRecord>, ZoneError> {
+    let path = path.as_ref();
+    let mut parser = ZoneParser {
+        file: Some(path.to_path_buf()),
+        origin: origin.cloned(),
+        default_ttl: None,
+        last_ttl: None,
+        last_class: RecordClass::IN,
+        last_owner: None,
+        depth: 0,
+    };
+
+    let input = std::fs::read(path).map_err(|err| ZoneError {
+        file: Some(path.to_path_buf()),
+        line: 0,
+        column: 0,
+        kind: ZoneErrorKind::Io(err.to_string()),
+    })?;
+
+    let mut records = vec![];
+    parser
+        .parse(&input, &mut records)
+        .map_err(|err| parser.locate(err))?;
+
+    Ok(records)
+}
@@ -0,0 +1,215 @@
+use super::{ZoneError, ZoneErrorKind};
+
+/// A single word or quoted string, escapes are left in so names can tell
+/// escaped dots apart from label separators
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Token {
+    pub raw: Vec<u8>,
+    pub quoted: bool,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// One logical line of a zone file, parentheses can make it span multiple lines
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    /// Lines starting with whitespace reuse the previous owner name
+    pub leading_blank: bool,
+    pub tokens: Vec<Token>,
+    pub line: usize,
+}
+
+struct Lexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(byte)
+    }
+
+    fn error(&self, kind: ZoneErrorKind) -> ZoneError {
+        ZoneError::at(self.line, self.column, kind)
+    }
+
+    /// Reads until the closing quote, the opening one has already been consumed
+    fn quoted(&mut self, raw: &mut Vec<u8>) -> Result<(), ZoneError> {
+        loop {
+            match self.bump() {
+                None | Some(b'\n') => return Err(self.error(ZoneErrorKind::UnterminatedString)),
+                Some(b'"') => return Ok(()),
+                Some(b'\\') => {
+                    raw.push(b'\\');
+                    match self.bump() {
+                        Some(byte) => raw.push(byte),
+                        None => return Err(self.error(ZoneErrorKind::UnterminatedString)),
+                    }
+                }
+                Some(byte) => raw.push(byte),
+            }
+        }
+    }
+
+    fn word(&mut self, raw: &mut Vec<u8>) -> Result<(), ZoneError> {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b' ' | b'\t' | b'\r' | b'\n' | b';' | b'(' | b')' => break,
+                b'\\' => {
+                    self.bump();
+                    raw.push(b'\\');
+                    match self.bump() {
+                        Some(byte) => raw.push(byte),
+                        None => return Err(self.error(ZoneErrorKind::InvalidEscape)),
+                    }
+                }
+                // e.g. `alpn="h2,h3"`
+                b'"' => {
+                    self.bump();
+                    self.quoted(raw)?;
+                }
+                _ => {
+                    self.bump();
+                    raw.push(byte);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry(&mut self) -> Result<Option<Entry>, ZoneError> {
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+
+        let mut entry = Entry {
+            leading_blank: matches!(self.peek(), Some(b' ' | b'\t')),
+            tokens: vec![],
+            line: self.line,
+        };
+        let mut depth = 0;
+
+        while let Some(byte) = self.peek() {
+            match byte {
+                b' ' | b'\t' | b'\r' => {
+                    self.bump();
+                }
+                b';' => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.bump();
+                    }
+                }
+                b'\n' => {
+                    self.bump();
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                b'(' => {
+                    self.bump();
+                    depth += 1;
+                }
+                b')' => {
+                    if depth == 0 {
+                        return Err(self.error(ZoneErrorKind::UnbalancedParentheses));
+                    }
+
+                    self.bump();
+                    depth -= 1;
+                }
+                _ => {
+                    let mut token = Token {
+                        raw: vec![],
+                        quoted: byte == b'"',
+                        line: self.line,
+                        column: self.column,
+                    };
+
+                    if token.quoted {
+                        self.bump();
+                        self.quoted(&mut token.raw)?;
+                    } else {
+                        self.word(&mut token.raw)?;
+                    }
+
+                    entry.tokens.push(token);
+                }
+            }
+        }
+
+        if depth != 0 {
+            return Err(self.error(ZoneErrorKind::UnbalancedParentheses));
+        }
+
+        Ok(Some(entry))
+    }
+}
+
+/// Splits a zone file into entries, skipping blank and comment only lines
+pub(crate) fn tokenize(input: &[u8]) -> Result<Vec<Entry>, ZoneError> {
+    let mut lexer = Lexer {
+        input,
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+
+    let mut entries = vec![];
+    while let Some(entry) = lexer.entry()? {
+        if !entry.tokens.is_empty() {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Resolves `\X` and `\DDD` escapes
+pub(crate) fn unescape(raw: &[u8]) -> Result<Vec<u8>, ZoneErrorKind> {
+    let mut ret = Vec::with_capacity(raw.len());
+    let mut iter = raw.iter().copied();
+
+    while let Some(byte) = iter.next() {
+        if byte != b'\\' {
+            ret.push(byte);
+            continue;
+        }
+
+        match iter.next() {
+            Some(digit) if digit.is_ascii_digit() => {
+                let mut value = u16::from(digit - b'0');
+                for _ in 0..2 {
+                    match iter.next() {
+                        Some(digit) if digit.is_ascii_digit() => {
+                            value = value * 10 + u16::from(digit - b'0');
+                        }
+                        _ => return Err(ZoneErrorKind::InvalidEscape),
+                    }
+                }
+
+                ret.push(u8::try_from(value).map_err(|_| ZoneErrorKind::InvalidEscape)?);
+            }
+            Some(escaped) => ret.push(escaped),
+            None => return Err(ZoneErrorKind::InvalidEscape),
+        }
+    }
+
+    Ok(ret)
+}
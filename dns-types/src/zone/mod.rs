@@ -0,0 +1,8 @@
+mod implementation;
+mod lexer;
+mod writer;
+
+#[cfg(test)]
+mod tests;
+
+pub use implementation::*;
@@ -0,0 +1,140 @@
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+
+use crate::zone::*;
+use crate::*;
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+fn a(name: &str, ttl: u32, ip: Ipv4Addr) -> ResourceRecord {
+    ResourceRecord {
+        name: domain(name),
+        rtype: RecordType::A,
+        rclass: RecordClass::IN,
+        ttl,
+        data: RData::A(ip),
+    }
+}
+
+#[test]
+fn read_file_with_include() {
+    let records = read_zone_file("tests/zones/example.com.zone", None).unwrap();
+
+    assert_eq!(records.len(), 5);
+    assert_eq!(
+        records[0],
+        ResourceRecord {
+            name: domain("example.com"),
+            rtype: RecordType::SOA,
+            rclass: RecordClass::IN,
+            ttl: 3600,
+            data: RData::SOA(Soa {
+                mname: domain("ns1.example.com"),
+                rname: domain("admin.example.com"),
+                serial: 2_024_010_101,
+                refresh: 7200,
+                retry: 3600,
+                expire: 1_209_600,
+                minimum: 300,
+            }),
+        }
+    );
+    // Blank owner reuses the previous one
+    assert_eq!(records[1].name, domain("example.com"));
+    assert_eq!(records[1].data, RData::NS(domain("ns1.example.com")));
+    assert_eq!(
+        records[3],
+        a("sub.example.com", 3600, Ipv4Addr::new(192, 0, 2, 3))
+    );
+    // Origin goes back to normal after the include
+    assert_eq!(
+        records[4],
+        a("www.example.com", 300, Ipv4Addr::new(192, 0, 2, 2))
+    );
+}
+
+#[test]
+fn ttl_and_class_inheritance() {
+    let records = parse_zone(
+        "a 60 IN A 192.0.2.1\n\
+         b A 192.0.2.2\n\
+         $TTL 30\n\
+         c CH A 192.0.2.3\n\
+         d A 192.0.2.4\n",
+        Some(&domain("example.com")),
+    )
+    .unwrap();
+
+    // Without $TTL the last TTL is used
+    assert_eq!(
+        records[1],
+        a("b.example.com", 60, Ipv4Addr::new(192, 0, 2, 2))
+    );
+    assert_eq!(records[2].ttl, 30);
+    assert_eq!(records[2].rclass, RecordClass::CH);
+    assert_eq!(records[3].ttl, 30);
+    assert_eq!(records[3].rclass, RecordClass::CH);
+}
+
+#[test]
+fn escaped_labels() {
+    let records = parse_zone(
+        "a\\.b.example.com. 60 A 192.0.2.1\n\
+         \\099\\.d 60 A 192.0.2.2\n",
+        Some(&domain("example.com")),
+    )
+    .unwrap();
+
+    assert_eq!(
+        records[0].name,
+        Domain(vec!["a.b".into(), "example".into(), "com".into()])
+    );
+    assert_eq!(
+        records[1].name,
+        Domain(vec!["c.d".into(), "example".into(), "com".into()])
+    );
+}
+
+#[test]
+fn relative_name_without_origin() {
+    assert_eq!(
+        parse_zone("www 60 A 192.0.2.1", None),
+        Err(ZoneError {
+            file: None,
+            line: 1,
+            column: 1,
+            kind: ZoneErrorKind::NoOrigin
+        })
+    );
+}
+
+#[test]
+fn error_positions() {
+    // Missing the closing parenthesis
+    assert_eq!(
+        parse_zone("a. 60 SOA ns. admin. ( 1 2 3 4\n 5\n", None)
+            .unwrap_err()
+            .kind,
+        ZoneErrorKind::UnbalancedParentheses
+    );
+
+    let err = parse_zone("a. 60 A 192.0.2.1\nb. 60 A 192.0.2\n", None).unwrap_err();
+    assert_eq!((err.line, err.column), (2, 9));
+    assert_eq!(err.kind, ZoneErrorKind::InvalidAddress("192.0.2".into()));
+
+    let err = parse_zone("a. 60 MX 10\n", None).unwrap_err();
+    assert_eq!((err.line, err.column), (1, 12));
+    assert_eq!(err.kind, ZoneErrorKind::MissingField("exchange"));
+}
+
+#[test]
+fn error_in_included_file() {
+    let err = parse_zone("$INCLUDE tests/zones/missing.zone\n", None).unwrap_err();
+    assert!(matches!(err.kind, ZoneErrorKind::Io(_)));
+
+    let err = read_zone_file("tests/zones/sub.zone", None).unwrap_err();
+    assert_eq!(err.file, Some(PathBuf::from("tests/zones/sub.zone")));
+    assert_eq!(err.kind, ZoneErrorKind::NoOrigin);
+}
@@ -0,0 +1,3 @@
+mod directives;
+mod rdata;
+mod writer;
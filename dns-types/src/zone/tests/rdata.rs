@@ -0,0 +1,136 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+
+use crate::zone::*;
+use crate::*;
+
+fn parse_data(line: &str) -> Result<RData, ZoneErrorKind> {
+    let origin = Domain(vec!["example".into(), "com".into()]);
+
+    parse_zone(line, Some(&origin))
+        .map(|mut records| records.remove(0).data)
+        .map_err(|err| err.kind)
+}
+
+#[test]
+fn addresses() {
+    assert_eq!(
+        parse_data("@ 60 A 192.0.2.1"),
+        Ok(RData::A(Ipv4Addr::new(192, 0, 2, 1)))
+    );
+    assert_eq!(
+        parse_data("@ 60 AAAA 2001:db8::1"),
+        Ok(RData::AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))
+    );
+}
+
+#[test]
+fn mx_and_srv() {
+    assert_eq!(
+        parse_data("@ 60 MX 10 mail"),
+        Ok(RData::MX(Mx {
+            preference: 10,
+            exchange: Domain(vec!["mail".into(), "example".into(), "com".into()])
+        }))
+    );
+    assert_eq!(
+        parse_data("_xmpp._tcp 60 SRV 1 2 5222 xmpp.example.org."),
+        Ok(RData::SRV(Srv {
+            priority: 1,
+            weight: 2,
+            port: 5222,
+            target: Domain(vec!["xmpp".into(), "example".into(), "org".into()])
+        }))
+    );
+}
+
+#[test]
+fn aliases() {
+    assert_eq!(
+        parse_data("www 60 CNAME @"),
+        Ok(RData::CNAME(Domain(vec!["example".into(), "com".into()])))
+    );
+    assert_eq!(
+        parse_data("old 60 DNAME example.net."),
+        Ok(RData::DNAME(Domain(vec!["example".into(), "net".into()])))
+    );
+}
+
+#[test]
+fn txt_strings() {
+    assert_eq!(
+        parse_data("@ 60 TXT \"hello world\" bare \"quote \\\" and \\059\" ; comment"),
+        Ok(RData::TXT(vec![
+            Bytes::from_static(b"hello world"),
+            Bytes::from_static(b"bare"),
+            Bytes::from_static(b"quote \" and ;"),
+        ]))
+    );
+
+    assert_eq!(
+        parse_data(&format!("@ 60 TXT \"{}\"", "a".repeat(256))),
+        Err(ZoneErrorKind::StringTooLong)
+    );
+}
+
+#[test]
+fn caa_and_hinfo() {
+    assert_eq!(
+        parse_data("@ 60 CAA 128 issue \"letsencrypt.org\""),
+        Ok(RData::CAA(Caa {
+            flags: 128,
+            tag: "issue".into(),
+            value: Bytes::from_static(b"letsencrypt.org")
+        }))
+    );
+    assert_eq!(
+        parse_data("@ 60 HINFO \"RFC8482\" \"\""),
+        Ok(RData::HINFO(Hinfo {
+            cpu: Bytes::from_static(b"RFC8482"),
+            os: Bytes::new()
+        }))
+    );
+}
+
+#[test]
+fn https_params() {
+    assert_eq!(
+        parse_data("@ 60 HTTPS 1 . port=443 alpn=\"h2,h3\" ipv4hint=192.0.2.1"),
+        Ok(RData::HTTPS(Svcb {
+            priority: 1,
+            target: Domain(vec![]),
+            // Sorted by key
+            params: vec![
+                SvcParam::Alpn(vec![Bytes::from_static(b"h2"), Bytes::from_static(b"h3")]),
+                SvcParam::Port(443),
+                SvcParam::Ipv4Hint(vec![Ipv4Addr::new(192, 0, 2, 1)]),
+            ]
+        }))
+    );
+}
+
+#[test]
+fn generic_data() {
+    assert_eq!(
+        parse_data("@ 60 TYPE65280 \\# 3 ABCDEF"),
+        Ok(RData::Unknown(Bytes::from_static(&[0xAB, 0xCD, 0xEF])))
+    );
+    // Known types are still decoded
+    assert_eq!(
+        parse_data("@ 60 A \\# 4 C0000201"),
+        Ok(RData::A(Ipv4Addr::new(192, 0, 2, 1)))
+    );
+    assert_eq!(
+        parse_data("@ 60 NULL 1"),
+        Err(ZoneErrorKind::UnsupportedType(RecordType::NULL))
+    );
+}
+
+#[test]
+fn trailing_data() {
+    assert_eq!(
+        parse_data("@ 60 CNAME a b"),
+        Err(ZoneErrorKind::TrailingData("b".into()))
+    );
+}
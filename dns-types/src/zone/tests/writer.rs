@@ -0,0 +1,202 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+
+use crate::zone::*;
+use crate::*;
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+fn record(rtype: RecordType, data: RData) -> ResourceRecord {
+    ResourceRecord {
+        name: domain("example.com"),
+        rtype,
+        rclass: RecordClass::IN,
+        ttl: 300,
+        data,
+    }
+}
+
+/// Writing a record and reading it back in should give the same record
+fn assert_round_trip(record: &ResourceRecord) {
+    let text = record.to_string();
+
+    assert_eq!(
+        parse_zone(&text, None).map(|records| records[0].clone()),
+        Ok(record.clone()),
+        "{text}"
+    );
+}
+
+#[test]
+fn record_format() {
+    assert_eq!(
+        record(RecordType::A, RData::A(Ipv4Addr::new(192, 0, 2, 1))).to_string(),
+        "example.com.\t300\tIN\tA\t192.0.2.1"
+    );
+    assert_eq!(
+        ResourceRecord {
+            rclass: RecordClass::Unknown(32),
+            ..record(
+                RecordType::Unknown(65280),
+                RData::Unknown(Bytes::from_static(&[0xAB, 0x01]))
+            )
+        }
+        .to_string(),
+        "example.com.\t300\tCLASS32\tTYPE65280\t\\# 2 AB01"
+    );
+}
+
+#[test]
+fn escaped_names() {
+    let name = Domain(vec!["a.b".into(), "c d".into(), "@".into()]);
+
+    assert_eq!(name.to_string(), "a\\.b.c\\032d.\\@.");
+    assert_round_trip(&ResourceRecord {
+        name: name.clone(),
+        ..record(RecordType::CNAME, RData::CNAME(name))
+    });
+}
+
+#[test]
+fn round_trips() {
+    let records = [
+        record(
+            RecordType::AAAA,
+            RData::AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        ),
+        record(RecordType::NS, RData::NS(domain("ns1.example.com"))),
+        record(
+            RecordType::MX,
+            RData::MX(Mx {
+                preference: 10,
+                exchange: domain("mail.example.com"),
+            }),
+        ),
+        record(
+            RecordType::SOA,
+            RData::SOA(Soa {
+                mname: domain("ns1.example.com"),
+                rname: domain("admin.example.com"),
+                serial: 1,
+                refresh: 2,
+                retry: 3,
+                expire: 4,
+                minimum: 5,
+            }),
+        ),
+        record(
+            RecordType::TXT,
+            RData::TXT(vec![
+                Bytes::from_static(b"v=spf1 -all"),
+                Bytes::from_static(b"\"quoted\" \\ ;"),
+                Bytes::from_static(&[0x00, 0xFF]),
+            ]),
+        ),
+        record(
+            RecordType::HINFO,
+            RData::HINFO(Hinfo {
+                cpu: Bytes::from_static(b"RFC8482"),
+                os: Bytes::new(),
+            }),
+        ),
+        record(
+            RecordType::CAA,
+            RData::CAA(Caa {
+                flags: 0,
+                tag: "issue".into(),
+                value: Bytes::from_static(b"letsencrypt.org"),
+            }),
+        ),
+        record(
+            RecordType::SRV,
+            RData::SRV(Srv {
+                priority: 1,
+                weight: 2,
+                port: 5222,
+                target: domain("xmpp.example.com"),
+            }),
+        ),
+        record(
+            RecordType::HTTPS,
+            RData::HTTPS(Svcb {
+                priority: 1,
+                target: Domain(vec![]),
+                params: vec![
+                    SvcParam::Mandatory(vec![1, 3]),
+                    SvcParam::Alpn(vec![
+                        Bytes::from_static(b"h2"),
+                        Bytes::from_static(b"odd,one"),
+                    ]),
+                    SvcParam::NoDefaultAlpn,
+                    SvcParam::Port(443),
+                    SvcParam::Ipv4Hint(vec![
+                        Ipv4Addr::new(192, 0, 2, 1),
+                        Ipv4Addr::new(192, 0, 2, 2),
+                    ]),
+                    SvcParam::Ech(Bytes::from_static(b"\x00\x45\xfe\x0d")),
+                    SvcParam::Ipv6Hint(vec![Ipv6Addr::LOCALHOST]),
+                    SvcParam::Unknown(1024, Bytes::from_static(b"a\"b")),
+                ],
+            }),
+        ),
+        record(RecordType::NULL, RData::Unknown(Bytes::new())),
+    ];
+
+    for record in &records {
+        assert_round_trip(record);
+    }
+}
+
+#[test]
+fn message_layout() {
+    let msg = Message {
+        header: Header {
+            id: 1234,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: true,
+            is_truncated: false,
+            should_recurse: true,
+            recursion_available: false,
+            _z: 0,
+            rescode: ResCode::NameError,
+            questions: 1,
+            answer_records: 0,
+            authority_records: 1,
+            additional_records: 1,
+        },
+        questions: vec![Question {
+            name: domain("nope.example.com"),
+            qtype: RecordType::A,
+            qclass: RecordClass::IN,
+        }],
+        answers: vec![],
+        authorities: vec![record(RecordType::NS, RData::NS(domain("ns1.example.com")))],
+        additional: vec![],
+        edns: Some(Edns::new(1232)),
+    };
+
+    assert_eq!(
+        msg.to_string(),
+        ";; ->>HEADER<<- opcode: QUERY, status: NXDOMAIN, id: 1234\n\
+         ;; flags: qr aa rd; QUERY: 1, ANSWER: 0, AUTHORITY: 1, ADDITIONAL: 1\n\
+         \n\
+         ;; OPT PSEUDOSECTION:\n\
+         ; EDNS: version: 0, flags:; udp: 1232\n\
+         \n\
+         ;; QUESTION SECTION:\n\
+         ;nope.example.com.\t\tIN\tA\n\
+         \n\
+         ;; AUTHORITY SECTION:\n\
+         example.com.\t300\tIN\tNS\tns1.example.com.\n"
+    );
+
+    // Only the records are left once comments are stripped
+    assert_eq!(
+        parse_zone(&msg.to_string(), None),
+        Ok(msg.authorities.clone())
+    );
+}
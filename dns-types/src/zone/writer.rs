@@ -0,0 +1,307 @@
+//! Presentation format (RFC 1035 section 5.1) for records and messages, the
+//! record lines can be read back in by [`super::parse_zone`]
+
+use std::fmt::{Display, Formatter, Result};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{Message, OpCode, RData, RecordClass, RecordType, ResCode, ResourceRecord, SvcParam};
+
+struct TypeName(RecordType);
+
+impl Display for TypeName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "TYPE{}", u16::from(self.0)),
+        }
+    }
+}
+
+struct ClassName(RecordClass);
+
+impl Display for ClassName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "CLASS{}", u16::from(self.0)),
+        }
+    }
+}
+
+/// Writes bytes escaping `special` characters, and anything unprintable as `\DDD`
+fn write_escaped(f: &mut Formatter<'_>, data: &[u8], special: &[u8]) -> Result {
+    for byte in data {
+        if special.contains(byte) {
+            write!(f, "\\{}", char::from(*byte))?;
+        } else if (0x20..=0x7E).contains(byte) {
+            write!(f, "{}", char::from(*byte))?;
+        } else {
+            write!(f, "\\{byte:03}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_character_string(f: &mut Formatter<'_>, data: &[u8]) -> Result {
+    f.write_str("\"")?;
+    write_escaped(f, data, b"\"\\")?;
+    f.write_str("\"")
+}
+
+fn svc_key_name(key: u16) -> String {
+    match key {
+        0 => "mandatory".into(),
+        1 => "alpn".into(),
+        2 => "no-default-alpn".into(),
+        3 => "port".into(),
+        4 => "ipv4hint".into(),
+        5 => "ech".into(),
+        6 => "ipv6hint".into(),
+        key => format!("key{key}"),
+    }
+}
+
+fn write_list<T: Display>(f: &mut Formatter<'_>, items: &[T]) -> Result {
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 {
+            f.write_str(",")?;
+        }
+
+        write!(f, "{item}")?;
+    }
+
+    Ok(())
+}
+
+impl Display for SvcParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(&svc_key_name(self.key()))?;
+
+        match self {
+            SvcParam::Mandatory(keys) => {
+                let keys: Vec<String> = keys.iter().map(|key| svc_key_name(*key)).collect();
+                f.write_str("=")?;
+                write_list(f, &keys)
+            }
+            SvcParam::Alpn(ids) => {
+                f.write_str("=\"")?;
+                for (i, id) in ids.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+
+                    write_escaped(f, id, b"\",\\")?;
+                }
+                f.write_str("\"")
+            }
+            SvcParam::NoDefaultAlpn => Ok(()),
+            SvcParam::Port(port) => write!(f, "={port}"),
+            SvcParam::Ipv4Hint(hints) => {
+                f.write_str("=")?;
+                write_list(f, hints)
+            }
+            SvcParam::Ech(config) => write!(f, "={}", STANDARD.encode(config)),
+            SvcParam::Ipv6Hint(hints) => {
+                f.write_str("=")?;
+                write_list(f, hints)
+            }
+            SvcParam::Unknown(_, data) => {
+                f.write_str("=\"")?;
+                write_escaped(f, data, b"\"\\")?;
+                f.write_str("\"")
+            }
+        }
+    }
+}
+
+impl Display for RData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            RData::A(ip) => write!(f, "{ip}"),
+            RData::AAAA(ip) => write!(f, "{ip}"),
+            RData::NS(domain)
+            | RData::CNAME(domain)
+            | RData::DNAME(domain)
+            | RData::PTR(domain) => {
+                write!(f, "{domain}")
+            }
+            RData::MX(mx) => write!(f, "{} {}", mx.preference, mx.exchange),
+            RData::SOA(soa) => write!(
+                f,
+                "{} {} {} {} {} {} {}",
+                soa.mname, soa.rname, soa.serial, soa.refresh, soa.retry, soa.expire, soa.minimum
+            ),
+            RData::TXT(strings) => {
+                for (i, string) in strings.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" ")?;
+                    }
+
+                    write_character_string(f, string)?;
+                }
+
+                Ok(())
+            }
+            RData::HINFO(hinfo) => {
+                write_character_string(f, &hinfo.cpu)?;
+                f.write_str(" ")?;
+                write_character_string(f, &hinfo.os)
+            }
+            RData::CAA(caa) => {
+                write!(f, "{} {} ", caa.flags, caa.tag)?;
+                write_character_string(f, &caa.value)
+            }
+            RData::SRV(srv) => write!(
+                f,
+                "{} {} {} {}",
+                srv.priority, srv.weight, srv.port, srv.target
+            ),
+            RData::SVCB(svcb) | RData::HTTPS(svcb) => {
+                write!(f, "{} {}", svcb.priority, svcb.target)?;
+                for param in &svcb.params {
+                    write!(f, " {param}")?;
+                }
+
+                Ok(())
+            }
+            // Generic format from RFC 3597
+            RData::Unknown(data) => {
+                write!(f, "\\# {}", data.len())?;
+                if !data.is_empty() {
+                    f.write_str(" ")?;
+                }
+
+                for byte in data {
+                    write!(f, "{byte:02X}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for ResourceRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            self.name,
+            self.ttl,
+            ClassName(self.rclass),
+            TypeName(self.rtype),
+            self.data
+        )
+    }
+}
+
+fn rescode_name(rescode: ResCode) -> String {
+    match rescode {
+        ResCode::NoError => "NOERROR".into(),
+        ResCode::FormatError => "FORMERR".into(),
+        ResCode::ServerFailure => "SERVFAIL".into(),
+        ResCode::NameError => "NXDOMAIN".into(),
+        ResCode::NotImplemented => "NOTIMP".into(),
+        ResCode::Refused => "REFUSED".into(),
+        ResCode::Reserved(code) => format!("RCODE{code}"),
+    }
+}
+
+fn opcode_name(opcode: OpCode) -> String {
+    match opcode {
+        OpCode::Query => "QUERY".into(),
+        OpCode::IQuery => "IQUERY".into(),
+        OpCode::Status => "STATUS".into(),
+        OpCode::Reserved(code) => format!("OPCODE{code}"),
+    }
+}
+
+/// Same layout as dig, everything except records is commented out
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let header = &self.header;
+
+        writeln!(
+            f,
+            ";; ->>HEADER<<- opcode: {}, status: {}, id: {}",
+            opcode_name(header.opcode),
+            rescode_name(header.rescode),
+            header.id
+        )?;
+
+        let flags = [
+            (header.is_response, " qr"),
+            (header.is_authoritative, " aa"),
+            (header.is_truncated, " tc"),
+            (header.should_recurse, " rd"),
+            (header.recursion_available, " ra"),
+        ];
+
+        f.write_str(";; flags:")?;
+        for (set, name) in flags {
+            if set {
+                f.write_str(name)?;
+            }
+        }
+
+        writeln!(
+            f,
+            "; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            self.questions.len(),
+            self.answers.len(),
+            self.authorities.len(),
+            self.additional.len() + usize::from(self.edns.is_some())
+        )?;
+
+        if let Some(edns) = &self.edns {
+            writeln!(f, "\n;; OPT PSEUDOSECTION:")?;
+            write!(f, "; EDNS: version: {}, flags:", edns.version)?;
+            if edns.dnssec_ok {
+                f.write_str(" do")?;
+            }
+            writeln!(f, "; udp: {}", edns.udp_payload_size)?;
+
+            for option in &edns.options {
+                write!(f, "; OPTION {}: ", option.code)?;
+                for byte in &option.data {
+                    write!(f, "{byte:02X}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        if !self.questions.is_empty() {
+            writeln!(f, "\n;; QUESTION SECTION:")?;
+            for question in &self.questions {
+                writeln!(
+                    f,
+                    ";{}\t\t{}\t{}",
+                    question.name,
+                    ClassName(question.qclass),
+                    TypeName(question.qtype)
+                )?;
+            }
+        }
+
+        let sections = [
+            ("ANSWER", &self.answers),
+            ("AUTHORITY", &self.authorities),
+            ("ADDITIONAL", &self.additional),
+        ];
+
+        for (name, records) in sections {
+            if records.is_empty() {
+                continue;
+            }
+
+            writeln!(f, "\n;; {name} SECTION:")?;
+            for record in records {
+                writeln!(f, "{record}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
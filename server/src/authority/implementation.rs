@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+use types::{Domain, RData, RecordClass, RecordType, ResCode, ResourceRecord, Soa};
+
+/// How many CNAMEs inside a zone get followed before giving up
+const MAX_CNAME_CHAIN: usize = 8;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ZoneError {
+    #[error("Zone {0} has no SOA record at its origin")]
+    MissingSoa(Domain),
+    #[error("Zone {0} has more than one SOA record")]
+    MultipleSoa(Domain),
+    #[error("Record for {name} is outside of zone {origin}")]
+    OutOfZone { name: Domain, origin: Domain },
+    #[error("{0} has a CNAME record alongside other data")]
+    CnameAndOtherData(Domain),
+}
+
+/// Is `name` equal to or below `parent`, both have to be lowercase
+fn is_subdomain(name: &Domain, parent: &Domain) -> bool {
+    name.0.ends_with(&parent.0)
+}
+
+/// Result of looking a question up in a zone
+#[derive(Debug, PartialEq, Eq)]
+pub struct ZoneAnswer {
+    pub rescode: ResCode,
+    /// Set for everything except referrals to child zones
+    pub is_authoritative: bool,
+    pub answers: Vec<ResourceRecord>,
+    pub authorities: Vec<ResourceRecord>,
+    pub additional: Vec<ResourceRecord>,
+}
+
+/// A single zone this server is authoritative for
+#[derive(Debug)]
+pub struct Zone {
+    origin: Domain,
+    soa: ResourceRecord,
+    /// Records keyed by their lowercase owner name
+    records: HashMap<Domain, Vec<ResourceRecord>>,
+    /// Every name that has records, plus all of their ancestors inside the zone,
+    /// so empty non-terminals aren't reported as NXDOMAIN
+    nodes: HashSet<Domain>,
+}
+
+impl Zone {
+    /// Builds a zone out of all of its records, which have to include exactly one SOA at `origin`
+    pub fn new(origin: &Domain, records: Vec<ResourceRecord>) -> Result<Zone, ZoneError> {
+        let origin = origin.to_lowercase();
+        let mut soa = None;
+        let mut by_name: HashMap<Domain, Vec<ResourceRecord>> = HashMap::new();
+        let mut nodes = HashSet::new();
+
+        for record in records {
+            let name = record.name.to_lowercase();
+            if !is_subdomain(&name, &origin) {
+                return Err(ZoneError::OutOfZone {
+                    name: record.name,
+                    origin,
+                });
+            }
+
+            if record.rtype == RecordType::SOA && name == origin {
+                if soa.is_some() {
+                    return Err(ZoneError::MultipleSoa(origin));
+                }
+
+                soa = Some(record.clone());
+            }
+
+            for depth in origin.0.len()..=name.0.len() {
+                nodes.insert(Domain(name.0[name.0.len() - depth..].to_vec()));
+            }
+
+            by_name.entry(name).or_default().push(record);
+        }
+
+        for (name, records) in &by_name {
+            let has_cname = records.iter().any(|r| r.rtype == RecordType::CNAME);
+            if has_cname && records.len() > 1 {
+                return Err(ZoneError::CnameAndOtherData(name.clone()));
+            }
+        }
+
+        let Some(soa) = soa else {
+            return Err(ZoneError::MissingSoa(origin));
+        };
+
+        Ok(Zone {
+            origin,
+            soa,
+            records: by_name,
+            nodes,
+        })
+    }
+
+    pub fn origin(&self) -> &Domain {
+        &self.origin
+    }
+
+    /// Does this zone hold the answer (or a referral) for `name`
+    pub fn contains(&self, name: &Domain) -> bool {
+        is_subdomain(&name.to_lowercase(), &self.origin)
+    }
+
+    /// Answers a question per RFC 1034 section 4.3.2, `qname` has to be inside the zone
+    pub fn lookup(&self, qname: &Domain, qtype: RecordType, qclass: RecordClass) -> ZoneAnswer {
+        let mut answer = ZoneAnswer {
+            rescode: ResCode::NoError,
+            is_authoritative: true,
+            answers: vec![],
+            authorities: vec![],
+            additional: vec![],
+        };
+
+        if qclass != self.soa.rclass && qclass != RecordClass::ANY {
+            answer.rescode = ResCode::Refused;
+            answer.is_authoritative = false;
+            return answer;
+        }
+
+        let mut qname = qname.clone();
+        for _ in 0..MAX_CNAME_CHAIN {
+            let name = qname.to_lowercase();
+            if !is_subdomain(&name, &self.origin) {
+                // CNAME led out of the zone, the client has to continue from here
+                return answer;
+            }
+
+            if let Some(ns) = self.find_delegation(&name) {
+                answer.is_authoritative = !answer.answers.is_empty();
+                answer.additional = self.glue(&ns);
+                answer.authorities = ns;
+                return answer;
+            }
+
+            let records = if let Some(records) = self.records.get(&name) {
+                records
+            } else if self.nodes.contains(&name) {
+                // Empty non-terminal, the name exists but has no data
+                answer.authorities.push(self.negative_soa());
+                return answer;
+            } else if let Some(records) = self.find_wildcard(&name) {
+                records
+            } else {
+                answer.rescode = ResCode::NameError;
+                answer.authorities.push(self.negative_soa());
+                return answer;
+            };
+
+            // Wildcard records take on the name that was asked for
+            let owner = qname.clone();
+
+            if let Some(cname) = records.iter().find(|r| r.rtype == RecordType::CNAME) {
+                answer.answers.push(ResourceRecord {
+                    name: owner,
+                    ..cname.clone()
+                });
+
+                if qtype == RecordType::CNAME {
+                    return answer;
+                }
+
+                if let RData::CNAME(target) = &cname.data {
+                    qname = target.clone();
+                    continue;
+                }
+
+                return answer;
+            }
+
+            let matching: Vec<ResourceRecord> = records
+                .iter()
+                .filter(|r| qtype == RecordType::ANY || r.rtype == qtype)
+                .map(|r| ResourceRecord {
+                    name: owner.clone(),
+                    ..r.clone()
+                })
+                .collect();
+
+            if matching.is_empty() {
+                answer.authorities.push(self.negative_soa());
+            } else {
+                answer.answers.extend(matching);
+            }
+
+            return answer;
+        }
+
+        answer
+    }
+
+    /// NS records of the closest delegation point between the origin and `name`
+    fn find_delegation(&self, name: &Domain) -> Option<Vec<ResourceRecord>> {
+        // Start right below the origin, the origin's own NS records aren't a delegation
+        for depth in self.origin.0.len() + 1..=name.0.len() {
+            let cut = Domain(name.0[name.0.len() - depth..].to_vec());
+            let Some(records) = self.records.get(&cut) else {
+                continue;
+            };
+
+            let ns: Vec<ResourceRecord> = records
+                .iter()
+                .filter(|r| r.rtype == RecordType::NS)
+                .cloned()
+                .collect();
+
+            if !ns.is_empty() {
+                return Some(ns);
+            }
+        }
+
+        None
+    }
+
+    /// Records at the wildcard below the closest encloser of `name`
+    fn find_wildcard(&self, name: &Domain) -> Option<&Vec<ResourceRecord>> {
+        for depth in (self.origin.0.len()..name.0.len()).rev() {
+            let encloser = &name.0[name.0.len() - depth..];
+            if !self.nodes.contains(&Domain(encloser.to_vec())) {
+                continue;
+            }
+
+            let mut wildcard = vec!["*".to_string()];
+            wildcard.extend_from_slice(encloser);
+
+            return self.records.get(&Domain(wildcard));
+        }
+
+        None
+    }
+
+    /// Address records for name servers inside this zone
+    fn glue(&self, ns: &[ResourceRecord]) -> Vec<ResourceRecord> {
+        let mut glue = vec![];
+        for record in ns {
+            let RData::NS(target) = &record.data else {
+                continue;
+            };
+
+            if let Some(records) = self.records.get(&target.to_lowercase()) {
+                glue.extend(
+                    records
+                        .iter()
+                        .filter(|r| matches!(r.rtype, RecordType::A | RecordType::AAAA))
+                        .cloned(),
+                );
+            }
+        }
+
+        glue
+    }
+
+    /// SOA for the authority section of negative answers, see RFC 2308 section 3
+    fn negative_soa(&self) -> ResourceRecord {
+        let mut soa = self.soa.clone();
+        if let RData::SOA(Soa { minimum, .. }) = &soa.data {
+            soa.ttl = soa.ttl.min(*minimum);
+        }
+
+        soa
+    }
+}
+
+/// All the zones this server is authoritative for
+#[derive(Debug, Default)]
+pub struct Catalog {
+    zones: Vec<Zone>,
+}
+
+impl Catalog {
+    pub fn new() -> Catalog {
+        Catalog::default()
+    }
+
+    pub fn insert(&mut self, zone: Zone) {
+        self.zones.push(zone);
+    }
+
+    /// Most specific zone containing `name`
+    pub fn find(&self, name: &Domain) -> Option<&Zone> {
+        self.zones
+            .iter()
+            .filter(|zone| zone.contains(name))
+            .max_by_key(|zone| zone.origin.0.len())
+    }
+}
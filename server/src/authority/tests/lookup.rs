@@ -0,0 +1,251 @@
+use std::net::Ipv4Addr;
+
+use types::{Domain, RData, RecordClass, RecordType, ResCode, ResourceRecord, Soa};
+
+use crate::authority::*;
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+fn record(name: &str, data: RData) -> ResourceRecord {
+    ResourceRecord {
+        name: domain(name),
+        rtype: match data {
+            RData::A(_) => RecordType::A,
+            RData::NS(_) => RecordType::NS,
+            RData::CNAME(_) => RecordType::CNAME,
+            RData::SOA(_) => RecordType::SOA,
+            _ => RecordType::Unknown(0),
+        },
+        rclass: RecordClass::IN,
+        ttl: 3600,
+        data,
+    }
+}
+
+fn soa() -> ResourceRecord {
+    record(
+        "example.com",
+        RData::SOA(Soa {
+            mname: domain("ns1.example.com"),
+            rname: domain("admin.example.com"),
+            serial: 1,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1_209_600,
+            minimum: 300,
+        }),
+    )
+}
+
+fn zone() -> Zone {
+    Zone::new(
+        &domain("example.com"),
+        vec![
+            soa(),
+            record("example.com", RData::NS(domain("ns1.example.com"))),
+            record("ns1.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 1))),
+            record("www.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 2))),
+            record("alias.example.com", RData::CNAME(domain("www.example.com"))),
+            record("away.example.com", RData::CNAME(domain("example.org"))),
+            record("*.wild.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 3))),
+            record("a.empty.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 4))),
+            record("sub.example.com", RData::NS(domain("ns.sub.example.com"))),
+            record("ns.sub.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 5))),
+        ],
+    )
+    .unwrap()
+}
+
+fn negative_soa() -> ResourceRecord {
+    ResourceRecord { ttl: 300, ..soa() }
+}
+
+#[test]
+fn exact_answer() {
+    assert_eq!(
+        zone().lookup(&domain("WWW.example.com"), RecordType::A, RecordClass::IN),
+        ZoneAnswer {
+            rescode: ResCode::NoError,
+            is_authoritative: true,
+            answers: vec![record(
+                "WWW.example.com",
+                RData::A(Ipv4Addr::new(192, 0, 2, 2))
+            )],
+            authorities: vec![],
+            additional: vec![],
+        }
+    );
+}
+
+#[test]
+fn no_data() {
+    assert_eq!(
+        zone().lookup(&domain("www.example.com"), RecordType::MX, RecordClass::IN),
+        ZoneAnswer {
+            rescode: ResCode::NoError,
+            is_authoritative: true,
+            answers: vec![],
+            authorities: vec![negative_soa()],
+            additional: vec![],
+        }
+    );
+}
+
+#[test]
+fn empty_non_terminal() {
+    let answer = zone().lookup(&domain("empty.example.com"), RecordType::A, RecordClass::IN);
+
+    assert_eq!(answer.rescode, ResCode::NoError);
+    assert_eq!(answer.authorities, vec![negative_soa()]);
+}
+
+#[test]
+fn name_error() {
+    assert_eq!(
+        zone().lookup(&domain("nope.example.com"), RecordType::A, RecordClass::IN),
+        ZoneAnswer {
+            rescode: ResCode::NameError,
+            is_authoritative: true,
+            answers: vec![],
+            authorities: vec![negative_soa()],
+            additional: vec![],
+        }
+    );
+}
+
+#[test]
+fn follows_cname_in_zone() {
+    let answer = zone().lookup(&domain("alias.example.com"), RecordType::A, RecordClass::IN);
+
+    assert_eq!(
+        answer.answers,
+        vec![
+            record("alias.example.com", RData::CNAME(domain("www.example.com"))),
+            record("www.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 2))),
+        ]
+    );
+}
+
+#[test]
+fn cname_out_of_zone() {
+    let answer = zone().lookup(&domain("away.example.com"), RecordType::A, RecordClass::IN);
+
+    assert_eq!(answer.rescode, ResCode::NoError);
+    assert_eq!(
+        answer.answers,
+        vec![record(
+            "away.example.com",
+            RData::CNAME(domain("example.org"))
+        )]
+    );
+    assert_eq!(answer.authorities, vec![]);
+}
+
+#[test]
+fn wildcard() {
+    let answer = zone().lookup(
+        &domain("a.b.wild.example.com"),
+        RecordType::A,
+        RecordClass::IN,
+    );
+
+    assert_eq!(
+        answer.answers,
+        vec![record(
+            "a.b.wild.example.com",
+            RData::A(Ipv4Addr::new(192, 0, 2, 3))
+        )]
+    );
+}
+
+#[test]
+fn wildcard_blocked_by_existing_name() {
+    // `empty.example.com.` exists, so `*.example.com.` couldn't apply even if it existed
+    let answer = zone().lookup(
+        &domain("b.empty.example.com"),
+        RecordType::A,
+        RecordClass::IN,
+    );
+
+    assert_eq!(answer.rescode, ResCode::NameError);
+}
+
+#[test]
+fn referral_with_glue() {
+    assert_eq!(
+        zone().lookup(
+            &domain("www.sub.example.com"),
+            RecordType::A,
+            RecordClass::IN
+        ),
+        ZoneAnswer {
+            rescode: ResCode::NoError,
+            is_authoritative: false,
+            answers: vec![],
+            authorities: vec![record(
+                "sub.example.com",
+                RData::NS(domain("ns.sub.example.com"))
+            )],
+            additional: vec![record(
+                "ns.sub.example.com",
+                RData::A(Ipv4Addr::new(192, 0, 2, 5))
+            )],
+        }
+    );
+}
+
+#[test]
+fn invalid_zones() {
+    assert_eq!(
+        Zone::new(
+            &domain("example.com"),
+            vec![record(
+                "www.example.com",
+                RData::A(Ipv4Addr::new(192, 0, 2, 2))
+            )]
+        )
+        .unwrap_err(),
+        ZoneError::MissingSoa(domain("example.com"))
+    );
+
+    assert!(matches!(
+        Zone::new(
+            &domain("example.com"),
+            vec![
+                soa(),
+                record("example.org", RData::A(Ipv4Addr::new(192, 0, 2, 2)))
+            ]
+        ),
+        Err(ZoneError::OutOfZone { .. })
+    ));
+}
+
+#[test]
+fn catalog_picks_closest_zone() {
+    let child = Zone::new(
+        &domain("sub.example.com"),
+        vec![ResourceRecord {
+            name: domain("sub.example.com"),
+            ..soa()
+        }],
+    )
+    .unwrap();
+
+    let mut catalog = Catalog::new();
+    catalog.insert(zone());
+    catalog.insert(child);
+
+    assert_eq!(
+        catalog
+            .find(&domain("www.sub.example.com"))
+            .map(Zone::origin),
+        Some(&domain("sub.example.com"))
+    );
+    assert_eq!(
+        catalog.find(&domain("www.example.com")).map(Zone::origin),
+        Some(&domain("example.com"))
+    );
+    assert!(catalog.find(&domain("example.org")).is_none());
+}
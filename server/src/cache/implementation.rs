@@ -0,0 +1,370 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use types::{
+    Domain, Message, Question, RData, RecordClass, RecordType, ResCode, ResourceRecord, Soa,
+};
+
+use crate::chain::{Chain, ChainError, Step};
+
+/// Final outcome of looking up a name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    Answer(Vec<ResourceRecord>),
+    /// The name doesn't exist at all, with the SOA of the zone saying so
+    NameError(ResourceRecord),
+    /// The name exists but has no records of the asked for type
+    NoData(ResourceRecord),
+}
+
+impl Resolution {
+    fn records_mut(&mut self) -> &mut [ResourceRecord] {
+        match self {
+            Resolution::Answer(records) => records,
+            Resolution::NameError(soa) | Resolution::NoData(soa) => std::slice::from_mut(soa),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// Always lowercase
+    name: Domain,
+    /// `None` for NXDOMAIN, which covers every type
+    rtype: Option<RecordType>,
+    rclass: RecordClass,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    data: Resolution,
+    expires: Instant,
+    /// Position in the LRU order
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Keys ordered from least to most recently used
+    lru: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl CacheInner {
+    fn touch(&mut self, key: &CacheKey) {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+
+        self.lru.remove(&entry.last_used);
+        self.tick += 1;
+        entry.last_used = self.tick;
+        self.lru.insert(self.tick, key.clone());
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+        }
+    }
+}
+
+/// Record sets and negative answers learned while resolving, shared between all requests
+#[derive(Debug)]
+pub struct Cache {
+    inner: Mutex<CacheInner>,
+    max_entries: usize,
+}
+
+impl Cache {
+    /// Cache holding at most `max_entries` entries, the least recently used ones get evicted first
+    pub fn new(max_entries: usize) -> Cache {
+        Cache {
+            inner: Mutex::new(CacheInner::default()),
+            max_entries,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheInner> {
+        // A panic while holding the lock can't leave the maps in a broken state
+        self.inner
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn store(&self, key: &CacheKey, data: Resolution, ttl: u32, now: Instant) {
+        if ttl == 0 || self.max_entries == 0 {
+            return;
+        }
+
+        let mut inner = self.lock();
+
+        inner.remove(key);
+        while inner.entries.len() >= self.max_entries {
+            let Some((_, oldest)) = inner.lru.pop_first() else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+        }
+
+        inner.entries.insert(
+            key.clone(),
+            CacheEntry {
+                data,
+                expires: now + Duration::from_secs(ttl.into()),
+                last_used: 0,
+            },
+        );
+        inner.touch(key);
+    }
+
+    fn load(&self, key: &CacheKey, now: Instant) -> Option<Resolution> {
+        let mut inner = self.lock();
+        let entry = inner.entries.get(key)?;
+
+        let remaining = entry.expires.saturating_duration_since(now).as_secs();
+        if remaining == 0 {
+            inner.remove(key);
+            return None;
+        }
+
+        let mut data = entry.data.clone();
+
+        // Can't be more than the original u32 TTL
+        #[allow(clippy::cast_possible_truncation)]
+        for record in data.records_mut() {
+            record.ttl = remaining as u32;
+        }
+
+        inner.touch(key);
+
+        Some(data)
+    }
+
+    /// Stores records, grouped into record sets by name, type and class
+    pub fn insert(&self, records: &[ResourceRecord]) {
+        self.insert_at(records, Instant::now());
+    }
+
+    pub(crate) fn insert_at(&self, records: &[ResourceRecord], now: Instant) {
+        let mut sets: HashMap<CacheKey, Vec<ResourceRecord>> = HashMap::new();
+        for record in records {
+            let key = CacheKey {
+                name: record.name.to_lowercase(),
+                rtype: Some(record.rtype),
+                rclass: record.rclass,
+            };
+
+            sets.entry(key).or_default().push(record.clone());
+        }
+
+        for (key, records) in sets {
+            // A record set lives as long as its shortest TTL (RFC 2181 section 5.2)
+            let ttl = records.iter().map(|r| r.ttl).min().unwrap_or(0);
+            self.store(&key, Resolution::Answer(records), ttl, now);
+        }
+    }
+
+    /// Remembers that `name` doesn't exist, or with `rtype` that it has no records of that type.
+    /// Lives for the SOA's TTL or minimum, whichever is lower (RFC 2308 section 5)
+    pub fn insert_negative(
+        &self,
+        name: &Domain,
+        rtype: Option<RecordType>,
+        rclass: RecordClass,
+        soa: &ResourceRecord,
+    ) {
+        self.insert_negative_at(name, rtype, rclass, soa, Instant::now());
+    }
+
+    pub(crate) fn insert_negative_at(
+        &self,
+        name: &Domain,
+        rtype: Option<RecordType>,
+        rclass: RecordClass,
+        soa: &ResourceRecord,
+        now: Instant,
+    ) {
+        let RData::SOA(Soa { minimum, .. }) = soa.data else {
+            return;
+        };
+
+        let key = CacheKey {
+            name: name.to_lowercase(),
+            rtype,
+            rclass,
+        };
+        let data = match rtype {
+            Some(_) => Resolution::NoData(soa.clone()),
+            None => Resolution::NameError(soa.clone()),
+        };
+
+        self.store(&key, data, soa.ttl.min(minimum), now);
+    }
+
+    /// Answers and negative answers (which need an SOA) in a response from a server for `zone`
+    /// (lowercase), cached on the way out. Only what that server can vouch for is believed:
+    /// records inside its zone, on the way from the question's name to its answer
+    pub fn insert_response(
+        &self,
+        res: &Message,
+        question: &Question,
+        zone: &Domain,
+    ) -> Option<Resolution> {
+        let in_zone = |name: &Domain| name.to_lowercase().0.ends_with(&zone.0);
+
+        let answers: Vec<ResourceRecord> = res
+            .answers
+            .iter()
+            .filter(|r| in_zone(&r.name))
+            .cloned()
+            .collect();
+
+        // Aliases can come with an NXDOMAIN or NODATA for their target, which
+        // is only looked up again once the chain has been followed
+        let mut chain = Chain::new(&question.name);
+        match chain.follow(&question.name, question.qtype, &answers) {
+            Ok(step) => {
+                let mut records = chain.into_records();
+                if let Step::Answered(answers) = step {
+                    records.extend(answers);
+                }
+
+                self.insert(&records);
+                return Some(Resolution::Answer(records));
+            }
+            // Nothing about the name itself, so the answer section doesn't count
+            Err(ChainError::NoAnswer(_)) => {}
+            Err(_) => return None,
+        }
+
+        // The SOA has to be for the zone the name is in, which the server has to be responsible for
+        let name = question.name.to_lowercase();
+        let soa = res
+            .authorities
+            .iter()
+            .find(|r| {
+                r.rtype == RecordType::SOA
+                    && in_zone(&r.name)
+                    && name.0.ends_with(&r.name.to_lowercase().0)
+            })?
+            .clone();
+
+        if res.header.rescode == ResCode::NameError {
+            self.insert_negative(&question.name, None, question.qclass, &soa);
+            Some(Resolution::NameError(soa))
+        } else {
+            // No answers and no referral, so the name exists without any records of this type
+            self.insert_negative(&question.name, Some(question.qtype), question.qclass, &soa);
+            Some(Resolution::NoData(soa))
+        }
+    }
+
+    /// Cached record set with TTLs counting down from when it was stored
+    pub(crate) fn get_at(
+        &self,
+        name: &Domain,
+        rtype: RecordType,
+        rclass: RecordClass,
+        now: Instant,
+    ) -> Option<Vec<ResourceRecord>> {
+        let key = CacheKey {
+            name: name.to_lowercase(),
+            rtype: Some(rtype),
+            rclass,
+        };
+
+        match self.load(&key, now)? {
+            Resolution::Answer(records) => Some(records),
+            Resolution::NameError(_) | Resolution::NoData(_) => None,
+        }
+    }
+
+    /// Everything known about a question, including negative answers
+    pub fn lookup(
+        &self,
+        name: &Domain,
+        rtype: RecordType,
+        rclass: RecordClass,
+    ) -> Option<Resolution> {
+        self.lookup_at(name, rtype, rclass, Instant::now())
+    }
+
+    pub(crate) fn lookup_at(
+        &self,
+        name: &Domain,
+        rtype: RecordType,
+        rclass: RecordClass,
+        now: Instant,
+    ) -> Option<Resolution> {
+        let name = name.to_lowercase();
+        let name_key = CacheKey {
+            name,
+            rtype: None,
+            rclass,
+        };
+
+        if let Some(resolution) = self.load(&name_key, now) {
+            return Some(resolution);
+        }
+
+        self.load(
+            &CacheKey {
+                rtype: Some(rtype),
+                ..name_key
+            },
+            now,
+        )
+    }
+
+    /// Closest cached delegation above `name`, along with the addresses of its name servers
+    pub fn closest_nameservers(
+        &self,
+        name: &Domain,
+        rclass: RecordClass,
+    ) -> Option<(Domain, Vec<IpAddr>)> {
+        self.closest_nameservers_at(name, rclass, Instant::now())
+    }
+
+    pub(crate) fn closest_nameservers_at(
+        &self,
+        name: &Domain,
+        rclass: RecordClass,
+        now: Instant,
+    ) -> Option<(Domain, Vec<IpAddr>)> {
+        for depth in (0..=name.0.len()).rev() {
+            let zone = Domain(name.0[name.0.len() - depth..].to_vec());
+            let Some(ns) = self.get_at(&zone, RecordType::NS, rclass, now) else {
+                continue;
+            };
+
+            let mut addresses = vec![];
+            for record in ns {
+                let RData::NS(target) = record.data else {
+                    continue;
+                };
+
+                for rtype in [RecordType::A, RecordType::AAAA] {
+                    for glue in self.get_at(&target, rtype, rclass, now).unwrap_or_default() {
+                        match glue.data {
+                            RData::A(ip) => addresses.push(IpAddr::V4(ip)),
+                            RData::AAAA(ip) => addresses.push(IpAddr::V6(ip)),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            if !addresses.is_empty() {
+                return Some((zone, addresses));
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::{Duration, Instant},
+};
+
+use types::{Domain, RData, RecordClass, RecordType, ResourceRecord, Soa};
+
+use crate::cache::*;
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+fn a(name: &str, ttl: u32, last: u8) -> ResourceRecord {
+    ResourceRecord {
+        name: domain(name),
+        rtype: RecordType::A,
+        rclass: RecordClass::IN,
+        ttl,
+        data: RData::A(Ipv4Addr::new(192, 0, 2, last)),
+    }
+}
+
+fn ns(name: &str, target: &str) -> ResourceRecord {
+    ResourceRecord {
+        name: domain(name),
+        rtype: RecordType::NS,
+        rclass: RecordClass::IN,
+        ttl: 3600,
+        data: RData::NS(domain(target)),
+    }
+}
+
+fn soa(ttl: u32, minimum: u32) -> ResourceRecord {
+    ResourceRecord {
+        name: domain("example.com"),
+        rtype: RecordType::SOA,
+        rclass: RecordClass::IN,
+        ttl,
+        data: RData::SOA(Soa {
+            mname: domain("ns1.example.com"),
+            rname: domain("admin.example.com"),
+            serial: 1,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1_209_600,
+            minimum,
+        }),
+    }
+}
+
+#[test]
+fn ttl_counts_down() {
+    let cache = Cache::new(10);
+    let now = Instant::now();
+
+    // The whole set expires with its shortest TTL
+    cache.insert_at(
+        &[a("www.example.com", 300, 1), a("www.example.com", 60, 2)],
+        now,
+    );
+
+    assert_eq!(
+        cache.get_at(
+            &domain("WWW.example.com"),
+            RecordType::A,
+            RecordClass::IN,
+            now + Duration::from_secs(20)
+        ),
+        Some(vec![
+            a("www.example.com", 40, 1),
+            a("www.example.com", 40, 2)
+        ])
+    );
+    assert_eq!(
+        cache.get_at(
+            &domain("www.example.com"),
+            RecordType::A,
+            RecordClass::IN,
+            now + Duration::from_mins(1)
+        ),
+        None
+    );
+}
+
+#[test]
+fn zero_ttl_not_cached() {
+    let cache = Cache::new(10);
+    cache.insert(&[a("www.example.com", 0, 1)]);
+
+    assert_eq!(
+        cache.lookup(&domain("www.example.com"), RecordType::A, RecordClass::IN),
+        None
+    );
+}
+
+#[test]
+fn evicts_least_recently_used() {
+    let cache = Cache::new(2);
+    let now = Instant::now();
+
+    cache.insert_at(&[a("a.example.com", 300, 1)], now);
+    cache.insert_at(&[a("b.example.com", 300, 2)], now);
+
+    // Makes `b.example.com.` the least recently used
+    cache.get_at(
+        &domain("a.example.com"),
+        RecordType::A,
+        RecordClass::IN,
+        now,
+    );
+    cache.insert_at(&[a("c.example.com", 300, 3)], now);
+
+    for (name, cached) in [
+        ("a.example.com", true),
+        ("b.example.com", false),
+        ("c.example.com", true),
+    ] {
+        assert_eq!(
+            cache
+                .get_at(&domain(name), RecordType::A, RecordClass::IN, now)
+                .is_some(),
+            cached,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn closest_delegation() {
+    let cache = Cache::new(10);
+    let now = Instant::now();
+
+    cache.insert_at(
+        &[
+            ns("com", "a.gtld-servers.net"),
+            a("a.gtld-servers.net", 3600, 1),
+            ns("example.com", "ns1.example.com"),
+            a("ns1.example.com", 3600, 2),
+            ResourceRecord {
+                name: domain("ns1.example.com"),
+                rtype: RecordType::AAAA,
+                rclass: RecordClass::IN,
+                ttl: 3600,
+                data: RData::AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2)),
+            },
+            // Delegation without any glue can't be used
+            ns("sub.example.com", "ns.elsewhere.org"),
+        ],
+        now,
+    );
+
+    assert_eq!(
+        cache.closest_nameservers_at(&domain("www.sub.example.com"), RecordClass::IN, now),
+        Some((
+            domain("example.com"),
+            vec![
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2))
+            ]
+        ))
+    );
+    assert_eq!(
+        cache.closest_nameservers_at(&domain("hackclub.com"), RecordClass::IN, now),
+        Some((domain("com"), vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]))
+    );
+    assert_eq!(
+        cache.closest_nameservers_at(&domain("example.org"), RecordClass::IN, now),
+        None
+    );
+}
+
+#[test]
+fn name_error_covers_every_type() {
+    let cache = Cache::new(10);
+    let now = Instant::now();
+
+    // Lives for the lower of the SOA's TTL and minimum
+    cache.insert_negative_at(
+        &domain("nope.example.com"),
+        None,
+        RecordClass::IN,
+        &soa(3600, 300),
+        now,
+    );
+
+    for rtype in [RecordType::A, RecordType::MX] {
+        assert_eq!(
+            cache.lookup_at(
+                &domain("nope.example.com"),
+                rtype,
+                RecordClass::IN,
+                now + Duration::from_secs(100)
+            ),
+            Some(Resolution::NameError(soa(200, 300)))
+        );
+    }
+
+    assert_eq!(
+        cache.lookup_at(
+            &domain("nope.example.com"),
+            RecordType::A,
+            RecordClass::IN,
+            now + Duration::from_mins(5)
+        ),
+        None
+    );
+}
+
+#[test]
+fn no_data_is_per_type() {
+    let cache = Cache::new(10);
+    let now = Instant::now();
+
+    cache.insert_at(&[a("www.example.com", 300, 1)], now);
+    cache.insert_negative_at(
+        &domain("www.example.com"),
+        Some(RecordType::AAAA),
+        RecordClass::IN,
+        &soa(60, 300),
+        now,
+    );
+
+    assert_eq!(
+        cache.lookup_at(
+            &domain("www.example.com"),
+            RecordType::AAAA,
+            RecordClass::IN,
+            now
+        ),
+        Some(Resolution::NoData(soa(60, 300)))
+    );
+    assert_eq!(
+        cache.lookup_at(
+            &domain("www.example.com"),
+            RecordType::A,
+            RecordClass::IN,
+            now
+        ),
+        Some(Resolution::Answer(vec![a("www.example.com", 300, 1)]))
+    );
+}
@@ -0,0 +1,183 @@
+use std::net::Ipv4Addr;
+
+use types::{
+    Domain, Header, Message, OpCode, Question, RData, RecordClass, RecordType, ResCode,
+    ResourceRecord, Soa,
+};
+
+use crate::cache::*;
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+fn record(name: Domain, data: RData) -> ResourceRecord {
+    let rtype = match data {
+        RData::A(_) => RecordType::A,
+        RData::NS(_) => RecordType::NS,
+        RData::CNAME(_) => RecordType::CNAME,
+        RData::SOA(_) => RecordType::SOA,
+        _ => unreachable!(),
+    };
+
+    ResourceRecord {
+        name,
+        rtype,
+        rclass: RecordClass::IN,
+        ttl: 3600,
+        data,
+    }
+}
+
+fn a(name: &str, last: u8) -> ResourceRecord {
+    record(domain(name), RData::A(Ipv4Addr::new(192, 0, 2, last)))
+}
+
+fn soa(zone: &str) -> ResourceRecord {
+    record(
+        domain(zone),
+        RData::SOA(Soa {
+            mname: domain("ns1.example.com"),
+            rname: domain("admin.example.com"),
+            serial: 1,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1_209_600,
+            minimum: 300,
+        }),
+    )
+}
+
+fn question(name: &str) -> Question {
+    Question {
+        name: domain(name),
+        qtype: RecordType::A,
+        qclass: RecordClass::IN,
+    }
+}
+
+fn response(
+    rescode: ResCode,
+    answers: Vec<ResourceRecord>,
+    authorities: Vec<ResourceRecord>,
+) -> Message {
+    Message {
+        header: Header {
+            id: 0,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: true,
+            is_truncated: false,
+            should_recurse: false,
+            recursion_available: false,
+            _z: 0,
+            rescode,
+            questions: 1,
+            answer_records: 0,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: vec![],
+        answers,
+        authorities,
+        additional: vec![],
+        edns: None,
+    }
+}
+
+fn cached(cache: &Cache, name: &Domain, rtype: RecordType) -> bool {
+    cache.lookup(name, rtype, RecordClass::IN).is_some()
+}
+
+#[test]
+fn out_of_bailiwick_answers() {
+    let cache = Cache::new(10);
+
+    // A server for example.com trying to take over the root and another zone's name servers
+    let res = response(
+        ResCode::NoError,
+        vec![
+            a("www.example.com", 1),
+            record(Domain(vec![]), RData::NS(domain("ns.evil.example"))),
+            a("ns.evil.example", 2),
+            a("other.example.com", 3),
+        ],
+        vec![],
+    );
+
+    assert_eq!(
+        cache.insert_response(&res, &question("www.example.com"), &domain("example.com")),
+        Some(Resolution::Answer(vec![a("www.example.com", 1)]))
+    );
+
+    assert!(cached(&cache, &domain("www.example.com"), RecordType::A));
+    assert!(!cached(&cache, &Domain(vec![]), RecordType::NS));
+    assert!(!cached(&cache, &domain("ns.evil.example"), RecordType::A));
+    // Inside the zone, but nothing to do with the question
+    assert!(!cached(&cache, &domain("other.example.com"), RecordType::A));
+    assert_eq!(
+        cache.closest_nameservers(&domain("www.example.org"), RecordClass::IN),
+        None
+    );
+}
+
+#[test]
+fn aliases_out_of_bailiwick() {
+    let cache = Cache::new(10);
+
+    let cname = record(
+        domain("www.example.com"),
+        RData::CNAME(domain("www.example.net")),
+    );
+    let res = response(
+        ResCode::NoError,
+        vec![cname.clone(), a("www.example.net", 1)],
+        vec![],
+    );
+
+    // The target has to be asked about separately
+    assert_eq!(
+        cache.insert_response(&res, &question("www.example.com"), &domain("example.com")),
+        Some(Resolution::Answer(vec![cname]))
+    );
+    assert!(cached(
+        &cache,
+        &domain("www.example.com"),
+        RecordType::CNAME
+    ));
+    assert!(!cached(&cache, &domain("www.example.net"), RecordType::A));
+
+    // A resolver forwarded to for the root can vouch for both
+    let cache = Cache::new(10);
+    assert!(matches!(
+        cache.insert_response(&res, &question("www.example.com"), &Domain(vec![])),
+        Some(Resolution::Answer(records)) if records.len() == 2
+    ));
+    assert!(cached(&cache, &domain("www.example.net"), RecordType::A));
+}
+
+#[test]
+fn negative_answers_need_the_zones_soa() {
+    let zone = domain("example.com");
+
+    for soa in [soa("com"), soa("other.example.com"), soa("example.net")] {
+        let cache = Cache::new(10);
+        let res = response(ResCode::NameError, vec![], vec![soa.clone()]);
+
+        assert_eq!(
+            cache.insert_response(&res, &question("nope.example.com"), &zone),
+            None,
+            "{}",
+            soa.name
+        );
+        assert!(!cached(&cache, &domain("nope.example.com"), RecordType::A));
+    }
+
+    let cache = Cache::new(10);
+    let res = response(ResCode::NameError, vec![], vec![soa("example.com")]);
+    assert_eq!(
+        cache.insert_response(&res, &question("nope.example.com"), &zone),
+        Some(Resolution::NameError(soa("example.com")))
+    );
+    assert!(cached(&cache, &domain("nope.example.com"), RecordType::A));
+}
@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+use types::{Domain, RData, RecordType, ResourceRecord};
+
+/// How many CNAMEs and DNAMEs get followed for a single question before giving up
+pub const MAX_CHAIN_LENGTH: usize = 8;
+
+/// Longest a name can be in wire format (RFC 1035 section 2.3.4)
+const MAX_NAME_LENGTH: usize = 255;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ChainError {
+    #[error("Aliases for {0} loop back on themselves")]
+    Loop(Domain),
+    #[error("More than {MAX_CHAIN_LENGTH} aliases for {0}")]
+    TooLong(Domain),
+    #[error("DNAME substitution for {0} makes a name that is too long")]
+    NameTooLong(Domain),
+    #[error("Answer doesn't say anything about {0}")]
+    NoAnswer(Domain),
+}
+
+/// What's left to do after following the aliases in a set of records
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Records of the asked for type at the end of the chain
+    Answered(Vec<ResourceRecord>),
+    /// The chain leads to a name the records don't cover, resolution has to start over there
+    Restart(Domain),
+}
+
+fn wire_length(name: &Domain) -> usize {
+    name.0.iter().map(|label| label.len() + 1).sum::<usize>() + 1
+}
+
+/// Aliases collected while resolving a single question, in the order they were followed
+#[derive(Debug)]
+pub struct Chain {
+    qname: Domain,
+    records: Vec<ResourceRecord>,
+    /// Every name the chain went through, lowercase
+    seen: HashSet<Domain>,
+}
+
+impl Chain {
+    pub fn new(qname: &Domain) -> Chain {
+        Chain {
+            qname: qname.clone(),
+            records: vec![],
+            seen: HashSet::from([qname.to_lowercase()]),
+        }
+    }
+
+    /// CNAME and DNAME records (including synthesized CNAMEs) that belong in the answer section
+    pub fn into_records(self) -> Vec<ResourceRecord> {
+        self.records
+    }
+
+    fn advance(&mut self, target: &Domain) -> Result<(), ChainError> {
+        if !self.seen.insert(target.to_lowercase()) {
+            return Err(ChainError::Loop(self.qname.clone()));
+        }
+
+        if self.seen.len() > MAX_CHAIN_LENGTH + 1 {
+            return Err(ChainError::TooLong(self.qname.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Walks the aliases in `records` starting at `name`, until reaching records of `qtype`
+    /// or a name `records` have nothing for
+    pub fn follow(
+        &mut self,
+        name: &Domain,
+        qtype: RecordType,
+        records: &[ResourceRecord],
+    ) -> Result<Step, ChainError> {
+        let mut current = name.clone();
+
+        loop {
+            let owner = current.to_lowercase();
+
+            let matching: Vec<ResourceRecord> = records
+                .iter()
+                .filter(|r| {
+                    (qtype == RecordType::ANY || r.rtype == qtype) && r.name.to_lowercase() == owner
+                })
+                .cloned()
+                .collect();
+
+            if !matching.is_empty() {
+                return Ok(Step::Answered(matching));
+            }
+
+            // DNAMEs take priority over the CNAMEs servers synthesize from them (RFC 6672 section 3.1),
+            // the CNAME gets synthesized here too so that it's the same no matter where the records came from
+            let dname = records.iter().find_map(|r| match &r.data {
+                RData::DNAME(target)
+                    if qtype != RecordType::DNAME
+                        && owner.0.len() > r.name.0.len()
+                        && owner.0.ends_with(&r.name.to_lowercase().0) =>
+                {
+                    Some((r, target))
+                }
+                _ => None,
+            });
+
+            let next = if let Some((dname, target)) = dname {
+                let prefix = &current.0[..current.0.len() - dname.name.0.len()];
+                let synthesized = Domain([prefix, &target.0].concat());
+
+                if wire_length(&synthesized) > MAX_NAME_LENGTH {
+                    return Err(ChainError::NameTooLong(current));
+                }
+
+                let cname = ResourceRecord {
+                    name: current.clone(),
+                    rtype: RecordType::CNAME,
+                    rclass: dname.rclass,
+                    ttl: dname.ttl,
+                    data: RData::CNAME(synthesized.clone()),
+                };
+
+                self.records.push(dname.clone());
+                if qtype == RecordType::CNAME {
+                    return Ok(Step::Answered(vec![cname]));
+                }
+
+                self.records.push(cname);
+                synthesized
+            } else if let Some(cname) = records
+                .iter()
+                .find(|r| r.rtype == RecordType::CNAME && r.name.to_lowercase() == owner)
+            {
+                let RData::CNAME(target) = &cname.data else {
+                    return Err(ChainError::NoAnswer(current));
+                };
+
+                self.records.push(cname.clone());
+                target.clone()
+            } else if current == *name {
+                return Err(ChainError::NoAnswer(current));
+            } else {
+                return Ok(Step::Restart(current));
+            };
+
+            self.advance(&next)?;
+            current = next;
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::net::Ipv4Addr;
+
+use types::{Domain, RData, RecordClass, RecordType, ResourceRecord};
+
+use crate::chain::*;
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+fn record(name: &str, data: RData) -> ResourceRecord {
+    ResourceRecord {
+        name: domain(name),
+        rtype: match data {
+            RData::A(_) => RecordType::A,
+            RData::CNAME(_) => RecordType::CNAME,
+            RData::DNAME(_) => RecordType::DNAME,
+            _ => RecordType::Unknown(0),
+        },
+        rclass: RecordClass::IN,
+        ttl: 300,
+        data,
+    }
+}
+
+fn cname(name: &str, target: &str) -> ResourceRecord {
+    record(name, RData::CNAME(domain(target)))
+}
+
+fn a(name: &str) -> ResourceRecord {
+    record(name, RData::A(Ipv4Addr::new(192, 0, 2, 1)))
+}
+
+#[test]
+fn direct_answer() {
+    let mut chain = Chain::new(&domain("www.example.com"));
+
+    assert_eq!(
+        chain.follow(
+            &domain("www.example.com"),
+            RecordType::A,
+            &[a("WWW.example.com")]
+        ),
+        Ok(Step::Answered(vec![a("WWW.example.com")]))
+    );
+    assert_eq!(chain.into_records(), vec![]);
+}
+
+#[test]
+fn cname_answered_in_same_response() {
+    let mut chain = Chain::new(&domain("www.example.com"));
+    let records = [
+        cname("www.example.com", "cdn.example.net"),
+        cname("cdn.example.net", "edge.example.org"),
+        a("edge.example.org"),
+    ];
+
+    assert_eq!(
+        chain.follow(&domain("www.example.com"), RecordType::A, &records),
+        Ok(Step::Answered(vec![a("edge.example.org")]))
+    );
+    assert_eq!(chain.into_records(), records[..2].to_vec());
+}
+
+#[test]
+fn cname_restarts_at_target() {
+    let mut chain = Chain::new(&domain("www.example.com"));
+
+    assert_eq!(
+        chain.follow(
+            &domain("www.example.com"),
+            RecordType::A,
+            &[cname("www.example.com", "cdn.example.net")]
+        ),
+        Ok(Step::Restart(domain("cdn.example.net")))
+    );
+    assert_eq!(
+        chain.follow(
+            &domain("cdn.example.net"),
+            RecordType::A,
+            &[a("cdn.example.net")]
+        ),
+        Ok(Step::Answered(vec![a("cdn.example.net")]))
+    );
+    assert_eq!(
+        chain.into_records(),
+        vec![cname("www.example.com", "cdn.example.net")]
+    );
+}
+
+#[test]
+fn cname_asked_for() {
+    let mut chain = Chain::new(&domain("www.example.com"));
+
+    assert_eq!(
+        chain.follow(
+            &domain("www.example.com"),
+            RecordType::CNAME,
+            &[cname("www.example.com", "cdn.example.net")]
+        ),
+        Ok(Step::Answered(vec![cname(
+            "www.example.com",
+            "cdn.example.net"
+        )]))
+    );
+}
+
+#[test]
+fn dname_synthesizes_cname() {
+    let mut chain = Chain::new(&domain("www.old.example.com"));
+    let dname = record("old.example.com", RData::DNAME(domain("new.example.net")));
+
+    assert_eq!(
+        chain.follow(
+            &domain("www.old.example.com"),
+            RecordType::A,
+            // The server's own synthesized CNAME gets replaced by ours
+            &[
+                dname.clone(),
+                cname("www.old.example.com", "www.new.example.net"),
+                a("www.new.example.net"),
+            ]
+        ),
+        Ok(Step::Answered(vec![a("www.new.example.net")]))
+    );
+    assert_eq!(
+        chain.into_records(),
+        vec![dname, cname("www.old.example.com", "www.new.example.net")]
+    );
+}
+
+#[test]
+fn dname_doesnt_apply_to_owner() {
+    let mut chain = Chain::new(&domain("old.example.com"));
+
+    assert_eq!(
+        chain.follow(
+            &domain("old.example.com"),
+            RecordType::A,
+            &[
+                record("old.example.com", RData::DNAME(domain("new.example.net"))),
+                a("old.example.com"),
+            ]
+        ),
+        Ok(Step::Answered(vec![a("old.example.com")]))
+    );
+}
+
+#[test]
+fn loop_detected() {
+    let mut chain = Chain::new(&domain("a.example.com"));
+
+    assert_eq!(
+        chain.follow(
+            &domain("a.example.com"),
+            RecordType::A,
+            &[
+                cname("a.example.com", "b.example.com"),
+                cname("b.example.com", "A.example.com"),
+            ]
+        ),
+        Err(ChainError::Loop(domain("a.example.com")))
+    );
+}
+
+#[test]
+fn loop_across_restarts() {
+    let mut chain = Chain::new(&domain("a.example.com"));
+
+    assert_eq!(
+        chain.follow(
+            &domain("a.example.com"),
+            RecordType::A,
+            &[cname("a.example.com", "b.example.com")]
+        ),
+        Ok(Step::Restart(domain("b.example.com")))
+    );
+    assert_eq!(
+        chain.follow(
+            &domain("b.example.com"),
+            RecordType::A,
+            &[cname("b.example.com", "a.example.com")]
+        ),
+        Err(ChainError::Loop(domain("a.example.com")))
+    );
+}
+
+#[test]
+fn too_long() {
+    let names: Vec<String> = (0..=MAX_CHAIN_LENGTH + 1)
+        .map(|i| format!("n{i}.example.com"))
+        .collect();
+    let records: Vec<ResourceRecord> = names
+        .windows(2)
+        .map(|pair| cname(&pair[0], &pair[1]))
+        .collect();
+
+    let mut chain = Chain::new(&domain(&names[0]));
+    assert_eq!(
+        chain.follow(&domain(&names[0]), RecordType::A, &records),
+        Err(ChainError::TooLong(domain(&names[0])))
+    );
+
+    // Exactly the limit is fine
+    let mut chain = Chain::new(&domain(&names[0]));
+    assert_eq!(
+        chain.follow(
+            &domain(&names[0]),
+            RecordType::A,
+            &records[..MAX_CHAIN_LENGTH]
+        ),
+        Ok(Step::Restart(domain(&names[MAX_CHAIN_LENGTH])))
+    );
+}
+
+#[test]
+fn nothing_about_name() {
+    let mut chain = Chain::new(&domain("www.example.com"));
+
+    assert_eq!(
+        chain.follow(
+            &domain("www.example.com"),
+            RecordType::A,
+            &[a("other.example.com")]
+        ),
+        Err(ChainError::NoAnswer(domain("www.example.com")))
+    );
+}
@@ -0,0 +1,375 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+use utils::{Transport, UDP_PAYLOAD_SIZE};
+
+use crate::{family::FamilyPolicy, forward::zone_name};
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Couldn't read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+}
+
+/// What the server does with questions for names outside of its own zones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Resolves them starting from the root
+    #[default]
+    Recursive,
+    /// Hands them to the upstreams of the matching `[[forward]]` rule, which has to include one for the root
+    Forwarding,
+    /// Refuses them
+    Authoritative,
+}
+
+fn default_listen() -> Vec<SocketAddr> {
+    vec![
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8080)),
+        SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 8080, 0, 0)),
+    ]
+}
+
+/// Addresses to accept queries on for each transport
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Listen {
+    pub udp: Vec<SocketAddr>,
+    pub tcp: Vec<SocketAddr>,
+    /// DNS over TLS, needs a certificate and key in `[tls]`
+    pub tls: Vec<SocketAddr>,
+    /// DNS over HTTPS at `/dns-query`, with the same certificate and key as TLS
+    pub https: Vec<SocketAddr>,
+    /// DNS over QUIC, also with the certificate and key in `[tls]`
+    pub quic: Vec<SocketAddr>,
+}
+
+impl Default for Listen {
+    fn default() -> Listen {
+        Listen {
+            udp: default_listen(),
+            tcp: default_listen(),
+            tls: vec![],
+            https: vec![],
+            quic: vec![],
+        }
+    }
+}
+
+/// Where recursion starts and how upstream servers get contacted
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Upstream {
+    /// Zone file with the root name servers, the built in IANA hints are used without one
+    pub root_hints: Option<PathBuf>,
+    /// Detected from which families the host has routes for when left out
+    pub families: Option<FamilyPolicy>,
+    /// Randomize the case of names in queries and check responses keep it (DNS 0x20)
+    pub randomize_case: bool,
+}
+
+/// How to talk to an upstream resolver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamTransport {
+    /// UDP, falling back to TCP for truncated responses
+    #[default]
+    Auto,
+    Udp,
+    Tcp,
+    Tls,
+    Https,
+    Quic,
+}
+
+impl From<UpstreamTransport> for Transport {
+    fn from(transport: UpstreamTransport) -> Transport {
+        match transport {
+            UpstreamTransport::Auto => Transport::Unspecified,
+            UpstreamTransport::Udp => Transport::Udp,
+            UpstreamTransport::Tcp => Transport::Tcp,
+            UpstreamTransport::Tls => Transport::Tls,
+            UpstreamTransport::Https => Transport::Https,
+            UpstreamTransport::Quic => Transport::Quic,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamServer {
+    pub address: SocketAddr,
+    #[serde(default)]
+    pub transport: UpstreamTransport,
+}
+
+/// Sends questions for names at or below `zone` to its upstreams instead of resolving them
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForwardRule {
+    /// `.` covers every name
+    pub zone: String,
+    /// Tried in order, skipping the ones that have been failing
+    pub upstreams: Vec<UpstreamServer>,
+}
+
+/// What the server presents to clients connecting over TLS, HTTPS or QUIC, both PEM files
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Certificate chain, leaf first
+    pub certificate: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// How many record sets the cache holds before evicting the least recently used, 0 disables it
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> CacheConfig {
+        CacheConfig {
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// All in seconds
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    /// How long a TCP, TLS or HTTPS client gets to send its query
+    pub tcp_read: u64,
+    /// How long a TCP, TLS, HTTPS or QUIC connection stays open without a new query
+    pub idle: u64,
+}
+
+impl Timeouts {
+    pub fn tcp_read(&self) -> Duration {
+        Duration::from_secs(self.tcp_read)
+    }
+
+    pub fn idle(&self) -> Duration {
+        Duration::from_secs(self.idle)
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts {
+            tcp_read: 60,
+            idle: 10,
+        }
+    }
+}
+
+/// What happens to UDP queries that come in while the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Overload {
+    /// Tells the client right away, so it can move on to another server
+    #[default]
+    Servfail,
+    /// Ignores them, costing nothing when the load is a flood of queries
+    Drop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Largest UDP message accepted and sent to EDNS clients
+    pub udp_payload_size: u16,
+    /// Queries on one TCP, TLS or QUIC connection answered at once, later ones wait to be read
+    pub pipelined_queries: usize,
+    /// Threads answering UDP queries, each handles one query at a time
+    pub udp_workers: usize,
+    /// UDP queries waiting for a worker, anything past it gets `udp_overload`
+    pub udp_queue: usize,
+    pub udp_overload: Overload,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            udp_payload_size: UDP_PAYLOAD_SIZE,
+            pipelined_queries: 16,
+            udp_workers: 32,
+            udp_queue: 256,
+            udp_overload: Overload::Servfail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> tracing::Level {
+        match level {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Log {
+    pub level: LogLevel,
+}
+
+/// Everything about how the server runs, anything left out of the file gets its default
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub mode: Mode,
+    /// Zone files to serve authoritatively
+    pub zones: Vec<PathBuf>,
+    pub listen: Listen,
+    pub upstream: Upstream,
+    pub forward: Vec<ForwardRule>,
+    pub tls: TlsConfig,
+    pub cache: CacheConfig,
+    pub timeouts: Timeouts,
+    pub limits: Limits,
+    pub log: Log,
+}
+
+impl Config {
+    /// Config in TOML, relative paths are kept as they are
+    pub fn parse(input: &str) -> Result<Config, ConfigError> {
+        Ok(toml::from_str(input)?)
+    }
+
+    /// Config from a TOML file, relative paths in it are relative to the file
+    pub fn read(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let input = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let mut config = Config::parse(&input)?;
+
+        if let Some(dir) = path.parent() {
+            for zone in &mut config.zones {
+                *zone = dir.join(&*zone);
+            }
+
+            let paths = [
+                &mut config.upstream.root_hints,
+                &mut config.tls.certificate,
+                &mut config.tls.key,
+            ];
+            for path in paths.into_iter().flatten() {
+                *path = dir.join(&*path);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Checks for settings that parse fine but can't work together
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| Err(ConfigError::Invalid(reason.into()));
+
+        let listen = &self.listen;
+        if listen.udp.is_empty()
+            && listen.tcp.is_empty()
+            && listen.tls.is_empty()
+            && listen.https.is_empty()
+            && listen.quic.is_empty()
+        {
+            return invalid("there has to be at least one address to listen on");
+        }
+
+        if !(listen.tls.is_empty() && listen.https.is_empty() && listen.quic.is_empty())
+            && (self.tls.certificate.is_none() || self.tls.key.is_none())
+        {
+            return invalid("listening over TLS, HTTPS or QUIC needs a certificate and key");
+        }
+
+        if self.mode == Mode::Authoritative && self.zones.is_empty() {
+            return invalid("authoritative mode needs at least one zone");
+        }
+
+        if self.mode == Mode::Authoritative && !self.forward.is_empty() {
+            return invalid("authoritative mode can't forward");
+        }
+
+        let zones: Vec<_> = self
+            .forward
+            .iter()
+            .map(|rule| zone_name(&rule.zone))
+            .collect();
+        for (i, rule) in self.forward.iter().enumerate() {
+            if rule.upstreams.is_empty() {
+                return Err(ConfigError::Invalid(format!(
+                    "forward rule for {} has no upstreams",
+                    rule.zone
+                )));
+            }
+
+            if zones[..i].contains(&zones[i]) {
+                return Err(ConfigError::Invalid(format!(
+                    "more than one forward rule for {}",
+                    rule.zone
+                )));
+            }
+        }
+
+        if self.mode == Mode::Forwarding && !zones.iter().any(|zone| zone.0.is_empty()) {
+            return invalid("forwarding mode needs a forward rule for the root zone \".\"");
+        }
+
+        // https://www.rfc-editor.org/rfc/rfc6891#section-6.2.5
+        if self.limits.udp_payload_size < 512 {
+            return invalid("udp_payload_size can't be less than 512");
+        }
+
+        if self.limits.pipelined_queries == 0 {
+            return invalid("pipelined_queries has to be at least 1");
+        }
+
+        if self.limits.udp_workers == 0 {
+            return invalid("udp_workers has to be at least 1");
+        }
+
+        if self.timeouts.tcp_read == 0 {
+            return invalid("tcp_read timeout has to be at least a second");
+        }
+
+        if self.timeouts.idle == 0 {
+            return invalid("idle timeout has to be at least a second");
+        }
+
+        Ok(())
+    }
+}
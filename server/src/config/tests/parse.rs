@@ -0,0 +1,171 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{config::*, family::FamilyPolicy};
+
+#[test]
+fn defaults() {
+    let config = Config::parse("").unwrap();
+
+    assert_eq!(config, Config::default());
+    assert_eq!(config.mode, Mode::Recursive);
+    assert_eq!(config.listen.udp.len(), 2);
+    assert_eq!(config.cache.max_entries, 10_000);
+    assert_eq!(config.timeouts.tcp_read(), Duration::from_mins(1));
+    assert!(config.listen.tls.is_empty());
+    assert!(config.listen.https.is_empty());
+    assert!(config.listen.quic.is_empty());
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn partial_sections() {
+    let config = Config::parse(
+        "mode = \"authoritative\"\n\
+         zones = [\"example.com.zone\"]\n\
+         [listen]\n\
+         udp = [\"0.0.0.0:53\"]\n\
+         [upstream]\n\
+         families = \"ipv6-only\"\n\
+         randomize_case = true\n\
+         [limits]\n\
+         udp_overload = \"drop\"\n\
+         [log]\n\
+         level = \"debug\"\n",
+    )
+    .unwrap();
+
+    assert_eq!(config.mode, Mode::Authoritative);
+    assert_eq!(config.listen.udp, vec!["0.0.0.0:53".parse().unwrap()]);
+    // Left out of the section, so it keeps its default
+    assert_eq!(config.listen.tcp, Listen::default().tcp);
+    assert_eq!(config.upstream.families, Some(FamilyPolicy::Ipv6Only));
+    assert!(config.upstream.randomize_case);
+    assert_eq!(config.log.level, LogLevel::Debug);
+    assert_eq!(config.limits.udp_overload, Overload::Drop);
+    assert_eq!(config.limits.udp_workers, Limits::default().udp_workers);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn unknown_settings() {
+    assert!(matches!(
+        Config::parse("[cache]\nmax_entires = 5\n"),
+        Err(ConfigError::Parse(_))
+    ));
+    assert!(matches!(
+        Config::parse("mode = \"sideways\"\n"),
+        Err(ConfigError::Parse(_))
+    ));
+}
+
+#[test]
+fn invalid_combinations() {
+    let invalid = |input: &str| {
+        matches!(
+            Config::parse(input).unwrap().validate(),
+            Err(ConfigError::Invalid(_))
+        )
+    };
+
+    assert!(invalid("[listen]\nudp = []\ntcp = []\n"));
+    assert!(invalid("mode = \"authoritative\"\n"));
+    assert!(invalid("[limits]\nudp_payload_size = 511\n"));
+    assert!(invalid("[limits]\npipelined_queries = 0\n"));
+    assert!(invalid("[limits]\nudp_workers = 0\n"));
+    assert!(invalid("[timeouts]\ntcp_read = 0\n"));
+    assert!(invalid("[timeouts]\nidle = 0\n"));
+    assert!(invalid("[listen]\nhttps = [\"[::1]:443\"]\n"));
+    assert!(invalid("[listen]\nquic = [\"[::1]:853\"]\n"));
+    assert!(invalid(
+        "[listen]\ntls = [\"[::1]:853\"]\n[tls]\nkey = \"key.pem\"\n"
+    ));
+}
+
+#[test]
+fn tls_listener() {
+    let config = Config::parse(
+        "[listen]\n\
+         tls = [\"0.0.0.0:853\"]\n\
+         quic = [\"0.0.0.0:853\"]\n\
+         [tls]\n\
+         certificate = \"cert.pem\"\n\
+         key = \"key.pem\"\n",
+    )
+    .unwrap();
+
+    assert_eq!(config.listen.tls, vec!["0.0.0.0:853".parse().unwrap()]);
+    assert_eq!(config.listen.quic, config.listen.tls);
+    assert_eq!(config.listen.udp, Listen::default().udp);
+    assert_eq!(config.tls.certificate, Some(PathBuf::from("cert.pem")));
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn forward_rules() {
+    let config = Config::parse(
+        "mode = \"forwarding\"\n\
+         [[forward]]\n\
+         zone = \".\"\n\
+         upstreams = [{ address = \"1.1.1.1:853\", transport = \"tls\" }, { address = \"8.8.8.8:53\" }]\n\
+         [[forward]]\n\
+         zone = \"corp.example.\"\n\
+         upstreams = [{ address = \"10.0.0.53:53\", transport = \"tcp\" }]\n",
+    )
+    .unwrap();
+
+    assert_eq!(config.mode, Mode::Forwarding);
+    assert_eq!(config.forward.len(), 2);
+    assert_eq!(
+        config.forward[0].upstreams[0],
+        UpstreamServer {
+            address: "1.1.1.1:853".parse().unwrap(),
+            transport: UpstreamTransport::Tls
+        }
+    );
+    assert_eq!(
+        config.forward[0].upstreams[1].transport,
+        UpstreamTransport::Auto
+    );
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn invalid_forwarding() {
+    let invalid = |input: &str| {
+        matches!(
+            Config::parse(input).unwrap().validate(),
+            Err(ConfigError::Invalid(_))
+        )
+    };
+
+    let corp =
+        "[[forward]]\nzone = \"corp.example\"\nupstreams = [{ address = \"10.0.0.53:53\" }]\n";
+
+    // Conditional forwarding alone is fine while recursing, but forwarding needs the root covered
+    assert!(!invalid(corp));
+    assert!(invalid(&format!("mode = \"forwarding\"\n{corp}")));
+    assert!(invalid(&format!("{corp}{corp}")));
+    assert!(invalid("[[forward]]\nzone = \".\"\nupstreams = []\n"));
+    assert!(invalid(&format!(
+        "mode = \"authoritative\"\nzones = [\"example.com.zone\"]\n{corp}"
+    )));
+}
+
+#[test]
+fn example_file() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let config = Config::read(dir.join("config.example.toml")).unwrap();
+
+    // Paths end up relative to the config file
+    assert_eq!(
+        config.zones,
+        vec![dir.join("../dns-types/tests/zones/example.com.zone")]
+    );
+    assert_eq!(
+        Config {
+            zones: vec![],
+            ..config
+        },
+        Config::default()
+    );
+}
@@ -0,0 +1,311 @@
+use std::io::{BufRead, Read, Write};
+
+use base64::{
+    alphabet::URL_SAFE,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use thiserror::Error;
+use types::{Message, RData, ResCode, Soa};
+
+/// Where queries are accepted, the path RFC 8484 uses in its examples and clients default to
+pub const DOH_PATH: &str = "/dns-query";
+
+/// Media type of DNS messages sent over HTTPS (RFC 8484 section 6)
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// Longest request or header line accepted
+const MAX_LINE_LENGTH: u64 = 8192;
+const MAX_HEADERS: usize = 100;
+
+/// base64url, which RFC 8484 says is sent without padding but accepting it costs nothing
+const BASE64URL: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// HTTP statuses sent back to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    BadRequest,
+    NotFound,
+    MethodNotAllowed,
+    LengthRequired,
+    PayloadTooLarge,
+    UriTooLong,
+    UnsupportedMediaType,
+    RequestHeaderFieldsTooLarge,
+    NotImplemented,
+    HttpVersionNotSupported,
+}
+
+impl Status {
+    pub fn code(self) -> u16 {
+        match self {
+            Status::Ok => 200,
+            Status::BadRequest => 400,
+            Status::NotFound => 404,
+            Status::MethodNotAllowed => 405,
+            Status::LengthRequired => 411,
+            Status::PayloadTooLarge => 413,
+            Status::UriTooLong => 414,
+            Status::UnsupportedMediaType => 415,
+            Status::RequestHeaderFieldsTooLarge => 431,
+            Status::NotImplemented => 501,
+            Status::HttpVersionNotSupported => 505,
+        }
+    }
+
+    pub fn reason(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::BadRequest => "Bad Request",
+            Status::NotFound => "Not Found",
+            Status::MethodNotAllowed => "Method Not Allowed",
+            Status::LengthRequired => "Length Required",
+            Status::PayloadTooLarge => "Payload Too Large",
+            Status::UriTooLong => "URI Too Long",
+            Status::UnsupportedMediaType => "Unsupported Media Type",
+            Status::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            Status::NotImplemented => "Not Implemented",
+            Status::HttpVersionNotSupported => "HTTP Version Not Supported",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DohError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// The request can't be answered with a DNS message, the client gets this status instead
+    #[error("Rejected request with HTTP status {}", .0.code())]
+    Rejected(Status),
+}
+
+/// DNS query out of an HTTP request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DohRequest {
+    /// The query in DNS wire format, not parsed yet
+    pub message: Vec<u8>,
+    /// Whether the client wants the connection kept open after the response
+    pub keep_alive: bool,
+}
+
+enum Line {
+    Line(String),
+    /// The connection closed before anything was sent
+    Closed,
+}
+
+fn read_line<R: BufRead>(reader: &mut R, too_long: Status) -> Result<Line, DohError> {
+    let mut line = vec![];
+    reader.take(MAX_LINE_LENGTH).read_until(b'\n', &mut line)?;
+
+    if line.is_empty() {
+        return Ok(Line::Closed);
+    }
+
+    let Some(line) = line.strip_suffix(b"\n") else {
+        return Err(if line.len() as u64 == MAX_LINE_LENGTH {
+            DohError::Rejected(too_long)
+        } else {
+            DohError::Io(std::io::ErrorKind::UnexpectedEof.into())
+        });
+    };
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    String::from_utf8(line.to_vec())
+        .map(Line::Line)
+        .map_err(|_| DohError::Rejected(Status::BadRequest))
+}
+
+/// Value of the `dns` parameter in a query string
+fn dns_parameter(query: &str) -> Option<&str> {
+    query
+        .split('&')
+        .find_map(|parameter| parameter.strip_prefix("dns="))
+}
+
+/// Reads an RFC 8484 request, a GET with the query in the `dns` parameter or a POST with it
+/// as the body. `None` when the connection closed cleanly before another request
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<Option<DohRequest>, DohError> {
+    let reject = |status| Err(DohError::Rejected(status));
+
+    let request_line = match read_line(reader, Status::UriTooLong)? {
+        Line::Line(line) => line,
+        Line::Closed => return Ok(None),
+    };
+
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return reject(Status::BadRequest);
+    };
+
+    // HTTP/1.0 closes after every response unless asked not to, HTTP/1.1 is the other way around
+    let mut keep_alive = match version {
+        "HTTP/1.1" => true,
+        "HTTP/1.0" => false,
+        _ => return reject(Status::HttpVersionNotSupported),
+    };
+
+    let mut content_type = None;
+    let mut content_length = None;
+    let mut chunked = false;
+
+    for i in 0.. {
+        let Line::Line(line) = read_line(reader, Status::RequestHeaderFieldsTooLarge)? else {
+            return Err(DohError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        };
+        if line.is_empty() {
+            break;
+        }
+
+        if i == MAX_HEADERS {
+            return reject(Status::RequestHeaderFieldsTooLarge);
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            return reject(Status::BadRequest);
+        };
+        let value = value.trim();
+
+        match name.to_ascii_lowercase().as_str() {
+            "content-type" => content_type = Some(value.to_ascii_lowercase()),
+            "content-length" => match value.parse::<usize>() {
+                Ok(length) => content_length = Some(length),
+                Err(_) => return reject(Status::BadRequest),
+            },
+            "transfer-encoding" => chunked = true,
+            "connection" => {
+                for option in value.split(',').map(str::trim) {
+                    if option.eq_ignore_ascii_case("close") {
+                        keep_alive = false;
+                    } else if option.eq_ignore_ascii_case("keep-alive") {
+                        keep_alive = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != DOH_PATH {
+        return reject(Status::NotFound);
+    }
+
+    let message = match method {
+        "GET" => {
+            let Some(encoded) = dns_parameter(query) else {
+                return reject(Status::BadRequest);
+            };
+
+            match BASE64URL.decode(encoded) {
+                Ok(message) => message,
+                Err(_) => return reject(Status::BadRequest),
+            }
+        }
+        "POST" => {
+            // Parameters like `; charset=...` don't matter
+            let media_type = content_type
+                .as_deref()
+                .and_then(|content_type| content_type.split(';').next())
+                .map(str::trim);
+            if media_type != Some(DNS_MESSAGE) {
+                return reject(Status::UnsupportedMediaType);
+            }
+
+            if chunked {
+                return reject(Status::NotImplemented);
+            }
+
+            let Some(length) = content_length else {
+                return reject(Status::LengthRequired);
+            };
+
+            if length > u16::MAX.into() {
+                return reject(Status::PayloadTooLarge);
+            }
+
+            let mut message = vec![0; length];
+            reader.read_exact(&mut message)?;
+            message
+        }
+        _ => return reject(Status::MethodNotAllowed),
+    };
+
+    Ok(Some(DohRequest {
+        message,
+        keep_alive,
+    }))
+}
+
+/// How long caches can keep a response, the lowest TTL of its answers (RFC 8484 section 5.1).
+/// Negative responses last as long as they would in a DNS cache (RFC 2308 section 5)
+pub fn max_age(response: &Message) -> u32 {
+    if !matches!(
+        response.header.rescode,
+        ResCode::NoError | ResCode::NameError
+    ) {
+        return 0;
+    }
+
+    if let Some(ttl) = response.answers.iter().map(|r| r.ttl).min() {
+        return ttl;
+    }
+
+    response
+        .authorities
+        .iter()
+        .find_map(|r| match r.data {
+            RData::SOA(Soa { minimum, .. }) => Some(r.ttl.min(minimum)),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+/// Writes an HTTP/1.1 response, carrying a DNS message and how long it can be cached if there is one
+pub fn write_response<W: Write>(
+    writer: &mut W,
+    status: Status,
+    message: Option<(&[u8], u32)>,
+    keep_alive: bool,
+) -> std::io::Result<()> {
+    // Built up in one buffer so it goes out in as few TLS records as possible
+    let mut response = vec![];
+    write!(
+        response,
+        "HTTP/1.1 {} {}\r\n",
+        status.code(),
+        status.reason()
+    )?;
+
+    if status == Status::MethodNotAllowed {
+        write!(response, "Allow: GET, POST\r\n")?;
+    }
+
+    if !keep_alive {
+        write!(response, "Connection: close\r\n")?;
+    }
+
+    let body = match message {
+        Some((body, max_age)) => {
+            write!(
+                response,
+                "Content-Type: {DNS_MESSAGE}\r\n\
+                 Cache-Control: max-age={max_age}\r\n"
+            )?;
+            body
+        }
+        None => &[],
+    };
+
+    write!(response, "Content-Length: {}\r\n\r\n", body.len())?;
+    response.extend_from_slice(body);
+
+    writer.write_all(&response)?;
+    writer.flush()
+}
@@ -0,0 +1,134 @@
+use crate::doh::*;
+
+fn read(request: &[u8]) -> Result<Option<DohRequest>, DohError> {
+    read_request(&mut &request[..])
+}
+
+fn rejected(request: &[u8]) -> Option<Status> {
+    match read(request) {
+        Err(DohError::Rejected(status)) => Some(status),
+        _ => None,
+    }
+}
+
+#[test]
+fn get_requests() {
+    // The example from RFC 8484 section 4.1.1
+    let request =
+        b"GET /dns-query?ct&dns=AAABAAABAAAAAAAAA3d3dwdleGFtcGxlA2NvbQAAAQAB HTTP/1.1\r\n\
+                    Host: dns.example\r\n\
+                    Accept: application/dns-message\r\n\r\n";
+
+    let request = read(request).unwrap().unwrap();
+    assert_eq!(&request.message[..4], [0x00, 0x00, 0x01, 0x00]);
+    assert_eq!(request.message.len(), 33);
+    assert!(request.keep_alive);
+
+    // Padding is fine too
+    let padded = read(b"GET /dns-query?dns=AAA= HTTP/1.0\r\n\r\n")
+        .unwrap()
+        .unwrap();
+    assert_eq!(padded.message, vec![0, 0]);
+    assert!(!padded.keep_alive);
+}
+
+#[test]
+fn post_requests() {
+    let requests = b"POST /dns-query HTTP/1.1\r\n\
+                     content-type: application/dns-message\r\n\
+                     Content-Length: 3\r\n\
+                     Connection: close\r\n\r\n\
+                     abc\
+                     POST /dns-query HTTP/1.0\r\n\
+                     Content-Type: Application/DNS-Message; charset=binary\r\n\
+                     Connection: keep-alive\r\n\
+                     Content-Length: 2\r\n\r\n\
+                     de";
+    let mut reader = &requests[..];
+
+    assert_eq!(
+        read_request(&mut reader).unwrap(),
+        Some(DohRequest {
+            message: b"abc".to_vec(),
+            keep_alive: false,
+        })
+    );
+    assert_eq!(
+        read_request(&mut reader).unwrap(),
+        Some(DohRequest {
+            message: b"de".to_vec(),
+            keep_alive: true,
+        })
+    );
+    assert_eq!(read_request(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn rejected_requests() {
+    let post = |headers: &str| format!("POST /dns-query HTTP/1.1\r\n{headers}\r\n").into_bytes();
+
+    assert_eq!(
+        rejected(b"GET /resolve?dns=AAA HTTP/1.1\r\n\r\n"),
+        Some(Status::NotFound)
+    );
+    assert_eq!(
+        rejected(b"GET /dns-query?name=example.com HTTP/1.1\r\n\r\n"),
+        Some(Status::BadRequest)
+    );
+    assert_eq!(
+        rejected(b"GET /dns-query?dns=*** HTTP/1.1\r\n\r\n"),
+        Some(Status::BadRequest)
+    );
+    assert_eq!(
+        rejected(b"PUT /dns-query HTTP/1.1\r\n\r\n"),
+        Some(Status::MethodNotAllowed)
+    );
+    assert_eq!(
+        rejected(b"GET /dns-query?dns=AAA HTTP/2\r\n\r\n"),
+        Some(Status::HttpVersionNotSupported)
+    );
+    assert_eq!(
+        rejected(b"GET /dns-query?dns=AAA\r\n\r\n"),
+        Some(Status::BadRequest)
+    );
+    assert_eq!(
+        rejected(&post("Content-Type: text/plain\r\nContent-Length: 1\r\n")),
+        Some(Status::UnsupportedMediaType)
+    );
+    assert_eq!(
+        rejected(&post("Content-Type: application/dns-message\r\n")),
+        Some(Status::LengthRequired)
+    );
+    assert_eq!(
+        rejected(&post(
+            "Content-Type: application/dns-message\r\nTransfer-Encoding: chunked\r\n"
+        )),
+        Some(Status::NotImplemented)
+    );
+    assert_eq!(
+        rejected(&post(
+            "Content-Type: application/dns-message\r\nContent-Length: 65536\r\n"
+        )),
+        Some(Status::PayloadTooLarge)
+    );
+
+    let long = format!("GET /dns-query?dns={} HTTP/1.1\r\n\r\n", "A".repeat(10_000));
+    assert_eq!(rejected(long.as_bytes()), Some(Status::UriTooLong));
+}
+
+#[test]
+fn cut_off_requests() {
+    assert!(matches!(
+        read(b"GET /dns-query?dns=AAA HTTP/1.1\r\nHost: dns.example\r\n"),
+        Err(DohError::Io(_))
+    ));
+    assert!(matches!(
+        read(
+            b"POST /dns-query HTTP/1.1\r\n\
+              Content-Type: application/dns-message\r\n\
+              Content-Length: 10\r\n\r\n\
+              abc"
+        ),
+        Err(DohError::Io(_))
+    ));
+}
@@ -0,0 +1,114 @@
+use std::net::Ipv4Addr;
+
+use types::{
+    Domain, Header, Message, OpCode, RData, RecordClass, RecordType, ResCode, ResourceRecord, Soa,
+};
+
+use crate::doh::*;
+
+fn message(
+    rescode: ResCode,
+    answers: Vec<ResourceRecord>,
+    authorities: Vec<ResourceRecord>,
+) -> Message {
+    Message {
+        header: Header {
+            id: 0,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: false,
+            is_truncated: false,
+            should_recurse: true,
+            recursion_available: true,
+            _z: 0,
+            rescode,
+            questions: 0,
+            answer_records: 0,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: vec![],
+        answers,
+        authorities,
+        additional: vec![],
+        edns: None,
+    }
+}
+
+fn a(ttl: u32) -> ResourceRecord {
+    ResourceRecord {
+        name: Domain(vec!["example".into(), "com".into()]),
+        rtype: RecordType::A,
+        rclass: RecordClass::IN,
+        ttl,
+        data: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+    }
+}
+
+fn soa(ttl: u32, minimum: u32) -> ResourceRecord {
+    ResourceRecord {
+        name: Domain(vec!["example".into(), "com".into()]),
+        rtype: RecordType::SOA,
+        rclass: RecordClass::IN,
+        ttl,
+        data: RData::SOA(Soa {
+            mname: Domain(vec!["ns".into(), "example".into(), "com".into()]),
+            rname: Domain(vec!["admin".into(), "example".into(), "com".into()]),
+            serial: 1,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1_209_600,
+            minimum,
+        }),
+    }
+}
+
+#[test]
+fn max_ages() {
+    assert_eq!(
+        max_age(&message(
+            ResCode::NoError,
+            vec![a(300), a(60), a(3600)],
+            vec![]
+        )),
+        60
+    );
+    // Negative answers last for the lower of the SOA's TTL and minimum
+    assert_eq!(
+        max_age(&message(ResCode::NameError, vec![], vec![soa(3600, 900)])),
+        900
+    );
+    assert_eq!(
+        max_age(&message(ResCode::NoError, vec![], vec![soa(600, 900)])),
+        600
+    );
+    assert_eq!(max_age(&message(ResCode::NoError, vec![], vec![])), 0);
+    assert_eq!(
+        max_age(&message(ResCode::ServerFailure, vec![a(300)], vec![])),
+        0
+    );
+}
+
+#[test]
+fn written_responses() {
+    let mut response = vec![];
+    write_response(&mut response, Status::Ok, Some((b"abc", 60)), true).unwrap();
+    assert_eq!(
+        String::from_utf8(response).unwrap(),
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: application/dns-message\r\n\
+         Cache-Control: max-age=60\r\n\
+         Content-Length: 3\r\n\r\n\
+         abc"
+    );
+
+    let mut response = vec![];
+    write_response(&mut response, Status::MethodNotAllowed, None, false).unwrap();
+    assert_eq!(
+        String::from_utf8(response).unwrap(),
+        "HTTP/1.1 405 Method Not Allowed\r\n\
+         Allow: GET, POST\r\n\
+         Connection: close\r\n\
+         Content-Length: 0\r\n\r\n"
+    );
+}
@@ -0,0 +1,172 @@
+use std::{io, net::UdpSocket, sync::Arc, time::Duration};
+
+use bytes::BytesMut;
+use quinn::{
+    crypto::rustls::{NoInitialCipherSuite, QuicServerConfig},
+    Connection, ConnectionError, Endpoint, EndpointConfig, IdleTimeout, ReadToEndError, RecvStream,
+    SendStream, ServerConfig, TokioRuntime, TransportConfig, VarInt,
+};
+use thiserror::Error;
+use tracing::debug;
+use types::{serializer::Serializable, Message};
+
+/// Error codes connections get closed with (RFC 9250 section 4.3)
+const DOQ_INTERNAL_ERROR: VarInt = VarInt::from_u32(0x1);
+const DOQ_PROTOCOL_ERROR: VarInt = VarInt::from_u32(0x2);
+
+/// Largest query accepted, a DNS message and its length
+const MAX_QUERY_SIZE: usize = 2 + u16::MAX as usize;
+
+#[derive(Error, Debug)]
+pub enum DoqError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("TLS config can't be used for QUIC: {0}")]
+    Tls(#[from] NoInitialCipherSuite),
+}
+
+/// What each connection gets
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// How long a connection stays open without any queries
+    pub idle: Duration,
+    /// Queries on one connection answered at once
+    pub streams: u32,
+}
+
+/// Resolves a query in wire format, `None` when it's too broken to answer
+type Answer = Arc<dyn Fn(Vec<u8>) -> Option<Message> + Send + Sync>;
+
+/// Answers DNS over QUIC (RFC 9250) on `socket`, each query on a stream of its own. `answer` runs
+/// on a thread of its own for each query, so it can block
+pub fn serve(
+    socket: UdpSocket,
+    tls: Arc<rustls::ServerConfig>,
+    limits: Limits,
+    answer: impl Fn(Vec<u8>) -> Option<Message> + Send + Sync + 'static,
+) -> Result<(), DoqError> {
+    let mut transport = TransportConfig::default();
+    // Longer than QUIC can say is as long as it can
+    transport.max_idle_timeout(Some(
+        limits
+            .idle
+            .try_into()
+            .unwrap_or(IdleTimeout::from(VarInt::MAX)),
+    ));
+    transport.max_concurrent_bidi_streams(limits.streams.into());
+    // Nothing in DoQ goes over unidirectional streams
+    transport.max_concurrent_uni_streams(0_u8.into());
+
+    let mut config = ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls)?));
+    config.transport_config(Arc::new(transport));
+
+    let answer: Answer = Arc::new(answer);
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let endpoint = Endpoint::new(
+                EndpointConfig::default(),
+                Some(config),
+                socket,
+                Arc::new(TokioRuntime),
+            )?;
+
+            while let Some(incoming) = endpoint.accept().await {
+                let answer = answer.clone();
+                tokio::spawn(async move {
+                    match incoming.await {
+                        Ok(connection) => serve_connection(&connection, &answer).await,
+                        Err(err) => debug!("QUIC handshake failed: {err}"),
+                    }
+                });
+            }
+
+            Ok(())
+        })
+}
+
+async fn serve_connection(connection: &Connection, answer: &Answer) {
+    loop {
+        let (send, recv) = match connection.accept_bi().await {
+            Ok(streams) => streams,
+            // The client being done or going quiet for too long are the normal ways for it to end
+            Err(ConnectionError::ApplicationClosed(_) | ConnectionError::TimedOut) => return,
+            Err(err) => {
+                debug!("QUIC connection ended with an error: {err}");
+                return;
+            }
+        };
+
+        let connection = connection.clone();
+        let answer = answer.clone();
+        tokio::spawn(async move {
+            if let Err(code) = serve_stream(send, recv, answer).await {
+                connection.close(code, b"");
+            }
+        });
+    }
+}
+
+/// Answers the query on one stream, failing with the code to close the connection with
+async fn serve_stream(
+    mut send: SendStream,
+    mut recv: RecvStream,
+    answer: Answer,
+) -> Result<(), VarInt> {
+    let data = match recv.read_to_end(MAX_QUERY_SIZE).await {
+        Ok(data) => data,
+        Err(ReadToEndError::TooLong) => return Err(DOQ_PROTOCOL_ERROR),
+        // The client cancelled the query or the connection is gone
+        Err(ReadToEndError::Read(_)) => return Ok(()),
+    };
+
+    // The whole stream is one length prefixed query, with ID 0 (RFC 9250 section 4.2.1)
+    let query = match data.split_first_chunk() {
+        Some((size, query))
+            if usize::from(u16::from_be_bytes(*size)) == query.len()
+                && query.starts_with(&[0, 0]) =>
+        {
+            query.to_vec()
+        }
+        _ => return Err(DOQ_PROTOCOL_ERROR),
+    };
+
+    let mut response = tokio::task::spawn_blocking(move || answer(query))
+        .await
+        .map_err(|_| DOQ_INTERNAL_ERROR)?
+        .ok_or(DOQ_PROTOCOL_ERROR)?;
+
+    let mut buf = BytesMut::new();
+    response
+        .serialize(&mut buf)
+        .map_err(|_| DOQ_INTERNAL_ERROR)?;
+
+    // The length prefix can't go past 65535 bytes
+    if u16::try_from(buf.len()).is_err() {
+        debug!("DoQ response was truncated...");
+
+        response.header.is_truncated = true;
+        response.answers.clear();
+        response.authorities.clear();
+        response.additional.clear();
+
+        buf.clear();
+        response
+            .serialize(&mut buf)
+            .map_err(|_| DOQ_INTERNAL_ERROR)?;
+    }
+
+    let size = u16::try_from(buf.len()).map_err(|_| DOQ_INTERNAL_ERROR)?;
+    let mut framed = Vec::with_capacity(buf.len() + 2);
+    framed.extend_from_slice(&size.to_be_bytes());
+    framed.extend_from_slice(&buf);
+
+    // Failing means the client isn't waiting for the response anymore
+    if send.write_all(&framed).await.is_ok() {
+        let _ = send.finish();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use types::{
+    parser::{BytesBuf, Parsable},
+    Domain, Message, Question, RecordClass, RecordType,
+};
+use utils::{
+    make_request_with_options, spki_pin, RequestError, RequestOptions, TlsOptions, Transport,
+    DOQ_ALPN,
+};
+
+use crate::{doq::*, tls};
+
+fn file(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/tls/tests")
+        .join(name)
+}
+
+/// DNS over QUIC server on a new port, answering with `answer`
+fn server(answer: fn(Message) -> Option<Message>) -> SocketAddr {
+    let tls =
+        tls::server_config(&file("localhost.crt"), &file("localhost.key"), &[DOQ_ALPN]).unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    let limits = Limits {
+        idle: Duration::from_secs(10),
+        streams: 4,
+    };
+    thread::spawn(move || {
+        serve(socket, tls, limits, move |data| {
+            answer(Message::parse(&mut BytesBuf::from_bytes(data.into())).unwrap())
+        })
+    });
+
+    addr
+}
+
+fn request(addr: SocketAddr) -> Result<Message, RequestError> {
+    let question = Question {
+        name: Domain(vec!["www".into(), "example".into()]),
+        qtype: RecordType::A,
+        qclass: RecordClass::IN,
+    };
+    // The test certificate's CA isn't around, its key is enough
+    let certificate = &tls::read_certificates(&file("localhost.crt")).unwrap()[0];
+    let options = RequestOptions {
+        tls: TlsOptions {
+            spki_pins: vec![spki_pin(certificate).unwrap()],
+            ..TlsOptions::default()
+        },
+        ..RequestOptions::default()
+    };
+
+    make_request_with_options(question, addr, Transport::Quic, &options)
+}
+
+#[test]
+fn answers_queries() {
+    let addr = server(|mut query| {
+        assert_eq!(query.header.id, 0);
+        query.header.is_response = true;
+        Some(query)
+    });
+
+    for _ in 0..2 {
+        let response = request(addr).unwrap();
+        assert!(response.header.is_response);
+        assert_eq!(response.questions[0].name.0, ["www", "example"]);
+    }
+}
+
+#[test]
+fn unanswerable_queries() {
+    let addr = server(|_| None);
+
+    assert!(matches!(request(addr), Err(RequestError::Closed)));
+}
@@ -0,0 +1,76 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use serde::Deserialize;
+use types::RecordType;
+
+/// Which address families upstream servers get contacted over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FamilyPolicy {
+    Ipv4Only,
+    Ipv6Only,
+    /// Both, falling back to IPv6 once every IPv4 server has failed
+    PreferIpv4,
+    /// Both, falling back to IPv4 once every IPv6 server has failed
+    PreferIpv6,
+}
+
+impl FamilyPolicy {
+    /// Policy for a host that can reach the given families, IPv4 is preferred
+    /// when both work since broken IPv6 connectivity is still more common
+    pub fn from_reachability(ipv4: bool, ipv6: bool) -> FamilyPolicy {
+        match (ipv4, ipv6) {
+            (false, true) => FamilyPolicy::Ipv6Only,
+            (true, false) => FamilyPolicy::Ipv4Only,
+            _ => FamilyPolicy::PreferIpv4,
+        }
+    }
+
+    /// Checks which families have a route to the given addresses, no packets get sent
+    pub fn detect(probes: &[IpAddr]) -> FamilyPolicy {
+        let reachable = |v6: bool| {
+            probes.iter().filter(|ip| ip.is_ipv6() == v6).any(|ip| {
+                let local = if v6 {
+                    IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+                } else {
+                    IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+                };
+
+                UdpSocket::bind(SocketAddr::new(local, 0))
+                    .and_then(|socket| socket.connect(SocketAddr::new(*ip, 53)))
+                    .is_ok()
+            })
+        };
+
+        FamilyPolicy::from_reachability(reachable(false), reachable(true))
+    }
+
+    pub fn allows(self, ip: &IpAddr) -> bool {
+        match self {
+            FamilyPolicy::Ipv4Only => ip.is_ipv4(),
+            FamilyPolicy::Ipv6Only => ip.is_ipv6(),
+            FamilyPolicy::PreferIpv4 | FamilyPolicy::PreferIpv6 => true,
+        }
+    }
+
+    fn prefers_ipv6(self) -> bool {
+        matches!(self, FamilyPolicy::Ipv6Only | FamilyPolicy::PreferIpv6)
+    }
+
+    /// Drops servers of families that aren't allowed and moves the preferred family
+    /// to the front, keeping the existing order inside each family
+    pub fn order(self, servers: &mut Vec<IpAddr>) {
+        servers.retain(|ip| self.allows(ip));
+        servers.sort_by_key(|ip| ip.is_ipv6() != self.prefers_ipv6());
+    }
+
+    /// Address record types to look name servers up with, most preferred first
+    pub fn record_types(self) -> &'static [RecordType] {
+        match self {
+            FamilyPolicy::Ipv4Only => &[RecordType::A],
+            FamilyPolicy::Ipv6Only => &[RecordType::AAAA],
+            FamilyPolicy::PreferIpv4 => &[RecordType::A, RecordType::AAAA],
+            FamilyPolicy::PreferIpv6 => &[RecordType::AAAA, RecordType::A],
+        }
+    }
+}
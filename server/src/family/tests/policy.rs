@@ -0,0 +1,69 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use types::RecordType;
+
+use crate::family::*;
+
+fn v4(last: u8) -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(192, 0, 2, last))
+}
+
+fn v6(last: u16) -> IpAddr {
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, last))
+}
+
+#[test]
+fn reachability() {
+    assert_eq!(
+        FamilyPolicy::from_reachability(true, true),
+        FamilyPolicy::PreferIpv4
+    );
+    assert_eq!(
+        FamilyPolicy::from_reachability(true, false),
+        FamilyPolicy::Ipv4Only
+    );
+    assert_eq!(
+        FamilyPolicy::from_reachability(false, true),
+        FamilyPolicy::Ipv6Only
+    );
+    // Nothing to lose by trying everything
+    assert_eq!(
+        FamilyPolicy::from_reachability(false, false),
+        FamilyPolicy::PreferIpv4
+    );
+}
+
+#[test]
+fn preferred_family_first() {
+    let servers = vec![v6(1), v4(1), v6(2), v4(2)];
+
+    let mut ordered = servers.clone();
+    FamilyPolicy::PreferIpv4.order(&mut ordered);
+    assert_eq!(ordered, vec![v4(1), v4(2), v6(1), v6(2)]);
+
+    let mut ordered = servers.clone();
+    FamilyPolicy::PreferIpv6.order(&mut ordered);
+    assert_eq!(ordered, vec![v6(1), v6(2), v4(1), v4(2)]);
+}
+
+#[test]
+fn single_family() {
+    let servers = vec![v6(1), v4(1), v6(2), v4(2)];
+
+    let mut ordered = servers.clone();
+    FamilyPolicy::Ipv4Only.order(&mut ordered);
+    assert_eq!(ordered, vec![v4(1), v4(2)]);
+
+    let mut ordered = servers.clone();
+    FamilyPolicy::Ipv6Only.order(&mut ordered);
+    assert_eq!(ordered, vec![v6(1), v6(2)]);
+}
+
+#[test]
+fn address_record_types() {
+    assert_eq!(
+        FamilyPolicy::PreferIpv6.record_types(),
+        &[RecordType::AAAA, RecordType::A]
+    );
+    assert_eq!(FamilyPolicy::Ipv4Only.record_types(), &[RecordType::A]);
+}
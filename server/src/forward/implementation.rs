@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use types::Domain;
+
+use crate::config::{ForwardRule, UpstreamServer};
+
+/// How long an upstream is skipped after its first failure, doubling with every one after
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_mins(5);
+
+/// Lowercase name out of a zone written in a config file, `.` being the root
+pub fn zone_name(zone: &str) -> Domain {
+    Domain(
+        zone.split('.')
+            .filter(|label| !label.is_empty())
+            .map(str::to_lowercase)
+            .collect(),
+    )
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    /// Failures since the last success
+    failures: u32,
+    /// Only tried before this once every healthy upstream has failed
+    down_until: Option<Instant>,
+}
+
+/// Forwarding rules, along with how well their upstreams have been answering
+#[derive(Debug)]
+pub struct Forwarders {
+    rules: Vec<(Domain, Vec<UpstreamServer>)>,
+    health: Mutex<HashMap<UpstreamServer, Health>>,
+}
+
+impl Forwarders {
+    pub fn new(rules: &[ForwardRule]) -> Forwarders {
+        Forwarders {
+            rules: rules
+                .iter()
+                .map(|rule| (zone_name(&rule.zone), rule.upstreams.clone()))
+                .collect(),
+            health: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<UpstreamServer, Health>> {
+        // A panic while holding the lock can't leave the map in a broken state
+        self.health
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Zone (lowercase) and upstreams of the most specific rule covering `name`, if any
+    pub fn find(&self, name: &Domain) -> Option<(&Domain, &[UpstreamServer])> {
+        let name = name.to_lowercase();
+
+        self.rules
+            .iter()
+            .filter(|(zone, _)| name.0.ends_with(&zone.0))
+            .max_by_key(|(zone, _)| zone.0.len())
+            .map(|(zone, upstreams)| (zone, upstreams.as_slice()))
+    }
+
+    /// Upstreams in the order to try them, the ones that are up keep their configured order
+    /// and the ones that are down come last, soonest to recover first
+    pub fn order(&self, upstreams: &[UpstreamServer]) -> Vec<UpstreamServer> {
+        self.order_at(upstreams, Instant::now())
+    }
+
+    pub(crate) fn order_at(
+        &self,
+        upstreams: &[UpstreamServer],
+        now: Instant,
+    ) -> Vec<UpstreamServer> {
+        let health = self.lock();
+
+        let mut ordered = upstreams.to_vec();
+        ordered.sort_by_key(|upstream| {
+            health
+                .get(upstream)
+                .and_then(|health| health.down_until)
+                .filter(|until| *until > now)
+        });
+
+        ordered
+    }
+
+    pub fn record_success(&self, upstream: UpstreamServer) {
+        self.lock().remove(&upstream);
+    }
+
+    pub fn record_failure(&self, upstream: UpstreamServer) {
+        self.record_failure_at(upstream, Instant::now());
+    }
+
+    pub(crate) fn record_failure_at(&self, upstream: UpstreamServer, now: Instant) {
+        let mut health = self.lock();
+        let health = health.entry(upstream).or_default();
+
+        let backoff = BASE_BACKOFF
+            .saturating_mul(2_u32.saturating_pow(health.failures))
+            .min(MAX_BACKOFF);
+
+        health.failures += 1;
+        health.down_until = Some(now + backoff);
+    }
+}
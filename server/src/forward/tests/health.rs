@@ -0,0 +1,116 @@
+use std::time::{Duration, Instant};
+
+use types::Domain;
+
+use crate::{
+    config::{ForwardRule, UpstreamServer, UpstreamTransport},
+    forward::*,
+};
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+fn upstream(address: &str) -> UpstreamServer {
+    UpstreamServer {
+        address: address.parse().unwrap(),
+        transport: UpstreamTransport::Auto,
+    }
+}
+
+fn forwarders() -> Forwarders {
+    Forwarders::new(&[
+        ForwardRule {
+            zone: ".".into(),
+            upstreams: vec![upstream("192.0.2.1:53"), upstream("192.0.2.2:53")],
+        },
+        ForwardRule {
+            zone: "Corp.Example.".into(),
+            upstreams: vec![upstream("10.0.0.53:53")],
+        },
+    ])
+}
+
+#[test]
+fn zone_names() {
+    assert_eq!(zone_name("."), Domain(vec![]));
+    assert_eq!(zone_name("Corp.Example."), domain("corp.example"));
+    assert_eq!(zone_name("corp.example"), domain("corp.example"));
+}
+
+#[test]
+fn most_specific_rule() {
+    let forwarders = forwarders();
+
+    assert_eq!(
+        forwarders.find(&domain("www.CORP.example")),
+        Some((&domain("corp.example"), &[upstream("10.0.0.53:53")][..]))
+    );
+    assert_eq!(
+        forwarders.find(&domain("corp.example")),
+        Some((&domain("corp.example"), &[upstream("10.0.0.53:53")][..]))
+    );
+    assert_eq!(
+        forwarders
+            .find(&domain("notcorp.example"))
+            .map(|(zone, upstreams)| (zone.clone(), upstreams.len())),
+        Some((Domain(vec![]), 2))
+    );
+
+    let conditional = Forwarders::new(&[ForwardRule {
+        zone: "corp.example".into(),
+        upstreams: vec![upstream("10.0.0.53:53")],
+    }]);
+    assert_eq!(conditional.find(&domain("example.com")), None);
+}
+
+#[test]
+fn failing_upstreams_go_last() {
+    let forwarders = forwarders();
+    let now = Instant::now();
+    let upstreams = [
+        upstream("192.0.2.1:53"),
+        upstream("192.0.2.2:53"),
+        upstream("192.0.2.3:53"),
+    ];
+
+    forwarders.record_failure_at(upstreams[0], now);
+    forwarders.record_failure_at(upstreams[0], now);
+    forwarders.record_failure_at(upstreams[1], now);
+
+    assert_eq!(
+        forwarders.order_at(&upstreams, now),
+        vec![upstreams[2], upstreams[1], upstreams[0]]
+    );
+
+    // The first failure backs off for 5 seconds, the second for 10
+    assert_eq!(
+        forwarders.order_at(&upstreams, now + Duration::from_secs(6)),
+        vec![upstreams[1], upstreams[2], upstreams[0]]
+    );
+    assert_eq!(
+        forwarders.order_at(&upstreams, now + Duration::from_secs(11)),
+        upstreams.to_vec()
+    );
+}
+
+#[test]
+fn success_resets_backoff() {
+    let forwarders = forwarders();
+    let now = Instant::now();
+    let upstreams = [upstream("192.0.2.1:53"), upstream("192.0.2.2:53")];
+
+    for _ in 0..16 {
+        forwarders.record_failure_at(upstreams[0], now);
+    }
+
+    // Backoff is capped
+    assert_eq!(
+        forwarders.order_at(&upstreams, now + Duration::from_mins(5)),
+        upstreams.to_vec()
+    );
+
+    forwarders.record_failure_at(upstreams[0], now);
+    forwarders.record_success(upstreams[0]);
+    assert_eq!(forwarders.order_at(&upstreams, now), upstreams.to_vec());
+}
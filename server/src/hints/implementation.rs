@@ -0,0 +1,104 @@
+use std::{net::IpAddr, path::Path};
+
+use thiserror::Error;
+use types::{
+    zone::{parse_zone, read_zone_file, ZoneError},
+    Domain, RData, RecordType, ResourceRecord,
+};
+
+/// IANA's root hints, from <https://www.internic.net/domain/named.root>
+const BUILTIN_HINTS: &str = include_str!("named.root");
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum HintsError {
+    #[error("{0}")]
+    Zone(#[from] ZoneError),
+    #[error("Root hints have an NS record for {0}, they can only have them for the root")]
+    NotRoot(Domain),
+    #[error("Root hints don't have any NS records")]
+    NoNameservers,
+    #[error("Root hints don't have an address for any of the root name servers")]
+    NoAddresses,
+}
+
+/// Where to find the root name servers before priming (RFC 8109) has told us the current ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootHints {
+    /// NS records for the root
+    pub nameservers: Vec<ResourceRecord>,
+    /// A and AAAA records of the name servers
+    pub addresses: Vec<ResourceRecord>,
+}
+
+impl RootHints {
+    /// Hints for the root servers of the internet
+    pub fn builtin() -> RootHints {
+        RootHints::parse(BUILTIN_HINTS).expect("Built in root hints should be valid")
+    }
+
+    /// Hints in zone file format, like the `named.root` file IANA publishes.
+    /// Records other than the root's NS records and their addresses are ignored
+    pub fn parse(input: &str) -> Result<RootHints, HintsError> {
+        RootHints::from_records(parse_zone(input, Some(&Domain(vec![])))?)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<RootHints, HintsError> {
+        RootHints::from_records(read_zone_file(path, Some(&Domain(vec![])))?)
+    }
+
+    fn from_records(records: Vec<ResourceRecord>) -> Result<RootHints, HintsError> {
+        let mut nameservers = vec![];
+        let mut addresses = vec![];
+
+        for record in &records {
+            if record.rtype != RecordType::NS {
+                continue;
+            }
+
+            if !record.name.0.is_empty() {
+                return Err(HintsError::NotRoot(record.name.clone()));
+            }
+
+            nameservers.push(record.clone());
+        }
+
+        if nameservers.is_empty() {
+            return Err(HintsError::NoNameservers);
+        }
+
+        for record in records {
+            if !matches!(record.rtype, RecordType::A | RecordType::AAAA) {
+                continue;
+            }
+
+            let name = record.name.to_lowercase();
+            if nameservers
+                .iter()
+                .any(|ns| matches!(&ns.data, RData::NS(target) if target.to_lowercase() == name))
+            {
+                addresses.push(record);
+            }
+        }
+
+        if addresses.is_empty() {
+            return Err(HintsError::NoAddresses);
+        }
+
+        Ok(RootHints {
+            nameservers,
+            addresses,
+        })
+    }
+
+    /// Every address of every root name server
+    pub fn ips(&self) -> Vec<IpAddr> {
+        self.addresses
+            .iter()
+            .filter_map(|r| match r.data {
+                RData::A(ip) => Some(IpAddr::V4(ip)),
+                RData::AAAA(ip) => Some(IpAddr::V6(ip)),
+                _ => None,
+            })
+            .collect()
+    }
+}
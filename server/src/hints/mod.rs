@@ -0,0 +1,6 @@
+mod implementation;
+
+#[cfg(test)]
+mod tests;
+
+pub use implementation::*;
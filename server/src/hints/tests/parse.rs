@@ -0,0 +1,56 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use types::Domain;
+
+use crate::hints::*;
+
+fn domain(name: &str) -> Domain {
+    Domain(name.split('.').map(ToString::to_string).collect())
+}
+
+#[test]
+fn builtin_hints() {
+    let hints = RootHints::builtin();
+
+    assert_eq!(hints.nameservers.len(), 13);
+    assert_eq!(hints.addresses.len(), 26);
+
+    let ips = hints.ips();
+    assert_eq!(ips.iter().filter(|ip| ip.is_ipv4()).count(), 13);
+    assert_eq!(ips.iter().filter(|ip| ip.is_ipv6()).count(), 13);
+    assert!(ips.contains(&IpAddr::V4(Ipv4Addr::new(198, 41, 0, 4))));
+}
+
+#[test]
+fn private_root() {
+    let hints = RootHints::parse(
+        ". 3600 NS ns1.lab.\n\
+         ns1.lab. 3600 A 10.0.0.53\n\
+         ; Addresses of anything other than the root's name servers are left out\n\
+         www.lab. 3600 A 10.0.0.80\n",
+    )
+    .unwrap();
+
+    assert_eq!(hints.nameservers.len(), 1);
+    assert_eq!(hints.ips(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 53))]);
+}
+
+#[test]
+fn invalid_hints() {
+    assert_eq!(
+        RootHints::parse("ns1.lab. 3600 A 10.0.0.53\n"),
+        Err(HintsError::NoNameservers)
+    );
+    assert_eq!(
+        RootHints::parse(". 3600 NS ns1.lab.\n"),
+        Err(HintsError::NoAddresses)
+    );
+    assert_eq!(
+        RootHints::parse("lab. 3600 NS ns1.lab.\nns1.lab. 3600 A 10.0.0.53\n"),
+        Err(HintsError::NotRoot(domain("lab")))
+    );
+    assert!(matches!(
+        RootHints::parse(". 3600 NS"),
+        Err(HintsError::Zone(_))
+    ));
+}
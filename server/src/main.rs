@@ -1,118 +1,560 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::too_many_lines)]
 
-use anyhow::Result;
+use anyhow::{format_err, Result};
+use authority::{Catalog, Zone};
 use bytes::BytesMut;
+use cache::{Cache, Resolution};
+use chain::{Chain, Step};
+use clap::Parser;
+use config::{Config, Mode, Overload, UpstreamServer};
+use doh::{DohError, Status};
+use family::FamilyPolicy;
+use forward::Forwarders;
+use hints::RootHints;
+use rtt::RttTable;
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
 use std::{
-    io::{Read, Write},
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream, UdpSocket},
+    collections::HashSet,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, TrySendError},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
-use utils::make_request;
+use tracing::{debug, error, info, warn};
+use utils::{
+    stream::{Duplex, DuplexReader, TcpDuplex, TlsDuplex},
+    Pool, RequestOptions, DOQ_ALPN,
+};
+
+mod authority;
+mod cache;
+mod chain;
+mod config;
+mod doh;
+mod doq;
+mod family;
+mod forward;
+mod hints;
+mod rtt;
+mod tls;
 
 use types::{
     parser::{BytesBuf, Parsable},
     serializer::Serializable,
-    Domain, Header, Message, OpCode, Question, RecordClass, RecordType, ResCode,
+    zone::read_zone_file,
+    Domain, Edns, Header, Message, OpCode, Question, RData, RecordClass, RecordType, ResCode,
+    ResourceRecord,
 };
 
-static ROOT_SOURCE: SocketAddr =
-    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 41, 162, 30), 53));
-
+// https://www.rfc-editor.org/std/std75.txt
+// "The maximum allowable size of a DNS message over UDP not using the extensions described in this document is 512 bytes."
 const UDP_MAX_SIZE: usize = 512;
 
-// TODO: move error message and NXDOMAIN logic out of this func
-// by returning Result<Option<Message>> and having an outer func
-// that turns that into a Message
-fn resolve_domain(
-    id: u16,
-    request: Domain,
-    qtype: RecordType,
+/// Bounds on how long to wait before priming again, the root NS set's TTL is used in between
+const MIN_PRIMING_INTERVAL: Duration = Duration::from_mins(1);
+const MAX_PRIMING_INTERVAL: Duration = Duration::from_hours(24);
+
+/// Pause after failing to accept a connection
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Everything shared between requests
+struct State {
+    config: Config,
+    catalog: Catalog,
+    forwarders: Forwarders,
+    /// Asks upstream servers, keeping connections to them open between queries
+    pool: Pool,
+    cache: Cache,
+    rtt: RttTable,
+    families: FamilyPolicy,
+    hints: RootHints,
+}
+
+/// EDNS info to respond with, only if the client sent some
+fn response_edns(request: Option<&Edns>, max_payload_size: u16) -> Option<Edns> {
+    request.map(|edns| Edns::new(edns.udp_payload_size.min(max_payload_size)))
+}
+
+/// Most queries sent upstream on behalf of a single client question
+const MAX_QUERIES: usize = 64;
+
+/// How many name servers without glue can be looked up inside each other
+const MAX_NS_DEPTH: usize = 4;
+
+/// Work done on behalf of a single client question, shared with the lookups of
+/// name server addresses it needs so those can't loop or fan out forever
+#[derive(Debug, Default)]
+struct Lookup {
+    queries: usize,
+    /// Name servers whose addresses are being looked up, lowercase
+    pending: Vec<Domain>,
+}
+
+/// How a server's response moves resolution along
+enum Outcome {
+    Resolved(Resolution),
+    /// Delegation to a zone closer to the name
+    Referral {
+        zone: Domain,
+        nameservers: Vec<ResourceRecord>,
+        glue: Vec<ResourceRecord>,
+    },
+    /// Errors, refusals and responses that don't help, another server has to be asked
+    Lame(String),
+}
+
+/// Makes sense of a response from a name server of `zone` (lowercase),
+/// caching everything it can be trusted for
+fn classify_response(res: Message, question: &Question, zone: &Domain, cache: &Cache) -> Outcome {
+    if !matches!(res.header.rescode, ResCode::NoError | ResCode::NameError) {
+        return Outcome::Lame(format!("responded with {:?}", res.header.rescode));
+    }
+
+    if let Some(resolution) = cache.insert_response(&res, question, zone) {
+        return Outcome::Resolved(resolution);
+    }
+
+    if res.header.rescode == ResCode::NameError {
+        return Outcome::Lame("NXDOMAIN without an SOA".into());
+    }
+
+    let Some(cut) = res
+        .authorities
+        .iter()
+        .find(|r| r.rtype == RecordType::NS)
+        .map(|r| r.name.to_lowercase())
+    else {
+        return Outcome::Lame("no answer or referral".into());
+    };
+
+    // Referrals have to get closer to the name, anything else goes in circles
+    let name = question.name.to_lowercase();
+    if cut.0.len() <= zone.0.len() || !cut.0.ends_with(&zone.0) || !name.0.ends_with(&cut.0) {
+        return Outcome::Lame(format!("referral to {cut} while asking {zone}"));
+    }
+
+    let nameservers: Vec<ResourceRecord> = res
+        .authorities
+        .into_iter()
+        .filter(|r| r.rtype == RecordType::NS && r.name.to_lowercase() == cut)
+        .collect();
+
+    // Only glue inside the responding server's zone is believed,
+    // a server can't vouch for the addresses of hosts it isn't responsible for
+    let glue: Vec<ResourceRecord> = res
+        .additional
+        .into_iter()
+        .filter(|r| {
+            matches!(r.rtype, RecordType::A | RecordType::AAAA)
+                && r.name.to_lowercase().0.ends_with(&zone.0)
+                && nameservers
+                    .iter()
+                    .any(|ns| matches!(&ns.data, RData::NS(target) if *target == r.name))
+        })
+        .collect();
+
+    cache.insert(&nameservers);
+    cache.insert(&glue);
+
+    Outcome::Referral {
+        zone: cut,
+        nameservers,
+        glue,
+    }
+}
+
+/// Addresses in A and AAAA records
+fn addresses(records: &[ResourceRecord]) -> Vec<IpAddr> {
+    records
+        .iter()
+        .filter_map(|r| match r.data {
+            RData::A(ip) => Some(IpAddr::V4(ip)),
+            RData::AAAA(ip) => Some(IpAddr::V6(ip)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Addresses of a name server that came without glue, resolved like any other name.
+/// Only asks for the less preferred address family if the preferred one has none
+fn nameserver_addresses(
+    nameserver: &Domain,
     qclass: RecordClass,
-    source: SocketAddr,
-) -> Result<Option<Message>> {
-    let res = make_request(
-        Question {
-            name: request.clone(),
-            qtype,
-            qclass,
-        },
-        source,
-        // First tries UDP then falls back to TCP
-        utils::Transport::Unspecified,
-    )?;
+    state: &State,
+    lookup: &mut Lookup,
+) -> Vec<IpAddr> {
+    let name = nameserver.to_lowercase();
+    if lookup.pending.contains(&name) || lookup.pending.len() >= MAX_NS_DEPTH {
+        return vec![];
+    }
 
-    if res.header.answer_records > 0 {
-        return Ok(Some(Message {
-            header: Header {
-                id,
-                is_response: true,
-                opcode: OpCode::Query,
-                is_authoritative: false,
-                is_truncated: false,
-                should_recurse: false,
-                recursion_available: true,
-                _z: 0,
-                rescode: ResCode::NoError,
-                questions: 0,
-                answer_records: res.header.answer_records,
-                authority_records: 0,
-                additional_records: 0,
+    lookup.pending.push(name);
+
+    let mut found = vec![];
+    for qtype in state.families.record_types() {
+        let result = resolve_chain(
+            &Question {
+                name: nameserver.clone(),
+                qtype: *qtype,
+                qclass,
             },
-            questions: vec![],
-            answers: res.answers,
-            authorities: vec![],
-            additional: vec![],
-        }));
+            state,
+            lookup,
+        );
+
+        match result {
+            Ok((_, Resolution::Answer(records))) => found = addresses(&records),
+            Ok(_) => {}
+            Err(err) => {
+                warn!("Couldn't find the {qtype:?} address of name server {nameserver}: {err}");
+            }
+        }
+
+        if !found.is_empty() {
+            break;
+        }
     }
 
-    if res.header.authority_records > 0 && res.header.additional_records > 0 {
-        let mut authority_sources = vec![];
-        for authority in &res.authorities {
-            if let Some(domain) = &authority.domain_data {
-                for additional in &res.additional {
-                    if additional.name == *domain.first().expect("Has to exist")
-                        && additional.rtype == RecordType::A
-                    {
-                        authority_sources.push(Ipv4Addr::new(
-                            additional.data[0],
-                            additional.data[1],
-                            additional.data[2],
-                            additional.data[3],
-                        ));
-                    }
-                }
+    lookup.pending.pop();
+    found
+}
+
+/// Asks the name servers of `zone` about `question`, following referrals until there is an answer.
+/// The fastest known server goes first, and every other one is tried if it doesn't respond usefully
+fn resolve_domain(
+    question: &Question,
+    mut zone: Domain,
+    mut servers: Vec<IpAddr>,
+    state: &State,
+    lookup: &mut Lookup,
+) -> Result<Resolution> {
+    // Name servers of the current zone that came without glue,
+    // they only get looked up once every known address has failed
+    let mut unresolved: Vec<Domain> = vec![];
+    let mut tried: HashSet<IpAddr> = HashSet::new();
+
+    loop {
+        servers.retain(|server| !tried.contains(server));
+
+        if servers.is_empty() {
+            if unresolved.is_empty() {
+                return Err(format_err!(
+                    "No name server for {zone} could answer for {}",
+                    question.name
+                ));
             }
+
+            let nameserver = unresolved.remove(0);
+            servers = nameserver_addresses(&nameserver, question.qclass, state, lookup);
+            continue;
         }
 
-        if authority_sources.is_empty() {
-            return Ok(None);
+        state.rtt.sort(&mut servers);
+        state.families.order(&mut servers);
+
+        if servers.is_empty() {
+            continue;
         }
 
-        // TODO: maybe backtrack and try a different authority if one returns NXDOMAIN
-        return resolve_domain(
-            id,
-            request,
-            qtype,
-            qclass,
-            SocketAddr::V4(SocketAddrV4::new(authority_sources[0], 53)),
+        let server = servers.remove(0);
+        tried.insert(server);
+
+        lookup.queries += 1;
+        if lookup.queries > MAX_QUERIES {
+            return Err(format_err!(
+                "Gave up on {} after {MAX_QUERIES} queries",
+                question.name
+            ));
+        }
+
+        let start = Instant::now();
+        let res = match state.pool.request(
+            question.clone(),
+            SocketAddr::new(server, 53),
+            // First tries UDP then falls back to TCP
+            utils::Transport::Unspecified,
+        ) {
+            Ok(res) => res,
+            Err(err) => {
+                warn!("{server} didn't respond about {zone}: {err}");
+                state.rtt.record_failure(server);
+                continue;
+            }
+        };
+        let rtt = start.elapsed();
+
+        match classify_response(res, question, &zone, &state.cache) {
+            Outcome::Resolved(resolution) => {
+                state.rtt.record(server, rtt);
+                return Ok(resolution);
+            }
+            Outcome::Referral {
+                zone: cut,
+                nameservers,
+                glue,
+            } => {
+                state.rtt.record(server, rtt);
+
+                // Glue in a family that can't be used is as good as none
+                let glue: Vec<ResourceRecord> = glue
+                    .into_iter()
+                    .filter(|r| {
+                        addresses(std::slice::from_ref(r))
+                            .iter()
+                            .all(|ip| state.families.allows(ip))
+                    })
+                    .collect();
+
+                servers = addresses(&glue);
+                unresolved = nameservers
+                    .iter()
+                    .filter_map(|ns| match &ns.data {
+                        RData::NS(target) if !glue.iter().any(|r| r.name == *target) => {
+                            Some(target.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                tried.clear();
+                zone = cut;
+            }
+            Outcome::Lame(reason) => {
+                warn!("{server} is lame for {zone}: {reason}");
+                state.rtt.record_failure(server);
+            }
+        }
+    }
+}
+
+/// Hands a question to the upstreams of the forwarding rule for `zone` (lowercase), trying them until
+/// one responds usefully. Upstreams are only believed about names in that zone, the way authoritative
+/// servers are about theirs
+fn forward_domain(
+    question: &Question,
+    zone: &Domain,
+    upstreams: &[UpstreamServer],
+    state: &State,
+) -> Result<Resolution> {
+    for upstream in state.forwarders.order(upstreams) {
+        let res = match state.pool.request(
+            question.clone(),
+            upstream.address,
+            upstream.transport.into(),
+        ) {
+            Ok(res) => res,
+            Err(err) => {
+                warn!(
+                    "Upstream {} didn't respond about {}: {err}",
+                    upstream.address, question.name
+                );
+                state.forwarders.record_failure(upstream);
+                continue;
+            }
+        };
+
+        let resolution = if matches!(res.header.rescode, ResCode::NoError | ResCode::NameError) {
+            state.cache.insert_response(&res, question, zone)
+        } else {
+            None
+        };
+
+        if let Some(resolution) = resolution {
+            state.forwarders.record_success(upstream);
+            return Ok(resolution);
+        }
+
+        warn!(
+            "Upstream {} gave an unusable response about {} ({:?})",
+            upstream.address, question.name, res.header.rescode
         );
+        state.forwarders.record_failure(upstream);
     }
 
-    Ok(None)
+    Err(format_err!(
+        "No upstream could answer for {}",
+        question.name
+    ))
+}
+
+/// What the cache knows about a question, falling back to a CNAME for the name
+fn cached_resolution(
+    cache: &Cache,
+    name: &Domain,
+    qtype: RecordType,
+    qclass: RecordClass,
+) -> Option<Resolution> {
+    let resolution = cache.lookup(name, qtype, qclass);
+    if resolution.is_some() || qtype == RecordType::CNAME {
+        return resolution;
+    }
+
+    match cache.lookup(name, RecordType::CNAME, qclass)? {
+        Resolution::Answer(records) => Some(Resolution::Answer(records)),
+        Resolution::NameError(_) | Resolution::NoData(_) => None,
+    }
+}
+
+/// Resolves a question, restarting at the target of any CNAME or DNAME that doesn't answer it.
+/// Returns the aliases that were followed along with how the last name resolved
+fn resolve_chain(
+    question: &Question,
+    state: &State,
+    lookup: &mut Lookup,
+) -> Result<(Vec<ResourceRecord>, Resolution)> {
+    let mut chain = Chain::new(&question.name);
+    let mut name = question.name.clone();
+
+    loop {
+        let question_here = Question {
+            name: name.clone(),
+            ..question.clone()
+        };
+
+        let resolution = if let Some(resolution) =
+            cached_resolution(&state.cache, &name, question.qtype, question.qclass)
+        {
+            resolution
+        } else if let Some((zone, upstreams)) = state.forwarders.find(&name) {
+            forward_domain(&question_here, zone, upstreams, state)?
+        } else if state.config.mode == Mode::Forwarding {
+            return Err(format_err!("No forward rule covers {name}"));
+        } else {
+            // Skip as much of the tree as possible
+            let (zone, servers) = state
+                .cache
+                .closest_nameservers(&name, question.qclass)
+                .unwrap_or_else(|| (Domain(vec![]), state.hints.ips()));
+
+            resolve_domain(&question_here, zone, servers, state, lookup)?
+        };
+
+        let Resolution::Answer(records) = resolution else {
+            return Ok((chain.into_records(), resolution));
+        };
+
+        match chain.follow(&name, question.qtype, &records)? {
+            Step::Answered(answers) => {
+                return Ok((chain.into_records(), Resolution::Answer(answers)));
+            }
+            Step::Restart(target) => name = target,
+        }
+    }
+}
+
+/// Response for a recursive lookup, negative answers include the SOA of the zone.
+/// `aliases` go at the start of the answer section no matter how the chain ended
+fn resolution_message(
+    id: u16,
+    question: Question,
+    mut aliases: Vec<ResourceRecord>,
+    resolution: Resolution,
+) -> Message {
+    let (rescode, answers, authorities) = match resolution {
+        Resolution::Answer(answers) => (ResCode::NoError, answers, vec![]),
+        Resolution::NameError(soa) => (ResCode::NameError, vec![], vec![soa]),
+        Resolution::NoData(soa) => (ResCode::NoError, vec![], vec![soa]),
+    };
+    aliases.extend(answers);
+
+    Message {
+        header: Header {
+            id,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: false,
+            is_truncated: false,
+            should_recurse: false,
+            recursion_available: true,
+            _z: 0,
+            rescode,
+            questions: 0,
+            answer_records: 0,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: vec![question],
+        answers: aliases,
+        authorities,
+        additional: vec![],
+        edns: None,
+    }
+}
+
+/// Answer straight from one of our own zones. Only says recursion is available when `mode`
+/// resolves names outside of them
+fn authoritative_response(
+    id: u16,
+    question: Question,
+    zone: &Zone,
+    edns: Option<Edns>,
+    mode: Mode,
+) -> Message {
+    let answer = zone.lookup(&question.name, question.qtype, question.qclass);
+
+    Message {
+        header: Header {
+            id,
+            is_response: true,
+            opcode: OpCode::Query,
+            is_authoritative: answer.is_authoritative,
+            is_truncated: false,
+            should_recurse: false,
+            recursion_available: mode != Mode::Authoritative,
+            _z: 0,
+            rescode: answer.rescode,
+            questions: 0,
+            answer_records: 0,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: vec![question],
+        answers: answer.answers,
+        authorities: answer.authorities,
+        additional: answer.additional,
+        edns,
+    }
 }
 
+/// ID, question and EDNS info to answer a query that couldn't be resolved with
+type FailedQuery = (u16, Question, Option<Edns>);
+
 fn _recursive_resolve(
     transport: &'static str,
     mut data: BytesBuf,
-) -> std::result::Result<Message, (Option<u16>, anyhow::Error)> {
+    state: &State,
+) -> std::result::Result<Message, (Option<FailedQuery>, anyhow::Error)> {
     let mut msg = match Message::parse(&mut data) {
         Ok(msg) => msg,
         Err(err) => return Err((None, err.into())),
     };
 
-    if msg.header.questions != 1 || !msg.header.should_recurse {
+    let edns = response_edns(msg.edns.as_ref(), state.config.limits.udp_payload_size);
+
+    if msg.header.questions == 1 {
+        if let Some(zone) = state.catalog.find(&msg.questions[0].name) {
+            let q = msg.questions.remove(0);
+            info!("New {transport} authoritative lookup for: {}", q.name);
+
+            return Ok(authoritative_response(
+                msg.header.id,
+                q,
+                zone,
+                edns,
+                state.config.mode,
+            ));
+        }
+    }
+
+    if msg.header.questions != 1
+        || !msg.header.should_recurse
+        || state.config.mode == Mode::Authoritative
+    {
         return Ok(Message {
             header: Header {
                 id: msg.header.id,
@@ -129,52 +571,35 @@ fn _recursive_resolve(
                 authority_records: 0,
                 additional_records: 0,
             },
-            questions: vec![],
+            questions: msg.questions,
             answers: vec![],
             authorities: vec![],
             additional: vec![],
+            edns,
         });
     }
 
     let q = msg.questions.remove(0);
-    println!("New {transport} lookup for: {}", q.name);
-
-    match resolve_domain(msg.header.id, q.name, q.qtype, q.qclass, ROOT_SOURCE) {
-        Ok(res) => match res {
-            Some(msg) => Ok(msg),
-            None => Ok(Message {
-                header: Header {
-                    id: msg.header.id,
-                    is_response: true,
-                    opcode: OpCode::Query,
-                    is_authoritative: false,
-                    is_truncated: false,
-                    should_recurse: false,
-                    recursion_available: true,
-                    _z: 0,
-                    rescode: ResCode::NameError,
-                    questions: 0,
-                    answer_records: 0,
-                    authority_records: 0,
-                    additional_records: 0,
-                },
-                questions: vec![],
-                answers: vec![],
-                authorities: vec![],
-                additional: vec![],
-            }),
-        },
-        Err(err) => Err((Some(msg.header.id), err)),
+
+    info!("New {transport} lookup for: {}", q.name);
+
+    match resolve_chain(&q, state, &mut Lookup::default()) {
+        Ok((aliases, resolution)) => Ok(Message {
+            edns,
+            ..resolution_message(msg.header.id, q, aliases, resolution)
+        }),
+        Err(err) => Err((Some((msg.header.id, q, edns)), err)),
     }
 }
 
-fn recursive_resolve(transport: &'static str, data: BytesBuf) -> Option<Message> {
-    match _recursive_resolve(transport, data) {
+#[allow(clippy::used_underscore_items)]
+fn recursive_resolve(transport: &'static str, data: BytesBuf, state: &State) -> Option<Message> {
+    match _recursive_resolve(transport, data, state) {
         Ok(msg) => Some(msg),
-        Err((id, err)) => {
-            eprintln!("Error when making request, propogating to client: {err}");
+        Err((query, err)) => {
+            error!("Error when making request, propogating to client: {err}");
 
-            if let Some(id) = id {
+            if let Some((id, question, edns)) = query {
                 Some(Message {
                     header: Header {
                         id,
@@ -191,13 +616,14 @@ fn recursive_resolve(transport: &'static str, data: BytesBuf) -> Option<Message>
                         authority_records: 0,
                         additional_records: 0,
                     },
-                    questions: vec![],
+                    questions: vec![question],
                     answers: vec![],
                     authorities: vec![],
                     additional: vec![],
+                    edns,
                 })
             } else {
-                eprintln!(
+                error!(
                     "Couldn't even parse message id from data, so can't send client the error :/"
                 );
                 None
@@ -206,84 +632,704 @@ fn recursive_resolve(transport: &'static str, data: BytesBuf) -> Option<Message>
     }
 }
 
-fn udp_server() -> Result<()> {
-    let socket = UdpSocket::bind("127.0.0.1:8080")?;
+/// Resolves a UDP query and sends the response, truncated if it's too big for the client
+fn udp_answer(socket: &UdpSocket, data: Vec<u8>, addr: SocketAddr, state: &State) -> Result<()> {
+    let mut buf = BytesMut::new();
 
-    loop {
-        // https://www.rfc-editor.org/std/std75.txt
-        // "The maximum allowable size of a DNS message over UDP not using the extensions described in this document is 512 bytes."
-        let mut data = [0; UDP_MAX_SIZE];
+    if let Some(mut msg) = recursive_resolve("UDP", BytesBuf::new(data), state) {
+        msg.serialize(&mut buf)?;
 
-        let (_, addr) = socket.recv_from(&mut data)?;
+        // Clients without EDNS can only handle 512 bytes
+        let limit = msg.edns.as_ref().map_or(UDP_MAX_SIZE, |edns| {
+            UDP_MAX_SIZE.max(edns.udp_payload_size.into())
+        });
 
-        if data.is_empty() {
-            continue;
-        }
+        if buf.len() > limit {
+            debug!("UDP request was truncated...");
 
-        let mut buf = BytesMut::new();
+            // Only send the header so the client retries over TCP
+            msg.header.is_truncated = true;
+            msg.answers.clear();
+            msg.authorities.clear();
+            msg.additional.clear();
 
-        if let Some(msg) = recursive_resolve("UDP", BytesBuf::new(data.into())) {
+            buf.clear();
             msg.serialize(&mut buf)?;
+        }
 
-            let slice = if buf.len() > UDP_MAX_SIZE {
-                // Set is_truncated to true
-                buf[2] |= 0x02;
-                println!("UDP request was truncated...");
-                &buf[0..UDP_MAX_SIZE]
-            } else {
-                &buf
+        socket.send_to(&buf, addr)?;
+    }
+
+    Ok(())
+}
+
+fn send_message(socket: &UdpSocket, msg: &Message, addr: SocketAddr) -> Result<()> {
+    let mut buf = BytesMut::new();
+    msg.serialize(&mut buf)?;
+    socket.send_to(&buf, addr)?;
+
+    Ok(())
+}
+
+/// SERVFAIL for a query there's no room to resolve, nothing if it isn't a query at all
+fn overloaded_response(data: Vec<u8>, state: &State) -> Option<Message> {
+    let request = Message::parse(&mut BytesBuf::new(data)).ok()?;
+    if request.header.is_response {
+        return None;
+    }
+
+    Some(Message {
+        header: Header {
+            id: request.header.id,
+            is_response: true,
+            opcode: request.header.opcode,
+            is_authoritative: false,
+            is_truncated: false,
+            should_recurse: request.header.should_recurse,
+            recursion_available: true,
+            _z: 0,
+            rescode: ResCode::ServerFailure,
+            questions: 0,
+            answer_records: 0,
+            authority_records: 0,
+            additional_records: 0,
+        },
+        questions: request.questions,
+        answers: vec![],
+        authorities: vec![],
+        additional: vec![],
+        edns: response_edns(request.edns.as_ref(), state.config.limits.udp_payload_size),
+    })
+}
+
+/// Receives queries and queues them up for a pool of workers, so a slow lookup only holds up
+/// its own worker. Queries that don't fit in the queue get SERVFAIL or are dropped, depending
+/// on the config
+fn udp_server(socket: &UdpSocket, state: &State) -> Result<()> {
+    let limits = &state.config.limits;
+    let (queue, queued) = mpsc::sync_channel::<(Vec<u8>, SocketAddr)>(limits.udp_queue);
+    let queued = Mutex::new(queued);
+
+    thread::scope(|scope| {
+        for _ in 0..limits.udp_workers {
+            scope.spawn(|| loop {
+                // Only held while waiting, the lock is let go before answering
+                let next = queued.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                let Ok((data, addr)) = next else {
+                    return;
+                };
+
+                if let Err(err) = udp_answer(socket, data, addr, state) {
+                    debug!("Couldn't answer UDP query from {addr}: {err}");
+                }
+            });
+        }
+
+        // Only logged when it starts and stops, not for every query
+        let mut overloaded = false;
+
+        let err = loop {
+            let mut data = vec![0; limits.udp_payload_size.into()];
+
+            let (len, addr) = match socket.recv_from(&mut data) {
+                Ok(received) => received,
+                Err(err) => break err,
             };
 
-            socket.send_to(slice, addr)?;
+            if len == 0 {
+                continue;
+            }
+            data.truncate(len);
+
+            let Err(TrySendError::Full((data, addr))) = queue.try_send((data, addr)) else {
+                if overloaded {
+                    info!("UDP queue has room again");
+                    overloaded = false;
+                }
+                continue;
+            };
+
+            if !overloaded {
+                warn!(
+                    "UDP queue is full, {} queries until it has room",
+                    match limits.udp_overload {
+                        Overload::Servfail => "sending SERVFAIL for",
+                        Overload::Drop => "dropping",
+                    }
+                );
+                overloaded = true;
+            }
+
+            if limits.udp_overload == Overload::Servfail {
+                if let Some(msg) = overloaded_response(data, state) {
+                    if let Err(err) = send_message(socket, &msg, addr) {
+                        debug!("Couldn't send SERVFAIL to {addr}: {err}");
+                    }
+                }
+            }
+        };
+
+        // Lets the workers finish up and stop
+        drop(queue);
+        Err(err.into())
+    })
+}
+
+/// Whether a read failed because its timeout ran out, which is `WouldBlock` on some platforms
+fn is_timeout(err: &std::io::Error) -> bool {
+    matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Queries being answered at once on one connection
+struct InFlight {
+    count: Mutex<usize>,
+    finished: Condvar,
+}
+
+impl InFlight {
+    fn new() -> InFlight {
+        InFlight {
+            count: Mutex::new(0),
+            finished: Condvar::new(),
         }
     }
-}
 
-fn stream_handler(mut stream: TcpStream) -> Result<()> {
-    stream.set_read_timeout(Some(Duration::from_secs(60)))?;
-    // These 2 pesky bytes only mentioned once in RFC 1035
-    let mut size = [0; 2];
-    stream.read_exact(&mut size)?;
+    fn lock(&self) -> MutexGuard<'_, usize> {
+        self.count.lock().unwrap_or_else(PoisonError::into_inner)
+    }
 
-    let size = u16::from_be_bytes(size) as usize;
+    /// Waits until fewer than `max` queries are being answered, then counts one more
+    fn start(&self, max: usize) {
+        let mut count = self.lock();
+        while *count >= max {
+            count = self
+                .finished
+                .wait(count)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *count += 1;
+    }
 
-    let mut data = vec![0; size];
-    stream.read_exact(&mut data)?;
+    fn finish(&self) {
+        *self.lock() -= 1;
+        self.finished.notify_one();
+    }
 
-    let mut buf = BytesMut::new();
+    fn is_empty(&self) -> bool {
+        *self.lock() == 0
+    }
+}
 
-    if let Some(msg) = recursive_resolve("TCP", BytesBuf::new(data)) {
+/// Resolves a query that came in over a stream and writes the response back once it's ready
+fn stream_answer<D: Duplex>(
+    duplex: &D,
+    transport: &'static str,
+    data: Vec<u8>,
+    state: &State,
+) -> Result<()> {
+    if let Some(mut msg) = recursive_resolve(transport, BytesBuf::new(data), state) {
+        let mut buf = BytesMut::new();
         msg.serialize(&mut buf)?;
 
-        #[allow(clippy::cast_possible_truncation)]
-        stream.write_all(&u16::to_be_bytes(buf.len() as u16))?;
+        // The length prefix can't go past 65535 bytes, and a wrong one would throw off
+        // every later message on the connection
+        if u16::try_from(buf.len()).is_err() {
+            debug!("{transport} response was truncated...");
+
+            msg.header.is_truncated = true;
+            msg.answers.clear();
+            msg.authorities.clear();
+            msg.additional.clear();
+
+            buf.clear();
+            msg.serialize(&mut buf)?;
+        }
+
+        // Sent in one go so the length doesn't end up in a packet or TLS record of its own
+        let mut framed = Vec::with_capacity(buf.len() + 2);
+        framed.extend_from_slice(&u16::try_from(buf.len())?.to_be_bytes());
+        framed.extend_from_slice(&buf);
 
-        stream.write_all(&buf)?;
+        duplex.write_all(&framed)?;
     }
 
     Ok(())
 }
 
-fn tcp_server() -> Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:8080")?;
+/// Answers length prefixed queries (RFC 7766 section 8) until the client closes the connection
+/// or stays idle for too long. Queries are answered concurrently and each response goes out as
+/// soon as it's ready, so they can come back in a different order (RFC 7766 section 6.2.1.1)
+fn stream_handler<D: Duplex>(duplex: &D, transport: &'static str, state: &State) -> Result<()> {
+    let timeouts = &state.config.timeouts;
+    let socket = duplex.socket();
+    let in_flight = InFlight::new();
+    let mut reader = BufReader::new(DuplexReader(duplex));
+    let mut first = true;
+
+    thread::scope(|scope| loop {
+        socket.set_read_timeout(Some(if first {
+            timeouts.tcp_read()
+        } else {
+            timeouts.idle()
+        }))?;
+
+        // Waits for the next query without taking any of it, so timing out can't lose part of one
+        match reader.fill_buf() {
+            Ok([]) => return Ok(()),
+            Ok(_) => {}
+            // Connections normally end by the client closing them or going quiet between queries,
+            // it isn't quiet while there are still answers to send
+            Err(err) if !first && is_timeout(&err) => {
+                if in_flight.is_empty() {
+                    return Ok(());
+                }
+                continue;
+            }
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+
+        first = false;
+        socket.set_read_timeout(Some(timeouts.tcp_read()))?;
+
+        // These 2 pesky bytes only mentioned once in RFC 1035
+        let mut size = [0; 2];
+        reader.read_exact(&mut size)?;
+
+        let mut data = vec![0; u16::from_be_bytes(size).into()];
+        reader.read_exact(&mut data)?;
+
+        in_flight.start(state.config.limits.pipelined_queries);
+        let in_flight = &in_flight;
+        scope.spawn(move || {
+            if let Err(err) = stream_answer(duplex, transport, data, state) {
+                debug!("Couldn't answer {transport} query: {err}");
+            }
+            in_flight.finish();
+        });
+    })
+}
+
+/// Connection that was accepted, failures only get logged as they're about that one connection
+/// or don't last (like running out of file descriptors), and the listener has to keep going
+fn accepted(stream: std::io::Result<TcpStream>, transport: &str) -> Option<TcpStream> {
+    match stream {
+        Ok(stream) => Some(stream),
+        Err(err) => {
+            warn!("Couldn't accept {transport} connection: {err}");
+            // Gives whatever ran out a moment to free up rather than spinning on it
+            thread::sleep(ACCEPT_BACKOFF);
+            None
+        }
+    }
+}
 
+fn tcp_server(listener: &TcpListener, state: &Arc<State>) {
     for stream in listener.incoming() {
-        let stream = stream?;
+        let Some(stream) = accepted(stream, "TCP") else {
+            continue;
+        };
+        let state = state.clone();
 
         thread::spawn(move || {
-            stream_handler(stream).expect("TODO: deal with this");
+            if let Err(err) = stream_handler(&TcpDuplex::new(stream), "TCP", &state) {
+                debug!("TCP connection ended with an error: {err}");
+            }
         });
     }
+}
+
+/// DNS over TLS (RFC 7858), the same framing as TCP once the handshake is done
+fn tls_handler(socket: TcpStream, config: Arc<ServerConfig>, state: &State) -> Result<()> {
+    let duplex = TlsDuplex::new(socket, ServerConnection::new(config)?);
+
+    stream_handler(&duplex, "TLS", state)?;
+    duplex.close()?;
 
     Ok(())
 }
 
+/// DNS over HTTPS (RFC 8484) over HTTP/1.1, keeping the connection open between requests
+/// unless the client asks otherwise
+fn https_handler(socket: TcpStream, config: Arc<ServerConfig>, state: &State) -> Result<()> {
+    let timeouts = socket.try_clone()?;
+    let mut reader = BufReader::new(StreamOwned::new(ServerConnection::new(config)?, socket));
+    let mut first = true;
+
+    loop {
+        timeouts.set_read_timeout(Some(if first {
+            state.config.timeouts.tcp_read()
+        } else {
+            state.config.timeouts.idle()
+        }))?;
+
+        // Waits for the start of the next request, which is where idle connections get closed
+        match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof || (!first && is_timeout(&err)) => {
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        first = false;
+        timeouts.set_read_timeout(Some(state.config.timeouts.tcp_read()))?;
+
+        let request = match doh::read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(DohError::Rejected(status)) => {
+                debug!("Rejected HTTPS request with {}", status.code());
+                // The rest of the request might still be coming in, so there's no carrying on
+                doh::write_response(reader.get_mut(), status, None, false)?;
+                break;
+            }
+            Err(DohError::Io(err)) => return Err(err.into()),
+        };
+
+        if let Some(msg) = recursive_resolve("HTTPS", BytesBuf::new(request.message), state) {
+            let mut buf = BytesMut::new();
+            msg.serialize(&mut buf)?;
+
+            doh::write_response(
+                reader.get_mut(),
+                Status::Ok,
+                Some((&buf, doh::max_age(&msg))),
+                request.keep_alive,
+            )?;
+        } else {
+            doh::write_response(reader.get_mut(), Status::BadRequest, None, false)?;
+            break;
+        }
+
+        if !request.keep_alive {
+            break;
+        }
+    }
+
+    let stream = reader.get_mut();
+    stream.conn.send_close_notify();
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// Accepts connections and hands each to `handler` on its own thread, which does the handshake
+fn tls_server(
+    listener: &TcpListener,
+    transport: &'static str,
+    handler: fn(TcpStream, Arc<ServerConfig>, &State) -> Result<()>,
+    config: &Arc<ServerConfig>,
+    state: &Arc<State>,
+) {
+    for stream in listener.incoming() {
+        let Some(stream) = accepted(stream, transport) else {
+            continue;
+        };
+        let config = config.clone();
+        let state = state.clone();
+
+        thread::spawn(move || {
+            if let Err(err) = handler(stream, config, &state) {
+                debug!("{transport} connection ended with an error: {err}");
+            }
+        });
+    }
+}
+
+/// Loads every zone file
+fn load_zones(paths: &[PathBuf]) -> Result<Catalog> {
+    let mut catalog = Catalog::new();
+
+    for path in paths {
+        let records = read_zone_file(path, None)?;
+
+        // Zone files start with the SOA of the zone
+        let origin = match records.first() {
+            Some(record) if record.rtype == RecordType::SOA => record.name.clone(),
+            _ => {
+                return Err(format_err!(
+                    "{} doesn't start with an SOA record",
+                    path.display()
+                ))
+            }
+        };
+
+        let zone = Zone::new(&origin, records)?;
+        info!("Serving zone {} from {}", zone.origin(), path.display());
+        catalog.insert(zone);
+    }
+
+    Ok(catalog)
+}
+
+/// Root hints from the configured file, or the built in ones for the internet
+fn root_hints(path: Option<&Path>) -> Result<RootHints> {
+    match path {
+        Some(path) => {
+            let hints = RootHints::read(path)?;
+            info!("Using root hints from {}", path.display());
+            Ok(hints)
+        }
+        None => Ok(RootHints::builtin()),
+    }
+}
+
+/// Asks the root hint servers for the current root NS set (RFC 8109) and caches it along with
+/// the addresses of those servers. Returns how long until priming should happen again
+fn prime(state: &State) -> Result<Duration> {
+    let question = Question {
+        name: Domain(vec![]),
+        qtype: RecordType::NS,
+        qclass: RecordClass::IN,
+    };
+
+    let mut servers = state.hints.ips();
+    state.rtt.sort(&mut servers);
+    state.families.order(&mut servers);
+
+    for server in servers {
+        let start = Instant::now();
+        let res = match state.pool.request(
+            question.clone(),
+            SocketAddr::new(server, 53),
+            utils::Transport::Unspecified,
+        ) {
+            Ok(res) => res,
+            Err(err) => {
+                warn!("{server} didn't respond to priming: {err}");
+                state.rtt.record_failure(server);
+                continue;
+            }
+        };
+
+        let nameservers: Vec<ResourceRecord> = res
+            .answers
+            .into_iter()
+            .filter(|r| r.rtype == RecordType::NS && r.name.0.is_empty())
+            .collect();
+
+        if res.header.rescode != ResCode::NoError || nameservers.is_empty() {
+            warn!("{server} responded to priming without the root NS set");
+            state.rtt.record_failure(server);
+            continue;
+        }
+
+        state.rtt.record(server, start.elapsed());
+
+        // Addresses are only believed for the root's own name servers
+        let addresses: Vec<ResourceRecord> = res
+            .additional
+            .into_iter()
+            .filter(|r| {
+                matches!(r.rtype, RecordType::A | RecordType::AAAA)
+                    && nameservers.iter().any(|ns| {
+                        matches!(&ns.data, RData::NS(target) if target.to_lowercase() == r.name.to_lowercase())
+                    })
+            })
+            .collect();
+
+        state.cache.insert(&nameservers);
+        state.cache.insert(&addresses);
+
+        let ttl = nameservers.iter().map(|r| r.ttl).min().unwrap_or(0);
+        return Ok(
+            Duration::from_secs(ttl.into()).clamp(MIN_PRIMING_INTERVAL, MAX_PRIMING_INTERVAL)
+        );
+    }
+
+    Err(format_err!(
+        "None of the root hint servers responded to priming"
+    ))
+}
+
+/// Primes at startup and again whenever the root NS set expires,
+/// lookups fall back to the hints until the first priming succeeds
+fn priming_loop(state: &State) {
+    loop {
+        let wait = match prime(state) {
+            Ok(wait) => {
+                info!(
+                    "Primed root name servers, priming again in {}s",
+                    wait.as_secs()
+                );
+                wait
+            }
+            Err(err) => {
+                error!("Priming failed: {err}");
+                MIN_PRIMING_INTERVAL
+            }
+        };
+
+        thread::sleep(wait);
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// TOML config file, anything it leaves out gets its default
+    #[clap(short, long)]
+    config: Option<PathBuf>,
+
+    /// Check the config, zones and root hints then exit without serving
+    #[clap(long)]
+    check: bool,
+
+    /// Zone files to serve on top of the ones in the config
+    zones: Vec<PathBuf>,
+}
+
+/// Binds every address, skipping the ones that fail (e.g. IPv6 on a host without it)
+fn bind_all<T>(addrs: &[SocketAddr], bind: impl Fn(SocketAddr) -> std::io::Result<T>) -> Vec<T> {
+    addrs
+        .iter()
+        .filter_map(|addr| match bind(*addr) {
+            Ok(socket) => {
+                info!("Listening on {addr}");
+                Some(socket)
+            }
+            Err(err) => {
+                warn!("Couldn't listen on {addr}: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
 fn main() -> Result<()> {
-    let tcp = thread::spawn(|| tcp_server().unwrap());
+    let cli = Cli::parse();
+
+    let mut config = match &cli.config {
+        Some(path) => Config::read(path)?,
+        None => Config::default(),
+    };
+    config.zones.extend(cli.zones);
+    config.validate()?;
+
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::from(config.log.level))
+        .init();
 
-    // For now don't handle UDP
-    thread::spawn(udp_server).join().unwrap()?;
-    tcp.join().unwrap();
+    let hints = root_hints(config.upstream.root_hints.as_deref())?;
+    let catalog = load_zones(&config.zones)?;
+
+    // Only loaded when they're going to be used, each transport has its own ALPN protocol
+    // https://www.iana.org/assignments/tls-extensiontype-values/tls-extensiontype-values.xhtml#alpn-protocol-ids
+    let server_config =
+        |addrs: &[SocketAddr], alpn: &[&[u8]]| match (&config.tls.certificate, &config.tls.key) {
+            (Some(certificate), Some(key)) if !addrs.is_empty() => {
+                tls::server_config(certificate, key, alpn).map(Some)
+            }
+            _ => Ok(None),
+        };
+    let tls_config = server_config(&config.listen.tls, &[b"dot"])?;
+    let https_config = server_config(&config.listen.https, &[b"http/1.1"])?;
+    let quic_config = server_config(&config.listen.quic, &[DOQ_ALPN])?;
+
+    if cli.check {
+        info!("Config is valid");
+        return Ok(());
+    }
+
+    let families = config
+        .upstream
+        .families
+        .unwrap_or_else(|| FamilyPolicy::detect(&hints.ips()));
+
+    let state = Arc::new(State {
+        cache: Cache::new(config.cache.max_entries),
+        forwarders: Forwarders::new(&config.forward),
+        pool: Pool::new(RequestOptions {
+            randomize_case: config.upstream.randomize_case,
+            ..RequestOptions::default()
+        }),
+        config,
+        catalog,
+        rtt: RttTable::new(),
+        families,
+        hints,
+    });
+
+    info!("Running in {:?} mode", state.config.mode);
+
+    if state.config.mode == Mode::Recursive {
+        info!("Contacting upstream servers with {:?}", state.families);
+
+        let state = state.clone();
+        thread::spawn(move || priming_loop(&state));
+    }
+
+    let mut handles = vec![];
+
+    for tcp in bind_all(&state.config.listen.tcp, TcpListener::bind) {
+        let state = state.clone();
+        handles.push(thread::spawn(move || {
+            tcp_server(&tcp, &state);
+            Ok(())
+        }));
+    }
+
+    let encrypted = [
+        (
+            tls_config,
+            &state.config.listen.tls,
+            "TLS",
+            tls_handler as fn(_, _, &_) -> _,
+        ),
+        (
+            https_config,
+            &state.config.listen.https,
+            "HTTPS",
+            https_handler,
+        ),
+    ];
+    for (config, addrs, transport, handler) in encrypted {
+        let Some(config) = config else {
+            continue;
+        };
+
+        for listener in bind_all(addrs, TcpListener::bind) {
+            let config = config.clone();
+            let state = state.clone();
+            handles.push(thread::spawn(move || {
+                tls_server(&listener, transport, handler, &config, &state);
+                Ok(())
+            }));
+        }
+    }
+
+    if let Some(config) = quic_config {
+        let limits = doq::Limits {
+            idle: state.config.timeouts.idle(),
+            streams: u32::try_from(state.config.limits.pipelined_queries).unwrap_or(u32::MAX),
+        };
+
+        for socket in bind_all(&state.config.listen.quic, UdpSocket::bind) {
+            let config = config.clone();
+            let state = state.clone();
+            handles.push(thread::spawn(move || {
+                doq::serve(socket, config, limits, move |data| {
+                    recursive_resolve("QUIC", BytesBuf::new(data), &state)
+                })
+                .map_err(Into::into)
+            }));
+        }
+    }
+
+    for udp in bind_all(&state.config.listen.udp, UdpSocket::bind) {
+        let state = state.clone();
+        handles.push(thread::spawn(move || udp_server(&udp, &state)));
+    }
+
+    if handles.is_empty() {
+        return Err(format_err!("Couldn't listen on any address"));
+    }
+
+    for handle in handles {
+        handle.join().unwrap()?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,57 @@
+use std::{collections::HashMap, net::IpAddr, sync::Mutex, time::Duration};
+
+/// Estimate for servers that haven't been asked anything yet, low enough
+/// that they get tried before servers that are known to be slow
+pub const UNKNOWN_RTT: Duration = Duration::from_millis(200);
+
+/// Cap on the penalty for failures, so a server that was down is still
+/// ordered by how long it has been failing rather than excluded forever
+pub const MAX_RTT: Duration = Duration::from_secs(10);
+
+/// Smoothed round trip times of upstream servers, used to ask the fastest one first
+#[derive(Debug, Default)]
+pub struct RttTable {
+    servers: Mutex<HashMap<IpAddr, Duration>>,
+}
+
+impl RttTable {
+    pub fn new() -> RttTable {
+        RttTable::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<IpAddr, Duration>> {
+        // A panic while holding the lock can't leave the map in a broken state
+        self.servers
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    pub fn estimate(&self, server: IpAddr) -> Duration {
+        self.lock().get(&server).copied().unwrap_or(UNKNOWN_RTT)
+    }
+
+    /// Blends a new measurement into the estimate, weighing the history more than a single sample
+    pub fn record(&self, server: IpAddr, rtt: Duration) {
+        let mut servers = self.lock();
+
+        let estimate = match servers.get(&server) {
+            Some(old) => *old * 7 / 10 + rtt * 3 / 10,
+            None => rtt,
+        };
+
+        servers.insert(server, estimate.min(MAX_RTT));
+    }
+
+    /// Timeouts, errors and lame answers double the estimate
+    pub fn record_failure(&self, server: IpAddr) {
+        let mut servers = self.lock();
+
+        let estimate = servers.get(&server).copied().unwrap_or(UNKNOWN_RTT);
+        servers.insert(server, (estimate * 2).min(MAX_RTT));
+    }
+
+    /// Orders servers from the lowest estimate to the highest
+    pub fn sort(&self, servers: &mut [IpAddr]) {
+        servers.sort_by_cached_key(|server| self.estimate(*server));
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
+
+use crate::rtt::*;
+
+fn ip(last: u8) -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(192, 0, 2, last))
+}
+
+#[test]
+fn unknown_servers() {
+    let table = RttTable::new();
+
+    assert_eq!(table.estimate(ip(1)), UNKNOWN_RTT);
+}
+
+#[test]
+fn smoothed_estimate() {
+    let table = RttTable::new();
+
+    table.record(ip(1), Duration::from_millis(100));
+    assert_eq!(table.estimate(ip(1)), Duration::from_millis(100));
+
+    table.record(ip(1), Duration::from_millis(200));
+    assert_eq!(table.estimate(ip(1)), Duration::from_millis(130));
+}
+
+#[test]
+fn failures_double_estimate() {
+    let table = RttTable::new();
+
+    table.record(ip(1), Duration::from_millis(50));
+    table.record_failure(ip(1));
+    assert_eq!(table.estimate(ip(1)), Duration::from_millis(100));
+
+    table.record_failure(ip(2));
+    assert_eq!(table.estimate(ip(2)), UNKNOWN_RTT * 2);
+
+    for _ in 0..16 {
+        table.record_failure(ip(2));
+    }
+    assert_eq!(table.estimate(ip(2)), MAX_RTT);
+}
+
+#[test]
+fn fastest_first() {
+    let table = RttTable::new();
+
+    table.record(ip(1), Duration::from_millis(300));
+    table.record(ip(2), Duration::from_millis(20));
+    table.record_failure(ip(3));
+
+    let mut servers = vec![ip(3), ip(1), ip(4), ip(2)];
+    table.sort(&mut servers);
+
+    // Unknown servers go between fast and slow ones
+    assert_eq!(servers, vec![ip(2), ip(4), ip(1), ip(3)]);
+}
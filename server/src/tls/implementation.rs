@@ -0,0 +1,77 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    ServerConfig,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("Couldn't read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{0} doesn't have any PEM certificates")]
+    NoCertificates(PathBuf),
+    #[error("{0} doesn't have a PEM private key")]
+    NoKey(PathBuf),
+    #[error("{0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, TlsError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|source| TlsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Certificate chain from a PEM file, leaf first
+pub fn read_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let certificates = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|source| TlsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    if certificates.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+
+    Ok(certificates)
+}
+
+/// First private key in a PEM file, PKCS#8, PKCS#1 and SEC1 keys all work
+pub fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>, TlsError> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|source| TlsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?
+        .ok_or_else(|| TlsError::NoKey(path.to_path_buf()))
+}
+
+/// Config for accepting TLS connections, offering `alpn` to clients that ask for a protocol
+pub fn server_config(
+    certificate: &Path,
+    key: &Path,
+    alpn: &[&[u8]],
+) -> Result<Arc<ServerConfig>, TlsError> {
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(read_certificates(certificate)?, read_key(key)?)?;
+
+    config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+
+    Ok(Arc::new(config))
+}
@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use crate::tls::*;
+
+fn file(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/tls/tests")
+        .join(name)
+}
+
+#[test]
+fn matching_pair() {
+    let config = server_config(&file("localhost.crt"), &file("localhost.key"), &[b"dot"]).unwrap();
+
+    assert_eq!(config.alpn_protocols, vec![b"dot".to_vec()]);
+}
+
+#[test]
+fn bad_files() {
+    assert!(matches!(
+        server_config(&file("missing.crt"), &file("localhost.key"), &[]),
+        Err(TlsError::Io { .. })
+    ));
+    // Swapped around
+    assert!(matches!(
+        server_config(&file("localhost.key"), &file("localhost.crt"), &[]),
+        Err(TlsError::NoCertificates(_))
+    ));
+    assert!(matches!(
+        server_config(&file("localhost.crt"), &file("localhost.crt"), &[]),
+        Err(TlsError::NoKey(_))
+    ));
+    assert!(matches!(
+        server_config(&file("localhost.crt"), &file("other.key"), &[]),
+        Err(TlsError::Rustls(_))
+    ));
+}
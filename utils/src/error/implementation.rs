@@ -0,0 +1,91 @@
+use std::io::{self, ErrorKind};
+
+use thiserror::Error;
+use types::{parser::ParserError, serializer::SerializerError};
+
+/// Why a request didn't get a usable response
+#[derive(Error, Debug)]
+pub enum RequestError {
+    /// Nothing came back in time, the server could be down or the packets lost
+    #[error("Timed out waiting for a response")]
+    Timeout,
+    /// The response didn't fit, the query has to go over TCP instead
+    #[error("Data was truncated, try again over TCP")]
+    Truncated,
+    #[error("Connection refused")]
+    ConnectionRefused,
+    #[error("Connection closed before a response came")]
+    Closed,
+    #[error("TLS handshake failed: {0}")]
+    TlsHandshake(rustls::Error),
+    /// The QUIC connection failed for some other reason, like its TLS handshake or a protocol error
+    #[error("QUIC connection failed: {0}")]
+    Quic(quinn::ConnectionError),
+    #[error("Malformed response: {0}")]
+    Malformed(#[from] ParserError),
+    /// The response is for some other query, which can be an attempt at spoofing
+    #[error("Response has ID {received} instead of {expected}")]
+    IdMismatch { expected: u16, received: u16 },
+    #[error("Response is for a different question")]
+    QuestionMismatch,
+    /// The DNS over HTTPS server didn't answer with a DNS message
+    #[error("{0}")]
+    Http(String),
+    #[error("Couldn't build the query: {0}")]
+    Query(#[from] SerializerError),
+    #[error("{0}")]
+    Io(io::Error),
+}
+
+impl RequestError {
+    /// Whether asking the same server again could work, as opposed to it having answered badly
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            RequestError::Timeout
+                | RequestError::ConnectionRefused
+                | RequestError::Closed
+                | RequestError::Io(_)
+        )
+    }
+}
+
+impl From<io::Error> for RequestError {
+    fn from(err: io::Error) -> RequestError {
+        // TLS failures come wrapped in IO errors from the streams doing the handshake
+        if let Some(err) = err
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<rustls::Error>())
+        {
+            return RequestError::TlsHandshake(err.clone());
+        }
+
+        match err.kind() {
+            // Sockets with a read timeout give `WouldBlock` on some platforms
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => RequestError::Timeout,
+            ErrorKind::ConnectionRefused => RequestError::ConnectionRefused,
+            ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted => RequestError::Closed,
+            _ => RequestError::Io(err),
+        }
+    }
+}
+
+impl From<rustls::Error> for RequestError {
+    fn from(err: rustls::Error) -> RequestError {
+        RequestError::TlsHandshake(err)
+    }
+}
+
+impl From<quinn::ConnectionError> for RequestError {
+    fn from(err: quinn::ConnectionError) -> RequestError {
+        match err {
+            quinn::ConnectionError::TimedOut => RequestError::Timeout,
+            quinn::ConnectionError::Reset | quinn::ConnectionError::ApplicationClosed(_) => {
+                RequestError::Closed
+            }
+            err => RequestError::Quic(err),
+        }
+    }
+}
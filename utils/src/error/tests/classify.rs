@@ -0,0 +1,63 @@
+use std::io::{Error, ErrorKind};
+
+use crate::RequestError;
+
+#[test]
+fn io_errors() {
+    let classify = |kind: ErrorKind| RequestError::from(Error::from(kind));
+
+    assert!(matches!(
+        classify(ErrorKind::TimedOut),
+        RequestError::Timeout
+    ));
+    assert!(matches!(
+        classify(ErrorKind::WouldBlock),
+        RequestError::Timeout
+    ));
+    assert!(matches!(
+        classify(ErrorKind::ConnectionRefused),
+        RequestError::ConnectionRefused
+    ));
+    assert!(matches!(
+        classify(ErrorKind::UnexpectedEof),
+        RequestError::Closed
+    ));
+    assert!(matches!(
+        classify(ErrorKind::PermissionDenied),
+        RequestError::Io(_)
+    ));
+}
+
+#[test]
+fn tls_errors() {
+    // The way TLS streams report a failed handshake
+    let err = Error::new(
+        ErrorKind::InvalidData,
+        rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer),
+    );
+
+    let err = RequestError::from(err);
+    assert!(matches!(
+        err,
+        RequestError::TlsHandshake(rustls::Error::InvalidCertificate(_))
+    ));
+    assert!(!err.is_transient());
+}
+
+#[test]
+fn quic_errors() {
+    let classify = |err: quinn::ConnectionError| RequestError::from(err);
+
+    assert!(matches!(
+        classify(quinn::ConnectionError::TimedOut),
+        RequestError::Timeout
+    ));
+    assert!(matches!(
+        classify(quinn::ConnectionError::Reset),
+        RequestError::Closed
+    ));
+
+    let err = classify(quinn::ConnectionError::VersionMismatch);
+    assert!(matches!(err, RequestError::Quic(_)));
+    assert!(!err.is_transient());
+}
@@ -0,0 +1,372 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, SocketAddr},
+};
+
+use anyhow::{format_err, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bytes::Bytes;
+use rustls::pki_types::ServerName;
+use types::Message;
+
+use crate::{connect, tls_config, Deadline, Query, RequestError, RequestOptions, HTTPS_PORT};
+
+/// Media type of DNS messages sent over HTTPS (RFC 8484 section 6)
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// Largest response body accepted, a DNS message can't be bigger than this
+pub(crate) const MAX_BODY_SIZE: usize = u16::MAX as usize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum HttpsMethod {
+    /// Message in the `dns` query parameter, base64url encoded. Easier for HTTP caches
+    Get,
+    /// Message as the request body
+    #[default]
+    Post,
+}
+
+/// Where and how DNS over HTTPS requests get sent
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HttpsOptions {
+    /// Name of the server, used for SNI, certificate validation and the `Host` header.
+    /// The IP address is used when there isn't one
+    pub host: Option<String>,
+    pub path: String,
+    pub method: HttpsMethod,
+}
+
+impl Default for HttpsOptions {
+    fn default() -> HttpsOptions {
+        HttpsOptions {
+            host: None,
+            path: "/dns-query".into(),
+            method: HttpsMethod::Post,
+        }
+    }
+}
+
+impl HttpsOptions {
+    /// Options from a URI template like `https://dns.example/dns-query{?dns}` (RFC 8484 section 3).
+    /// Templates with the `{?dns}` variable use GET, ones without it POST. The scheme and host
+    /// can be left out, leaving just the path. The port comes from the address being connected to
+    pub fn from_template(template: &str) -> Result<HttpsOptions> {
+        let (host, path) = if let Some(rest) = template.strip_prefix("https://") {
+            match rest.find('/') {
+                Some(index) => (Some(&rest[..index]), &rest[index..]),
+                None => (Some(rest), "/"),
+            }
+        } else if template.starts_with('/') {
+            (None, template)
+        } else {
+            return Err(format_err!(
+                "DoH template {template} has to be an https:// URL or a path"
+            ));
+        };
+
+        if let Some(host) = host {
+            if host.is_empty() {
+                return Err(format_err!("DoH template {template} is missing a host"));
+            }
+
+            if host.contains([':', '@']) {
+                return Err(format_err!(
+                    "DoH template {template} can only have a host name, the port comes from the server address"
+                ));
+            }
+        }
+
+        let (path, method) = match path.strip_suffix("{?dns}") {
+            Some(path) => (path, HttpsMethod::Get),
+            None => (path, HttpsMethod::Post),
+        };
+
+        if path.contains(['{', '}']) {
+            return Err(format_err!(
+                "DoH template {template} can only have the {{?dns}} variable, at the end"
+            ));
+        }
+
+        if method == HttpsMethod::Get && path.contains('?') {
+            return Err(format_err!(
+                "DoH template {template} can't have a query string along with {{?dns}}"
+            ));
+        }
+
+        Ok(HttpsOptions {
+            host: host.map(str::to_lowercase),
+            path: path.into(),
+            method,
+        })
+    }
+
+    fn authority(&self, source: SocketAddr) -> String {
+        let host = match (&self.host, source.ip()) {
+            (Some(host), _) => host.clone(),
+            (None, IpAddr::V4(ip)) => ip.to_string(),
+            (None, IpAddr::V6(ip)) => format!("[{ip}]"),
+        };
+
+        if source.port() == HTTPS_PORT {
+            host
+        } else {
+            format!("{host}:{}", source.port())
+        }
+    }
+}
+
+/// HTTP/1.1 request carrying `data`
+pub(crate) fn http_request(options: &HttpsOptions, source: SocketAddr, data: &[u8]) -> Vec<u8> {
+    let host = options.authority(source);
+
+    let mut request = match options.method {
+        HttpsMethod::Get => format!(
+            "GET {}?dns={} HTTP/1.1\r\n\
+             Host: {host}\r\n\
+             Accept: {DNS_MESSAGE}\r\n\
+             Connection: close\r\n\r\n",
+            options.path,
+            URL_SAFE_NO_PAD.encode(data)
+        )
+        .into_bytes(),
+        HttpsMethod::Post => format!(
+            "POST {} HTTP/1.1\r\n\
+             Host: {host}\r\n\
+             Accept: {DNS_MESSAGE}\r\n\
+             Content-Type: {DNS_MESSAGE}\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n",
+            options.path,
+            data.len()
+        )
+        .into_bytes(),
+    };
+
+    if options.method == HttpsMethod::Post {
+        request.extend_from_slice(data);
+    }
+
+    request
+}
+
+/// Longest status or header line accepted, so a misbehaving server can't make us buffer forever
+pub(crate) const MAX_LINE_SIZE: u64 = 8192;
+
+/// `line` without its line ending, which it has to have to be complete
+pub(crate) fn complete_line(line: &str) -> Result<String, RequestError> {
+    match line.strip_suffix("\r\n").or(line.strip_suffix('\n')) {
+        Some(line) => Ok(line.to_string()),
+        None => Err(RequestError::Http(
+            "HTTP response ended in the middle of a line".into(),
+        )),
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, RequestError> {
+    let mut line = String::new();
+    reader.take(MAX_LINE_SIZE).read_line(&mut line)?;
+
+    complete_line(&line)
+}
+
+/// Checks the status line says the request worked
+pub(crate) fn check_status(status: &str) -> Result<(), RequestError> {
+    let mut parts = status.splitn(3, ' ');
+    let (Some(version), Some(code)) = (parts.next(), parts.next()) else {
+        return Err(RequestError::Http(format!(
+            "Invalid HTTP status line {status}"
+        )));
+    };
+
+    if !version.starts_with("HTTP/1.") {
+        return Err(RequestError::Http(format!(
+            "Unsupported HTTP version {version}"
+        )));
+    }
+
+    if code != "200" {
+        return Err(RequestError::Http(format!(
+            "DoH server responded with HTTP status {code}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// How the body of a response is delimited
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum BodyLength {
+    Chunked,
+    Known(usize),
+    /// We asked for the connection to be closed, so the body ends with it
+    UntilClose,
+}
+
+/// The response headers that matter to us
+#[derive(Default)]
+pub(crate) struct ResponseHeaders {
+    content_type: Option<String>,
+    content_length: Option<usize>,
+    chunked: bool,
+}
+
+impl ResponseHeaders {
+    pub(crate) fn add(&mut self, line: &str) -> Result<(), RequestError> {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(RequestError::Http(format!("Invalid HTTP header {line}")));
+        };
+        let value = value.trim();
+
+        match name.to_ascii_lowercase().as_str() {
+            "content-type" => self.content_type = Some(value.to_ascii_lowercase()),
+            "content-length" => {
+                self.content_length = Some(value.parse::<usize>().map_err(|_| {
+                    RequestError::Http(format!("Invalid HTTP Content-Length {value}"))
+                })?);
+            }
+            "transfer-encoding" => self.chunked = value.eq_ignore_ascii_case("chunked"),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// How to read the body, as long as it is a DNS message
+    pub(crate) fn body_length(&self) -> Result<BodyLength, RequestError> {
+        // Parameters like `; charset=...` don't matter
+        let media_type = self
+            .content_type
+            .as_deref()
+            .and_then(|content_type| content_type.split(';').next())
+            .map(str::trim);
+        if media_type != Some(DNS_MESSAGE) {
+            return Err(RequestError::Http(format!(
+                "DoH server responded with {} instead of {DNS_MESSAGE}",
+                self.content_type.as_deref().unwrap_or("no content type")
+            )));
+        }
+
+        match (self.chunked, self.content_length) {
+            (true, _) => Ok(BodyLength::Chunked),
+            (false, Some(length)) if length > MAX_BODY_SIZE => {
+                Err(RequestError::Http("HTTP response body is too big".into()))
+            }
+            (false, Some(length)) => Ok(BodyLength::Known(length)),
+            (false, None) => Ok(BodyLength::UntilClose),
+        }
+    }
+}
+
+/// Size of the chunk starting with `line`, 0 for the last one
+pub(crate) fn chunk_size(line: &str, read: usize) -> Result<usize, RequestError> {
+    // Chunk extensions come after a `;`
+    let size = line.split(';').next().unwrap_or_default().trim();
+    let size = usize::from_str_radix(size, 16)
+        .map_err(|_| RequestError::Http(format!("Invalid HTTP chunk size {size}")))?;
+
+    if read + size > MAX_BODY_SIZE {
+        return Err(RequestError::Http("HTTP response body is too big".into()));
+    }
+
+    Ok(size)
+}
+
+fn read_chunked<R: BufRead>(reader: &mut R) -> Result<Vec<u8>, RequestError> {
+    let mut body = vec![];
+
+    loop {
+        let size = chunk_size(&read_line(reader)?, body.len())?;
+        if size == 0 {
+            break;
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+
+        if !read_line(reader)?.is_empty() {
+            return Err(RequestError::Http(
+                "HTTP chunk is longer than its size".into(),
+            ));
+        }
+    }
+
+    // Trailers
+    while !read_line(reader)?.is_empty() {}
+
+    Ok(body)
+}
+
+/// Body of an HTTP/1.1 response, as long as it is a successful one carrying a DNS message
+pub(crate) fn read_http_response<R: Read>(stream: R) -> Result<Bytes, RequestError> {
+    let mut reader = BufReader::new(stream);
+
+    check_status(&read_line(&mut reader)?)?;
+
+    let mut headers = ResponseHeaders::default();
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+
+        headers.add(&line)?;
+    }
+
+    let body = match headers.body_length()? {
+        BodyLength::Chunked => read_chunked(&mut reader)?,
+        BodyLength::Known(length) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            body
+        }
+        BodyLength::UntilClose => {
+            let mut body = vec![];
+            reader
+                .take(MAX_BODY_SIZE as u64 + 1)
+                .read_to_end(&mut body)?;
+            if body.len() > MAX_BODY_SIZE {
+                return Err(RequestError::Http("HTTP response body is too big".into()));
+            }
+            body
+        }
+    };
+
+    Ok(body.into())
+}
+
+/// Name the server's certificate has to be for
+pub(crate) fn server_name(
+    options: &HttpsOptions,
+    source: SocketAddr,
+) -> Result<ServerName<'static>, RequestError> {
+    match &options.host {
+        Some(host) => ServerName::try_from(host.clone())
+            .map_err(|err| RequestError::Io(io::Error::new(io::ErrorKind::InvalidInput, err))),
+        None => Ok(ServerName::IpAddress(source.ip().into())),
+    }
+}
+
+pub(crate) fn make_https_req(
+    query: &Query,
+    source: SocketAddr,
+    options: &RequestOptions,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    let server_name = server_name(&options.https, source)?;
+    let config = tls_config(&options.tls, &[b"http/1.1"])?;
+    let mut conn = rustls::ClientConnection::new(config, server_name)?;
+
+    let mut sock = connect(source, deadline.attempt(0)?)?;
+
+    let mut tls_stream = rustls::Stream::new(&mut conn, &mut sock);
+
+    tls_stream.write_all(&http_request(&options.https, source, &query.data))?;
+    let body = read_http_response(&mut tls_stream)?;
+
+    tls_stream.conn.send_close_notify();
+    // The response is already in hand, failing to say goodbye doesn't matter
+    let _ = tls_stream.flush();
+
+    query.response(body)
+}
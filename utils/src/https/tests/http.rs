@@ -0,0 +1,106 @@
+use std::net::SocketAddr;
+
+use types::{Domain, Question, RecordClass, RecordType};
+
+use crate::{https::*, query_for, RequestOptions, Transport};
+
+fn source(address: &str) -> SocketAddr {
+    address.parse().unwrap()
+}
+
+#[test]
+fn get_requests() {
+    let options = HttpsOptions::from_template("https://dns.example/dns-query{?dns}").unwrap();
+
+    // The example query from RFC 8484 section 4.1.1
+    let query = [
+        0x00, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x77, 0x77,
+        0x77, 0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00,
+        0x01, 0x00, 0x01,
+    ];
+
+    assert_eq!(
+        String::from_utf8(http_request(&options, source("192.0.2.1:443"), &query)).unwrap(),
+        "GET /dns-query?dns=AAABAAABAAAAAAAAA3d3dwdleGFtcGxlA2NvbQAAAQAB HTTP/1.1\r\n\
+         Host: dns.example\r\n\
+         Accept: application/dns-message\r\n\
+         Connection: close\r\n\r\n"
+    );
+}
+
+#[test]
+fn queries_are_cacheable() {
+    let question = Question {
+        name: Domain(vec!["www".into(), "example".into(), "com".into()]),
+        qtype: RecordType::A,
+        qclass: RecordClass::IN,
+    };
+    let query = |transport| {
+        query_for(question.clone(), transport, &RequestOptions::default())
+            .unwrap()
+            .data
+    };
+
+    // ID 0, so asking again makes the same GET request
+    assert_eq!(query(Transport::Https)[..2], [0, 0]);
+    assert_eq!(query(Transport::Https), query(Transport::Https));
+}
+
+#[test]
+fn post_requests() {
+    let options = HttpsOptions::default();
+
+    assert_eq!(
+        String::from_utf8(http_request(&options, source("[2001:db8::1]:8443"), b"abc")).unwrap(),
+        "POST /dns-query HTTP/1.1\r\n\
+         Host: [2001:db8::1]:8443\r\n\
+         Accept: application/dns-message\r\n\
+         Content-Type: application/dns-message\r\n\
+         Content-Length: 3\r\n\
+         Connection: close\r\n\r\n\
+         abc"
+    );
+}
+
+#[test]
+fn response_bodies() {
+    let sized = b"HTTP/1.1 200 OK\r\n\
+                  content-type: application/dns-message\r\n\
+                  Content-Length: 3\r\n\r\n\
+                  abcdef";
+    assert_eq!(&read_http_response(&sized[..]).unwrap()[..], b"abc");
+
+    let chunked = b"HTTP/1.1 200 OK\r\n\
+                    Content-Type: Application/DNS-Message; charset=binary\r\n\
+                    Transfer-Encoding: chunked\r\n\r\n\
+                    2;ext=1\r\nab\r\n\
+                    1\r\nc\r\n\
+                    0\r\n\
+                    Trailer: yes\r\n\r\n";
+    assert_eq!(&read_http_response(&chunked[..]).unwrap()[..], b"abc");
+
+    let until_close = b"HTTP/1.0 200 OK\n\
+                        Content-Type: application/dns-message\n\n\
+                        abc";
+    assert_eq!(&read_http_response(&until_close[..]).unwrap()[..], b"abc");
+}
+
+#[test]
+fn bad_responses() {
+    for response in [
+        &b"HTTP/1.1 415 Unsupported Media Type\r\nContent-Length: 0\r\n\r\n"[..],
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 3\r\n\r\nabc",
+        b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabc",
+        b"HTTP/2 200\r\nContent-Type: application/dns-message\r\n\r\nabc",
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\nContent-Length: 5\r\n\r\nabc",
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\nContent-Length: 70000\r\n\r\n",
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabcd\r\n0\r\n\r\n",
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/dns-message",
+    ] {
+        assert!(
+            read_http_response(response).is_err(),
+            "{}",
+            String::from_utf8_lossy(response)
+        );
+    }
+}
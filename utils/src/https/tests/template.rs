@@ -0,0 +1,59 @@
+use crate::https::*;
+
+#[test]
+fn full_templates() {
+    assert_eq!(
+        HttpsOptions::from_template("https://DNS.Example/dns-query{?dns}").unwrap(),
+        HttpsOptions {
+            host: Some("dns.example".into()),
+            path: "/dns-query".into(),
+            method: HttpsMethod::Get,
+        }
+    );
+    assert_eq!(
+        HttpsOptions::from_template("https://dns.example/resolve?ct").unwrap(),
+        HttpsOptions {
+            host: Some("dns.example".into()),
+            path: "/resolve?ct".into(),
+            method: HttpsMethod::Post,
+        }
+    );
+    assert_eq!(
+        HttpsOptions::from_template("https://dns.example")
+            .unwrap()
+            .path,
+        "/"
+    );
+}
+
+#[test]
+fn path_templates() {
+    assert_eq!(
+        HttpsOptions::from_template("/dns-query").unwrap(),
+        HttpsOptions::default()
+    );
+    assert_eq!(
+        HttpsOptions::from_template("/q{?dns}").unwrap(),
+        HttpsOptions {
+            host: None,
+            path: "/q".into(),
+            method: HttpsMethod::Get,
+        }
+    );
+}
+
+#[test]
+fn invalid_templates() {
+    for template in [
+        "http://dns.example/dns-query",
+        "dns-query",
+        "https:///dns-query",
+        "https://dns.example:8443/dns-query",
+        "https://user@dns.example/dns-query",
+        "https://dns.example/{dns}",
+        "https://dns.example/{?dns}/query",
+        "https://dns.example/dns-query?ct{?dns}",
+    ] {
+        assert!(HttpsOptions::from_template(template).is_err(), "{template}");
+    }
+}
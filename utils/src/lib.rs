@@ -2,30 +2,128 @@ use std::{
     fmt::Display,
     io::{Read, Write},
     net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, TcpStream, UdpSocket},
-    sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use anyhow::{format_err, Result};
 use bytes::{BufMut, Bytes, BytesMut};
-use rustls::{pki_types::ServerName, RootCertStore};
+use rand::Rng;
 use types::{
     parser::{BytesBuf, Parsable},
     serializer::Serializable,
-    Header, Message, OpCode, Question, ResCode,
+    Domain, Edns, Header, Message, OpCode, Question, ResCode,
 };
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+mod error;
+mod https;
+/// The same requests for async code running on tokio
+pub mod nonblocking;
+mod pool;
+mod quic;
+pub mod stream;
+mod tls;
+
+pub use error::RequestError;
+use https::make_https_req;
+pub use https::{HttpsMethod, HttpsOptions};
+pub use pool::Pool;
+pub use quic::DOQ_ALPN;
+use tls::tls_config;
+pub use tls::{parse_spki_pin, read_ca_certificates, spki_pin, SpkiPin, TlsOptions};
+
+/// UDP payload size advertised over EDNS, small enough to avoid IP fragmentation
+/// (see https://www.dnsflagday.net/2020/)
+pub const UDP_PAYLOAD_SIZE: u16 = 1232;
+
+/// How long one attempt at a server gets by default, from connecting to having the response
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a whole request gets by default, across retries and fallbacks
+pub const OVERALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times an unanswered UDP query gets sent again by default
+pub const UDP_RETRIES: u32 = 1;
+
+/// Standard ports for each transport, used when falling back from one to another
+pub const DNS_PORT: u16 = 53;
+pub const TLS_PORT: u16 = 853;
+pub const QUIC_PORT: u16 = 853;
+pub const HTTPS_PORT: u16 = 443;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Transport {
     Tcp,
     Udp,
     Tls,
     Https,
+    /// DNS over QUIC (RFC 9250)
+    Quic,
+    /// UDP, falling back to TCP
     Unspecified,
+    /// TLS, falling back to HTTPS and then to UDP/TCP
     TryEncrypted,
+    /// TLS, falling back to HTTPS
     UnspecifiedEncrypted,
 }
 
+/// Settings for requests beyond where they go and over what
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RequestOptions {
+    pub https: HttpsOptions,
+    /// How TLS servers get authenticated, for DNS over HTTPS as well as DNS over TLS
+    pub tls: TlsOptions,
+    /// How long one attempt at a server gets. Each UDP retry waits twice as long as the try before it
+    pub attempt_timeout: Duration,
+    /// How long the whole request gets, no attempt goes past it
+    pub overall_timeout: Duration,
+    pub udp_retries: u32,
+    /// Randomizes the case of the name asked about and checks the response kept it (DNS 0x20),
+    /// making spoofed responses harder to get right. Off by default since some servers don't keep it
+    pub randomize_case: bool,
+}
+
+impl Default for RequestOptions {
+    fn default() -> RequestOptions {
+        RequestOptions {
+            https: HttpsOptions::default(),
+            tls: TlsOptions::default(),
+            attempt_timeout: REQUEST_TIMEOUT,
+            overall_timeout: OVERALL_TIMEOUT,
+            udp_retries: UDP_RETRIES,
+            randomize_case: false,
+        }
+    }
+}
+
+/// Time limits of one request
+#[derive(Clone, Copy, Debug)]
+struct Deadline {
+    attempt: Duration,
+    end: Instant,
+}
+
+impl Deadline {
+    fn new(options: &RequestOptions) -> Deadline {
+        Deadline {
+            attempt: options.attempt_timeout,
+            end: Instant::now() + options.overall_timeout,
+        }
+    }
+
+    /// How long try number `retry` of an attempt gets, backing off exponentially from the
+    /// attempt timeout but never past the end of the request
+    fn attempt(&self, retry: u32) -> Result<Duration, RequestError> {
+        let remaining = self.end.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(RequestError::Timeout);
+        }
+
+        Ok(self
+            .attempt
+            .saturating_mul(1 << retry.min(16))
+            .min(remaining))
+    }
+}
+
 impl Display for Transport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
@@ -33,6 +131,7 @@ impl Display for Transport {
             Transport::Tcp => "Transport::TCP",
             Transport::Udp => "Transport::UDP",
             Transport::Https => "Transport::HTTPS",
+            Transport::Quic => "Transport::QUIC",
             Transport::TryEncrypted => "Transport::TryEncrypted",
             Transport::Unspecified => "Transport::Unspecified",
             Transport::UnspecifiedEncrypted => "Transport::UnspecifiedEncrypted",
@@ -40,107 +139,306 @@ impl Display for Transport {
     }
 }
 
-fn generic_stream_req<T: Read + Write>(stream: &mut T, data: Bytes) -> Result<Message> {
+/// A query in wire format, along with what its response has to match
+struct Query {
+    data: Bytes,
+    id: u16,
+    question: Question,
+    /// Name as it was sent, which can have had its case randomized
+    sent_name: Domain,
+    randomized_case: bool,
+}
+
+impl Query {
+    /// Query for `question` with a random ID
+    fn new(question: Question, randomize_case: bool) -> Result<Query, RequestError> {
+        Query::with_id(question, rand::random(), randomize_case)
+    }
+
+    fn with_id(question: Question, id: u16, randomize_case: bool) -> Result<Query, RequestError> {
+        let sent_name = if randomize_case {
+            randomized_case(&question.name)
+        } else {
+            question.name.clone()
+        };
+
+        let data = query_message(
+            Question {
+                name: sent_name.clone(),
+                ..question.clone()
+            },
+            id,
+        )?;
+
+        Ok(Query {
+            data,
+            id,
+            question,
+            sent_name,
+            randomized_case: randomize_case,
+        })
+    }
+
+    /// Response to this query, as long as its ID and question match.
+    /// Truncated responses fail with [`RequestError::Truncated`], so the query can be sent over TCP
+    fn response(&self, data: Bytes) -> Result<Message, RequestError> {
+        let mut message = Message::parse(&mut BytesBuf::from_bytes(data))?;
+
+        if message.header.id != self.id {
+            return Err(RequestError::IdMismatch {
+                expected: self.id,
+                received: message.header.id,
+            });
+        }
+
+        // Truncated messages don't get parsed past the header, so there's no question to check.
+        // At worst a spoofed one sends the query over TCP, where spoofing is much harder
+        if message.header.is_truncated {
+            return Err(RequestError::Truncated);
+        }
+
+        let matches = match message.questions.as_slice() {
+            [question] => {
+                question.qtype == self.question.qtype
+                    && question.qclass == self.question.qclass
+                    && if self.randomized_case {
+                        question.name == self.sent_name
+                    } else {
+                        question.name.to_lowercase() == self.sent_name.to_lowercase()
+                    }
+            }
+            _ => false,
+        };
+        if !matches {
+            return Err(RequestError::QuestionMismatch);
+        }
+
+        // Callers get back the question they asked
+        message.questions = vec![self.question.clone()];
+
+        Ok(message)
+    }
+}
+
+/// `name` with each letter randomly upper or lower case (DNS 0x20)
+fn randomized_case(name: &Domain) -> Domain {
+    Domain(
+        name.0
+            .iter()
+            .map(|label| {
+                label
+                    .chars()
+                    .map(|c| {
+                        if rand::random() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            c.to_ascii_lowercase()
+                        }
+                    })
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+/// `data` with the two byte length prefix used over TCP and TLS
+fn framed(data: &[u8]) -> Vec<u8> {
     let mut buf = BytesMut::new();
 
     buf.reserve(data.len() + 2);
 
-    buf.put_u16(data.len().try_into()?);
+    // Queries are built from a single question, so they can't get anywhere near this big
+    buf.put_u16(data.len().try_into().unwrap_or(u16::MAX));
     buf.put(data);
 
-    stream.write_all(&buf)?;
+    buf.into()
+}
+
+fn generic_stream_req<T: Read + Write>(
+    stream: &mut T,
+    query: &Query,
+) -> Result<Message, RequestError> {
+    stream.write_all(&framed(&query.data))?;
 
     let mut size = [0; 2];
     stream.read_exact(&mut size)?;
 
     let size = u16::from_be_bytes(size) as usize;
 
-    let mut data = vec![0; size];
-    stream.read_exact(&mut data)?;
+    let mut response = vec![0; size];
+    stream.read_exact(&mut response)?;
 
-    let buf: Bytes = data.into();
-
-    Ok(Message::parse(&mut BytesBuf::from_bytes(buf))?)
+    query.response(response.into())
 }
 
-fn make_tls_req(data: Bytes, source: SocketAddr) -> Result<Option<Message>> {
-    let root_store = RootCertStore {
-        roots: webpki_roots::TLS_SERVER_ROOTS.into(),
-    };
-    let mut config = rustls::ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
+/// TCP connection to `source`, with reads giving up after `timeout`
+fn connect(source: SocketAddr, timeout: Duration) -> Result<TcpStream, RequestError> {
+    let sock = TcpStream::connect_timeout(&source, timeout)?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.set_write_timeout(Some(timeout))?;
 
-    config.key_log = Arc::new(rustls::KeyLogFile::new());
+    Ok(sock)
+}
 
-    let server_name = ServerName::IpAddress(source.ip().into());
-    let mut conn = rustls::ClientConnection::new(Arc::new(config), server_name).unwrap();
+fn make_tls_req(
+    query: &Query,
+    source: SocketAddr,
+    options: &TlsOptions,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    let server_name = options.server_name(source)?;
+    let mut conn = rustls::ClientConnection::new(tls_config(options, &[])?, server_name)?;
 
-    let mut sock = TcpStream::connect_timeout(&source, Duration::from_secs(5)).unwrap();
+    let mut sock = connect(source, deadline.attempt(0)?)?;
 
     let mut tls_stream = rustls::Stream::new(&mut conn, &mut sock);
 
-    let res = generic_stream_req(&mut tls_stream, data)?;
+    let res = generic_stream_req(&mut tls_stream, query)?;
 
     tls_stream.conn.send_close_notify();
+    // The response is already in hand, failing to say goodbye doesn't matter
+    let _ = tls_stream.flush();
 
-    Ok(Some(res))
+    Ok(res)
 }
 
-fn make_tcp_req(data: Bytes, source: SocketAddr) -> Result<Message> {
-    let mut stream = TcpStream::connect(source)?;
+fn make_tcp_req(
+    query: &Query,
+    source: SocketAddr,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    let mut stream = connect(source, deadline.attempt(0)?)?;
 
-    let res = generic_stream_req(&mut stream, data)?;
+    let res = generic_stream_req(&mut stream, query)?;
 
-    stream.shutdown(std::net::Shutdown::Both)?;
+    let _ = stream.shutdown(std::net::Shutdown::Both);
 
     Ok(res)
 }
 
-fn make_udp_req(data: &Bytes, source: SocketAddr) -> Result<Option<Message>> {
-    let local_bind = match source {
+/// Any local address of the same family as `source`, on a port picked by the OS
+fn local_bind(source: SocketAddr) -> SocketAddr {
+    match source {
         SocketAddr::V4(_) => SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)),
         SocketAddr::V6(_) => SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0)),
-    };
+    }
+}
 
-    let socket = UdpSocket::bind(local_bind)?;
+/// Local addresses to try binding UDP sockets to. Random ports make spoofed responses harder to
+/// get to us, if they're all taken the OS picks one
+fn udp_binds(source: SocketAddr) -> Vec<SocketAddr> {
+    let mut binds: Vec<SocketAddr> = (0..8)
+        .map(|_| {
+            with_port(
+                local_bind(source),
+                rand::thread_rng().gen_range(1024..=u16::MAX),
+            )
+        })
+        .collect();
+    binds.push(local_bind(source));
 
-    socket.send_to(data, source)?;
+    binds
+}
 
-    let mut data = vec![0; 512];
-    socket.recv(&mut data)?;
+/// Checks a datagram from `from` is the response to `query`. Anything else could be spoofed
+/// and is ignored rather than ending the request
+fn udp_response(
+    query: &Query,
+    source: SocketAddr,
+    from: SocketAddr,
+    response: &[u8],
+) -> Option<Result<Message, RequestError>> {
+    if from != source {
+        return None;
+    }
 
-    drop(socket);
+    match query.response(Bytes::copy_from_slice(response)) {
+        Ok(message) => Some(Ok(message)),
+        Err(RequestError::Truncated) => Some(Err(RequestError::Truncated)),
+        Err(_) => None,
+    }
+}
 
-    let buf: Bytes = data.into();
+/// Waits until `end` for the response to `query`
+fn receive_udp(
+    socket: &UdpSocket,
+    query: &Query,
+    source: SocketAddr,
+    end: Instant,
+) -> Result<Message, RequestError> {
+    let mut response = vec![0; UDP_PAYLOAD_SIZE.into()];
+
+    loop {
+        let remaining = end.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(RequestError::Timeout);
+        }
+
+        socket.set_read_timeout(Some(remaining))?;
+        let (len, from) = socket.recv_from(&mut response)?;
 
-    let ret = Message::parse(&mut BytesBuf::from_bytes(buf))?;
+        if let Some(result) = udp_response(query, source, from, &response[..len]) {
+            return result;
+        }
+    }
+}
 
-    if ret.header.is_truncated {
-        Ok(None) // Err(format_err!("Data was truncated, try again over TCP"))
-    } else {
-        Ok(Some(ret))
+fn make_udp_req(
+    query: &Query,
+    source: SocketAddr,
+    deadline: Deadline,
+    retries: u32,
+) -> Result<Message, RequestError> {
+    let socket = UdpSocket::bind(&*udp_binds(source))?;
+
+    // The same socket is kept for retries, so a late response to an earlier try still counts
+    for retry in 0..=retries {
+        let end = Instant::now() + deadline.attempt(retry)?;
+        socket.send_to(&query.data, source)?;
+
+        match receive_udp(&socket, query, source, end) {
+            Err(RequestError::Timeout) => continue,
+            result => return result,
+        }
     }
+
+    Err(RequestError::Timeout)
 }
 
-pub fn make_unspecified_req(data: Bytes, source: SocketAddr) -> Result<Message> {
+fn make_unspecified_req(
+    query: &Query,
+    source: SocketAddr,
+    deadline: Deadline,
+    retries: u32,
+) -> Result<Message, RequestError> {
     // TODO: log error when tracing is setup
-    if let Ok(Some(response)) = make_udp_req(&data, source) {
-        Ok(response)
-    } else {
-        make_tcp_req(data, source)
+    match make_udp_req(query, source, deadline, retries) {
+        Ok(response) => Ok(response),
+        // Nothing is left for TCP
+        Err(RequestError::Timeout) if deadline.attempt(0).is_err() => Err(RequestError::Timeout),
+        Err(_) => make_tcp_req(query, source, deadline),
     }
 }
 
+/// Same server on the standard port for another transport
+fn with_port(source: SocketAddr, port: u16) -> SocketAddr {
+    SocketAddr::new(source.ip(), port)
+}
+
 pub fn make_request(
     question: Question,
     source: SocketAddr,
     transport: Transport,
-) -> Result<Message> {
+) -> Result<Message, RequestError> {
+    make_request_with_options(question, source, transport, &RequestOptions::default())
+}
+
+/// Query for `question` in wire format, asking for recursion
+fn query_message(question: Question, id: u16) -> Result<Bytes, RequestError> {
     let mut msg_buf = BytesMut::new();
     Message {
         header: Header {
-            id: 0,
+            id,
             is_response: false,
             opcode: OpCode::Query,
             is_authoritative: false,
@@ -158,39 +456,54 @@ pub fn make_request(
         answers: vec![],
         authorities: vec![],
         additional: vec![],
+        edns: Some(Edns::new(UDP_PAYLOAD_SIZE)),
     }
     .serialize(&mut msg_buf)?;
 
-    let data: Bytes = msg_buf.into();
+    Ok(msg_buf.into())
+}
 
+/// Query to send over `transport`
+fn query_for(
+    question: Question,
+    transport: Transport,
+    options: &RequestOptions,
+) -> Result<Query, RequestError> {
     match transport {
-        Transport::Https => Err(format_err!("HTTPS is a WIP transport")),
-        Transport::Tcp => make_tcp_req(data, source),
-        Transport::Udp => {
-            if let Some(response) = make_udp_req(&data, source)? {
-                Ok(response)
-            } else {
-                Err(format_err!("Data was truncated, try again over TCP"))
-            }
-        }
-        Transport::Unspecified => make_unspecified_req(data, source),
-        // TODO: Make unspecified ecrypted fall back to/from HTTPS once thats implemented
-        Transport::Tls | Transport::UnspecifiedEncrypted => {
-            if let Some(response) = make_tls_req(data, source)? {
-                Ok(response)
-            } else {
-                Err(format_err!(
-                    "Failed to connect to TLS socket, try again over UDP/TCP"
-                ))
-            }
-        }
-        Transport::TryEncrypted => {
-            // TODO: log error when tracing is setup
-            if let Some(response) = make_tls_req(data.clone(), source)? {
-                Ok(response)
-            } else {
-                make_unspecified_req(data, source)
-            }
-        }
+        // QUIC streams already tell responses apart, so DoQ queries have ID 0 (RFC 9250 section 4.2.1).
+        // So do DoH queries, so the same question is the same GET request for HTTP caches
+        // (RFC 8484 section 4.1). Responses to both are matched on their question
+        Transport::Quic | Transport::Https => Query::with_id(question, 0, options.randomize_case),
+        _ => Query::new(question, options.randomize_case),
+    }
+}
+
+/// Makes a request, when `transport` falls back to another one the same server is tried on
+/// that transport's standard port
+pub fn make_request_with_options(
+    question: Question,
+    source: SocketAddr,
+    transport: Transport,
+    options: &RequestOptions,
+) -> Result<Message, RequestError> {
+    let query = query_for(question, transport, options)?;
+    let deadline = Deadline::new(options);
+    let retries = options.udp_retries;
+
+    match transport {
+        Transport::Https => make_https_req(&query, source, options, deadline),
+        Transport::Quic => quic::make_blocking_quic_req(&query, source, &options.tls, deadline),
+        Transport::Tcp => make_tcp_req(&query, source, deadline),
+        Transport::Udp => make_udp_req(&query, source, deadline, retries),
+        Transport::Unspecified => make_unspecified_req(&query, source, deadline, retries),
+        Transport::Tls => make_tls_req(&query, source, &options.tls, deadline),
+        // TODO: log errors when tracing is setup
+        Transport::UnspecifiedEncrypted => make_tls_req(&query, source, &options.tls, deadline)
+            .or_else(|_| make_https_req(&query, with_port(source, HTTPS_PORT), options, deadline)),
+        Transport::TryEncrypted => make_tls_req(&query, source, &options.tls, deadline)
+            .or_else(|_| make_https_req(&query, with_port(source, HTTPS_PORT), options, deadline))
+            .or_else(|_| {
+                make_unspecified_req(&query, with_port(source, DNS_PORT), deadline, retries)
+            }),
     }
 }
@@ -0,0 +1,288 @@
+use std::{future::Future, net::SocketAddr, time::Duration};
+
+use bytes::Bytes;
+use tokio::{
+    io::{
+        AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+        BufReader,
+    },
+    net::{TcpStream, UdpSocket},
+    time::timeout,
+};
+use tokio_rustls::TlsConnector;
+use types::{Message, Question};
+
+use crate::{
+    framed,
+    https::{
+        check_status, chunk_size, complete_line, http_request, server_name, BodyLength,
+        ResponseHeaders, MAX_BODY_SIZE, MAX_LINE_SIZE,
+    },
+    query_for,
+    quic::make_quic_req,
+    tls_config, udp_binds, udp_response, with_port, Deadline, Query, RequestError, RequestOptions,
+    TlsOptions, Transport, DNS_PORT, HTTPS_PORT, UDP_PAYLOAD_SIZE,
+};
+
+/// Runs `request`, giving up once `limit` has passed
+pub(crate) async fn limited<T>(
+    limit: Duration,
+    request: impl Future<Output = Result<T, RequestError>>,
+) -> Result<T, RequestError> {
+    timeout(limit, request)
+        .await
+        .unwrap_or(Err(RequestError::Timeout))
+}
+
+async fn generic_stream_req<T: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut T,
+    query: &Query,
+) -> Result<Message, RequestError> {
+    stream.write_all(&framed(&query.data)).await?;
+
+    let size = stream.read_u16().await?;
+
+    let mut response = vec![0; size.into()];
+    stream.read_exact(&mut response).await?;
+
+    query.response(response.into())
+}
+
+async fn make_tls_req(
+    query: &Query,
+    source: SocketAddr,
+    options: &TlsOptions,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    limited(deadline.attempt(0)?, async {
+        let server_name = options.server_name(source)?;
+        let config = tls_config(options, &[])?;
+        let sock = TcpStream::connect(source).await?;
+
+        let mut tls_stream = TlsConnector::from(config)
+            .connect(server_name, sock)
+            .await?;
+
+        let res = generic_stream_req(&mut tls_stream, query).await?;
+
+        // Sends close_notify, the response is already in hand so failing to doesn't matter
+        let _ = tls_stream.shutdown().await;
+
+        Ok(res)
+    })
+    .await
+}
+
+async fn make_tcp_req(
+    query: &Query,
+    source: SocketAddr,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    limited(deadline.attempt(0)?, async {
+        let mut stream = TcpStream::connect(source).await?;
+
+        let res = generic_stream_req(&mut stream, query).await?;
+
+        let _ = stream.shutdown().await;
+
+        Ok(res)
+    })
+    .await
+}
+
+async fn make_udp_req(
+    query: &Query,
+    source: SocketAddr,
+    deadline: Deadline,
+    retries: u32,
+) -> Result<Message, RequestError> {
+    let socket = UdpSocket::bind(&*udp_binds(source)).await?;
+    let mut response = vec![0; UDP_PAYLOAD_SIZE.into()];
+
+    // The same socket is kept for retries, so a late response to an earlier try still counts
+    for retry in 0..=retries {
+        let limit = deadline.attempt(retry)?;
+        socket.send_to(&query.data, source).await?;
+
+        let receive = async {
+            loop {
+                let (len, from) = socket.recv_from(&mut response).await?;
+
+                if let Some(result) = udp_response(query, source, from, &response[..len]) {
+                    return result;
+                }
+            }
+        };
+
+        match limited(limit, receive).await {
+            Err(RequestError::Timeout) => continue,
+            result => return result,
+        }
+    }
+
+    Err(RequestError::Timeout)
+}
+
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<String, RequestError> {
+    let mut line = String::new();
+    reader.take(MAX_LINE_SIZE).read_line(&mut line).await?;
+
+    complete_line(&line)
+}
+
+async fn read_chunked<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, RequestError> {
+    let mut body = vec![];
+
+    loop {
+        let size = chunk_size(&read_line(reader).await?, body.len())?;
+        if size == 0 {
+            break;
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+
+        if !read_line(reader).await?.is_empty() {
+            return Err(RequestError::Http(
+                "HTTP chunk is longer than its size".into(),
+            ));
+        }
+    }
+
+    // Trailers
+    while !read_line(reader).await?.is_empty() {}
+
+    Ok(body)
+}
+
+/// Body of an HTTP/1.1 response, as long as it is a successful one carrying a DNS message
+pub(crate) async fn read_http_response<R: AsyncRead + Unpin>(
+    stream: R,
+) -> Result<Bytes, RequestError> {
+    let mut reader = BufReader::new(stream);
+
+    check_status(&read_line(&mut reader).await?)?;
+
+    let mut headers = ResponseHeaders::default();
+    loop {
+        let line = read_line(&mut reader).await?;
+        if line.is_empty() {
+            break;
+        }
+
+        headers.add(&line)?;
+    }
+
+    let body = match headers.body_length()? {
+        BodyLength::Chunked => read_chunked(&mut reader).await?,
+        BodyLength::Known(length) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            body
+        }
+        BodyLength::UntilClose => {
+            let mut body = vec![];
+            reader
+                .take(MAX_BODY_SIZE as u64 + 1)
+                .read_to_end(&mut body)
+                .await?;
+            if body.len() > MAX_BODY_SIZE {
+                return Err(RequestError::Http("HTTP response body is too big".into()));
+            }
+            body
+        }
+    };
+
+    Ok(body.into())
+}
+
+async fn make_https_req(
+    query: &Query,
+    source: SocketAddr,
+    options: &RequestOptions,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    limited(deadline.attempt(0)?, async {
+        let server_name = server_name(&options.https, source)?;
+        let config = tls_config(&options.tls, &[b"http/1.1"])?;
+        let sock = TcpStream::connect(source).await?;
+
+        let mut tls_stream = TlsConnector::from(config)
+            .connect(server_name, sock)
+            .await?;
+
+        tls_stream
+            .write_all(&http_request(&options.https, source, &query.data))
+            .await?;
+        let body = read_http_response(&mut tls_stream).await?;
+
+        // The response is already in hand, failing to say goodbye doesn't matter
+        let _ = tls_stream.shutdown().await;
+
+        query.response(body)
+    })
+    .await
+}
+
+async fn make_unspecified_req(
+    query: &Query,
+    source: SocketAddr,
+    deadline: Deadline,
+    retries: u32,
+) -> Result<Message, RequestError> {
+    match make_udp_req(query, source, deadline, retries).await {
+        Ok(response) => Ok(response),
+        // Nothing is left for TCP
+        Err(RequestError::Timeout) if deadline.attempt(0).is_err() => Err(RequestError::Timeout),
+        Err(_) => make_tcp_req(query, source, deadline).await,
+    }
+}
+
+pub async fn make_request(
+    question: Question,
+    source: SocketAddr,
+    transport: Transport,
+) -> Result<Message, RequestError> {
+    make_request_with_options(question, source, transport, &RequestOptions::default()).await
+}
+
+/// Makes a request like [`crate::make_request_with_options`] does, with the same timeouts.
+/// Dropping the future cancels the request, closing its socket
+pub async fn make_request_with_options(
+    question: Question,
+    source: SocketAddr,
+    transport: Transport,
+    options: &RequestOptions,
+) -> Result<Message, RequestError> {
+    let query = query_for(question, transport, options)?;
+    let deadline = Deadline::new(options);
+    let retries = options.udp_retries;
+    let https = |source| make_https_req(&query, source, options, deadline);
+
+    match transport {
+        Transport::Https => https(source).await,
+        Transport::Quic => make_quic_req(&query, source, &options.tls, deadline).await,
+        Transport::Tcp => make_tcp_req(&query, source, deadline).await,
+        Transport::Udp => make_udp_req(&query, source, deadline, retries).await,
+        Transport::Unspecified => make_unspecified_req(&query, source, deadline, retries).await,
+        Transport::Tls => make_tls_req(&query, source, &options.tls, deadline).await,
+        Transport::UnspecifiedEncrypted => {
+            match make_tls_req(&query, source, &options.tls, deadline).await {
+                Ok(response) => Ok(response),
+                Err(_) => https(with_port(source, HTTPS_PORT)).await,
+            }
+        }
+        Transport::TryEncrypted => match make_tls_req(&query, source, &options.tls, deadline).await
+        {
+            Ok(response) => Ok(response),
+            Err(_) => match https(with_port(source, HTTPS_PORT)).await {
+                Ok(response) => Ok(response),
+                Err(_) => {
+                    make_unspecified_req(&query, with_port(source, DNS_PORT), deadline, retries)
+                        .await
+                }
+            },
+        },
+    }
+}
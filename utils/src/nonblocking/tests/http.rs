@@ -0,0 +1,27 @@
+use crate::nonblocking::read_http_response;
+
+#[tokio::test]
+async fn response_bodies() {
+    let chunked = b"HTTP/1.1 200 OK\r\n\
+                    Content-Type: application/dns-message\r\n\
+                    Transfer-Encoding: chunked\r\n\r\n\
+                    2\r\nab\r\n\
+                    1\r\nc\r\n\
+                    0\r\n\r\n";
+    assert_eq!(&read_http_response(&chunked[..]).await.unwrap()[..], b"abc");
+
+    let until_close = b"HTTP/1.1 200 OK\r\n\
+                        Content-Type: application/dns-message\r\n\r\n\
+                        abc";
+    assert_eq!(
+        &read_http_response(&until_close[..]).await.unwrap()[..],
+        b"abc"
+    );
+
+    let not_found = b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+    assert!(read_http_response(&not_found[..]).await.is_err());
+
+    let cut_short =
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\nContent-Length: 5\r\n\r\nabc";
+    assert!(read_http_response(&cut_short[..]).await.is_err());
+}
@@ -0,0 +1,276 @@
+use std::{net::SocketAddr, time::Duration};
+
+use bytes::BytesMut;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UdpSocket},
+    time::timeout,
+};
+use types::{
+    parser::{BytesBuf, Parsable},
+    serializer::Serializable,
+    Domain, Message, Question, RecordClass, RecordType, ResCode,
+};
+
+use crate::{
+    nonblocking::{make_request, make_request_with_options},
+    RequestError, RequestOptions, Transport,
+};
+
+fn question() -> Question {
+    Question {
+        name: Domain(vec!["www".into(), "example".into()]),
+        qtype: RecordType::A,
+        qclass: RecordClass::IN,
+    }
+}
+
+fn parse(data: Vec<u8>) -> Message {
+    Message::parse(&mut BytesBuf::from_bytes(data.into())).unwrap()
+}
+
+fn serialize(mut query: Message, truncated: bool) -> BytesMut {
+    query.header.is_response = true;
+    query.header.is_truncated = truncated;
+
+    let mut buf = BytesMut::new();
+    query.serialize(&mut buf).unwrap();
+    buf
+}
+
+async fn read_query(stream: &mut TcpStream) -> Message {
+    let size = stream.read_u16().await.unwrap();
+
+    let mut data = vec![0; size.into()];
+    stream.read_exact(&mut data).await.unwrap();
+
+    parse(data)
+}
+
+#[tokio::test]
+async fn udp_falls_back_to_tcp() {
+    let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = udp.local_addr().unwrap();
+    let tcp = TcpListener::bind(addr).await.unwrap();
+
+    tokio::spawn(async move {
+        let mut data = vec![0; 512];
+        let (len, client) = udp.recv_from(&mut data).await.unwrap();
+        data.truncate(len);
+
+        udp.send_to(&serialize(parse(data), true), client)
+            .await
+            .unwrap();
+    });
+
+    tokio::spawn(async move {
+        let (mut stream, _) = tcp.accept().await.unwrap();
+        let response = serialize(read_query(&mut stream).await, false);
+
+        stream
+            .write_u16(response.len().try_into().unwrap())
+            .await
+            .unwrap();
+        stream.write_all(&response).await.unwrap();
+    });
+
+    let response = make_request(question(), addr, Transport::Unspecified)
+        .await
+        .unwrap();
+    assert!(response.header.is_response);
+    assert!(!response.header.is_truncated);
+    assert_eq!(response.questions, vec![question()]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn times_out() {
+    // Never answers
+    let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    let err = make_request(question(), udp.local_addr().unwrap(), Transport::Udp)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RequestError::Timeout));
+}
+
+#[tokio::test(start_paused = true)]
+async fn udp_retries() {
+    let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = udp.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let mut data = vec![0; 512];
+        // The first try gets lost
+        udp.recv_from(&mut data).await.unwrap();
+        let (len, client) = udp.recv_from(&mut data).await.unwrap();
+        data.truncate(len);
+
+        udp.send_to(&serialize(parse(data.clone()), false), client)
+            .await
+            .unwrap();
+
+        // Then the next query gets lost too
+        udp.recv_from(&mut data).await.unwrap();
+    });
+
+    let options = RequestOptions {
+        attempt_timeout: Duration::from_secs(1),
+        udp_retries: 1,
+        ..RequestOptions::default()
+    };
+    let response = make_request_with_options(question(), addr, Transport::Udp, &options)
+        .await
+        .unwrap();
+    assert!(response.header.is_response);
+
+    // Without retries the lost query is the end of it
+    let options = RequestOptions {
+        udp_retries: 0,
+        ..options
+    };
+    let err = make_request_with_options(question(), addr, Transport::Udp, &options)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RequestError::Timeout));
+    server.await.unwrap();
+}
+
+/// Receives a query on `udp` and sends back whatever `responses` make of it, in order
+async fn respond_udp(udp: UdpSocket, responses: Vec<fn(Message) -> Message>) {
+    let mut data = vec![0; 512];
+    let (len, client) = udp.recv_from(&mut data).await.unwrap();
+    data.truncate(len);
+
+    for response in responses {
+        udp.send_to(&serialize(response(parse(data.clone())), false), client)
+            .await
+            .unwrap();
+    }
+}
+
+/// Marks a response as being one that shouldn't have been accepted
+fn spoofed(mut response: Message) -> Message {
+    response.header.rescode = ResCode::NameError;
+    response
+}
+
+#[tokio::test]
+async fn spoofed_responses() {
+    let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = udp.local_addr().unwrap();
+
+    tokio::spawn(respond_udp(
+        udp,
+        vec![
+            |query| {
+                let mut response = spoofed(query);
+                response.header.id = response.header.id.wrapping_add(1);
+                response
+            },
+            |query| {
+                let mut response = spoofed(query);
+                response.questions[0].qtype = RecordType::AAAA;
+                response
+            },
+            |query| {
+                let mut response = spoofed(query);
+                response.questions.clear();
+                response
+            },
+            |query| query,
+        ],
+    ));
+
+    let response = make_request(question(), addr, Transport::Udp)
+        .await
+        .unwrap();
+    assert_eq!(response.header.rescode, ResCode::NoError);
+}
+
+#[tokio::test]
+async fn spoofed_source() {
+    let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = udp.local_addr().unwrap();
+    let spoofer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    tokio::spawn(async move {
+        let mut data = vec![0; 512];
+        let (len, client) = udp.recv_from(&mut data).await.unwrap();
+        data.truncate(len);
+
+        // Gets everything right but where it comes from
+        spoofer
+            .send_to(&serialize(spoofed(parse(data.clone())), false), client)
+            .await
+            .unwrap();
+        udp.send_to(&serialize(parse(data), false), client)
+            .await
+            .unwrap();
+    });
+
+    let response = make_request(question(), addr, Transport::Udp)
+        .await
+        .unwrap();
+    assert_eq!(response.header.rescode, ResCode::NoError);
+}
+
+#[tokio::test]
+async fn randomized_case() {
+    let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = udp.local_addr().unwrap();
+
+    tokio::spawn(respond_udp(
+        udp,
+        vec![
+            |query| {
+                // Flipping the case of every letter is sure to get it wrong
+                let mut response = spoofed(query);
+                for label in &mut response.questions[0].name.0 {
+                    *label = label
+                        .chars()
+                        .map(|c| {
+                            if c.is_ascii_uppercase() {
+                                c.to_ascii_lowercase()
+                            } else {
+                                c.to_ascii_uppercase()
+                            }
+                        })
+                        .collect();
+                }
+                response
+            },
+            |query| query,
+        ],
+    ));
+
+    let options = RequestOptions {
+        randomize_case: true,
+        ..RequestOptions::default()
+    };
+    let response = make_request_with_options(question(), addr, Transport::Udp, &options)
+        .await
+        .unwrap();
+    assert_eq!(response.header.rescode, ResCode::NoError);
+    // Whatever case was sent, the question comes back the way it was asked
+    assert_eq!(response.questions, vec![question()]);
+}
+
+#[tokio::test]
+async fn cancelling_closes_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_query(&mut stream).await;
+
+        // Ends once the client hangs up
+        stream.read(&mut [0]).await.unwrap()
+    });
+
+    // Long before the request would time out on its own
+    let request = make_request(question(), addr, Transport::Tcp);
+    assert!(timeout(Duration::from_millis(100), request).await.is_err());
+
+    assert_eq!(server.await.unwrap(), 0);
+}
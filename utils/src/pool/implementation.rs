@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, Read},
+    net::{SocketAddr, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use types::{Message, Question};
+
+use crate::{
+    framed, make_request_with_options,
+    quic::QuicConnections,
+    stream::{Duplex, DuplexReader, TcpDuplex, TlsDuplex},
+    tls_config, Deadline, Query, RequestError, RequestOptions, TlsOptions, Transport,
+};
+
+/// Most queries waiting for a response on one connection. Well short of every ID being in use,
+/// so picking a random unused one stays quick
+const MAX_PENDING: usize = 4096;
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Nothing is left half done while these locks are held
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Queries sharing one TCP or TLS connection, each response goes to the query with its ID
+struct Multiplexed {
+    duplex: Box<dyn Duplex>,
+    /// Queries waiting for a response, by ID
+    pending: Mutex<HashMap<u16, Sender<Bytes>>>,
+    open: AtomicBool,
+}
+
+impl Multiplexed {
+    fn connect(
+        source: SocketAddr,
+        transport: Transport,
+        tls: &TlsOptions,
+        timeout: Duration,
+    ) -> Result<Arc<Multiplexed>, RequestError> {
+        let socket = TcpStream::connect_timeout(&source, timeout)?;
+        // Queries are small and shouldn't wait around for more to send
+        socket.set_nodelay(true)?;
+
+        let duplex: Box<dyn Duplex> = match transport {
+            Transport::Tcp => Box::new(TcpDuplex::new(socket)),
+            Transport::Tls => {
+                let conn =
+                    rustls::ClientConnection::new(tls_config(tls, &[])?, tls.server_name(source)?)?;
+                Box::new(TlsDuplex::new(socket, conn))
+            }
+            _ => unreachable!("only TCP and TLS connections get pooled"),
+        };
+
+        let conn = Arc::new(Multiplexed {
+            duplex,
+            pending: Mutex::new(HashMap::new()),
+            open: AtomicBool::new(true),
+        });
+
+        let reader = conn.clone();
+        thread::spawn(move || reader.read_responses());
+
+        Ok(conn)
+    }
+
+    fn is_open(&self) -> bool {
+        self.open.load(Ordering::Relaxed)
+    }
+
+    /// Hands out responses as they arrive, in whatever order the server sends them
+    fn read_responses(&self) {
+        let mut reader = BufReader::new(DuplexReader(&*self.duplex));
+
+        loop {
+            let mut size = [0; 2];
+            if reader.read_exact(&mut size).is_err() {
+                break;
+            }
+
+            let mut data = vec![0; u16::from_be_bytes(size).into()];
+            if reader.read_exact(&mut data).is_err() {
+                break;
+            }
+
+            let Some(id) = data.get(..2).map(|id| u16::from_be_bytes([id[0], id[1]])) else {
+                continue;
+            };
+
+            // Responses to queries that already gave up are dropped
+            if let Some(sender) = lock(&self.pending).remove(&id) {
+                let _ = sender.send(data.into());
+            }
+        }
+
+        self.open.store(false, Ordering::Relaxed);
+        // Dropping the senders wakes up everything still waiting
+        lock(&self.pending).clear();
+    }
+
+    fn request(
+        &self,
+        question: &Question,
+        timeout: Duration,
+        randomize_case: bool,
+    ) -> Result<Message, RequestError> {
+        let (sender, receiver) = mpsc::channel();
+
+        let id = {
+            let mut pending = lock(&self.pending);
+            if pending.len() >= MAX_PENDING {
+                return Err(RequestError::Io(io::Error::other(
+                    "Too many queries waiting on this connection",
+                )));
+            }
+
+            // Random rather than counting up, so they can't be guessed
+            let mut id = rand::random();
+            while pending.contains_key(&id) {
+                id = rand::random();
+            }
+            pending.insert(id, sender);
+
+            id
+        };
+
+        let forget = || lock(&self.pending).remove(&id);
+
+        let query = match Query::with_id(question.clone(), id, randomize_case) {
+            Ok(query) => query,
+            Err(err) => {
+                forget();
+                return Err(err);
+            }
+        };
+
+        if let Err(err) = self.duplex.write_all(&framed(&query.data)) {
+            forget();
+            self.open.store(false, Ordering::Relaxed);
+            return Err(err.into());
+        }
+
+        match receiver.recv_timeout(timeout) {
+            Ok(response) => query.response(response),
+            Err(RecvTimeoutError::Timeout) => {
+                forget();
+                Err(RequestError::Timeout)
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(RequestError::Closed),
+        }
+    }
+
+    fn close(&self) {
+        let _ = self.duplex.close();
+        let _ = self.duplex.socket().shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// Where the connection to one server is kept, locked while connecting so concurrent requests
+/// share a connection without holding up requests to other servers
+type Slot = Arc<Mutex<Option<Arc<Multiplexed>>>>;
+
+/// Makes requests like [`make_request_with_options`], but keeps TCP, TLS and QUIC connections
+/// open to reuse for later requests to the same server (RFC 7766 section 6.2.1, RFC 9250
+/// section 5.5).
+/// Requests from different threads go out over the same connection without waiting for
+/// each other, and responses can come back in any order
+pub struct Pool {
+    options: RequestOptions,
+    connections: Mutex<HashMap<(SocketAddr, Transport), Slot>>,
+    quic: QuicConnections,
+}
+
+impl Pool {
+    pub fn new(options: RequestOptions) -> Pool {
+        Pool {
+            options,
+            connections: Mutex::new(HashMap::new()),
+            quic: QuicConnections::default(),
+        }
+    }
+
+    /// An open connection to `source`, and whether it was already in the pool
+    fn connection(
+        &self,
+        source: SocketAddr,
+        transport: Transport,
+        timeout: Duration,
+    ) -> Result<(Arc<Multiplexed>, bool), RequestError> {
+        let slot = lock(&self.connections)
+            .entry((source, transport))
+            .or_default()
+            .clone();
+        let mut slot = lock(&slot);
+
+        if let Some(conn) = &*slot {
+            if conn.is_open() {
+                return Ok((conn.clone(), true));
+            }
+        }
+
+        let conn = Multiplexed::connect(source, transport, &self.options.tls, timeout)?;
+        *slot = Some(conn.clone());
+
+        Ok((conn, false))
+    }
+
+    pub fn request(
+        &self,
+        question: Question,
+        source: SocketAddr,
+        transport: Transport,
+    ) -> Result<Message, RequestError> {
+        match transport {
+            Transport::Tcp | Transport::Tls => {}
+            Transport::Quic => return self.quic.request(question, source, &self.options),
+            _ => return make_request_with_options(question, source, transport, &self.options),
+        }
+
+        let deadline = Deadline::new(&self.options);
+
+        let (conn, reused) = self.connection(source, transport, deadline.attempt(0)?)?;
+        let randomize_case = self.options.randomize_case;
+        match conn.request(&question, deadline.attempt(0)?, randomize_case) {
+            // The server can close connections that have been sitting around, so a new one
+            // gets a chance before giving up
+            Err(_) if reused && !conn.is_open() => {
+                let (conn, _) = self.connection(source, transport, deadline.attempt(0)?)?;
+                conn.request(&question, deadline.attempt(0)?, randomize_case)
+            }
+            response => response,
+        }
+    }
+
+    /// How many connections are open
+    pub fn open_connections(&self) -> usize {
+        lock(&self.connections)
+            .values()
+            .filter(|slot| lock(slot).as_ref().is_some_and(|conn| conn.is_open()))
+            .count()
+            + self.quic.open()
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        for slot in lock(&self.connections).values() {
+            if let Some(conn) = &*lock(slot) {
+                conn.close();
+            }
+        }
+        self.quic.close();
+    }
+}
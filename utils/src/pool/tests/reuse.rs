@@ -0,0 +1,108 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+};
+
+use bytes::BytesMut;
+use types::{
+    parser::{BytesBuf, Parsable},
+    serializer::Serializable,
+    Domain, Message, Question, RecordClass, RecordType,
+};
+
+use crate::{Pool, RequestOptions, Transport};
+
+fn question(name: &str) -> Question {
+    Question {
+        name: Domain(vec![name.into(), "example".into()]),
+        qtype: RecordType::A,
+        qclass: RecordClass::IN,
+    }
+}
+
+fn read_query(stream: &mut TcpStream) -> Message {
+    let mut size = [0; 2];
+    stream.read_exact(&mut size).unwrap();
+
+    let mut data = vec![0; u16::from_be_bytes(size).into()];
+    stream.read_exact(&mut data).unwrap();
+
+    Message::parse(&mut BytesBuf::from_bytes(data.into())).unwrap()
+}
+
+fn respond(stream: &mut TcpStream, mut query: Message) {
+    query.header.is_response = true;
+
+    let mut buf = BytesMut::new();
+    query.serialize(&mut buf).unwrap();
+
+    let mut framed = u16::try_from(buf.len()).unwrap().to_be_bytes().to_vec();
+    framed.extend_from_slice(&buf);
+    stream.write_all(&framed).unwrap();
+}
+
+/// Runs `serve` on every connection to a new listener, in order
+fn server(serve: Vec<fn(TcpStream)>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for serve in serve {
+            serve(listener.accept().unwrap().0);
+        }
+    });
+
+    addr
+}
+
+#[test]
+fn out_of_order_responses() {
+    // Only takes one connection, anything after it would never be answered
+    let addr = server(vec![|mut stream| {
+        let first = read_query(&mut stream);
+        let second = read_query(&mut stream);
+        assert_ne!(first.header.id, second.header.id);
+
+        respond(&mut stream, second);
+        respond(&mut stream, first);
+
+        let third = read_query(&mut stream);
+        respond(&mut stream, third);
+
+        // Kept open until the pool closes it
+        let _ = stream.read(&mut [0]);
+    }]);
+
+    let pool = Pool::new(RequestOptions::default());
+
+    thread::scope(|scope| {
+        for name in ["a", "b"] {
+            let pool = &pool;
+            scope.spawn(move || {
+                let response = pool.request(question(name), addr, Transport::Tcp).unwrap();
+                assert_eq!(response.questions, vec![question(name)]);
+            });
+        }
+    });
+
+    let response = pool.request(question("c"), addr, Transport::Tcp).unwrap();
+    assert_eq!(response.questions, vec![question("c")]);
+    assert_eq!(pool.open_connections(), 1);
+}
+
+#[test]
+fn reconnects_after_close() {
+    let answer_once = |mut stream: TcpStream| {
+        let query = read_query(&mut stream);
+        respond(&mut stream, query);
+    };
+    let addr = server(vec![answer_once, answer_once]);
+
+    let pool = Pool::new(RequestOptions::default());
+
+    for name in ["a", "b"] {
+        let response = pool.request(question(name), addr, Transport::Tcp).unwrap();
+        assert_eq!(response.questions, vec![question(name)]);
+    }
+}
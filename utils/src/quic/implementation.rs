@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+use quinn::{
+    crypto::rustls::QuicClientConfig, ClientConfig, Connection, Endpoint, ReadError,
+    ReadToEndError, VarInt, WriteError,
+};
+use tokio::{runtime::Runtime, time::timeout};
+use types::{Message, Question};
+
+use crate::{
+    framed, local_bind, nonblocking::limited, query_for, tls_config, Deadline, Query, RequestError,
+    RequestOptions, TlsOptions, Transport,
+};
+
+/// ALPN protocol of DNS over QUIC (RFC 9250 section 4.1.1)
+pub const DOQ_ALPN: &[u8] = b"doq";
+
+/// Closes the connection once the response is in (RFC 9250 section 4.3)
+const DOQ_NO_ERROR: VarInt = VarInt::from_u32(0);
+
+/// Largest response accepted, a DNS message and its length
+const MAX_RESPONSE_SIZE: usize = 2 + u16::MAX as usize;
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Nothing is left half done while these locks are held
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn client_config(options: &TlsOptions) -> Result<ClientConfig, RequestError> {
+    let crypto = QuicClientConfig::try_from(tls_config(options, &[DOQ_ALPN])?)
+        .map_err(|err| rustls::Error::General(err.to_string()))?;
+
+    Ok(ClientConfig::new(Arc::new(crypto)))
+}
+
+/// New connection to `source` from `endpoint`
+async fn connect(
+    endpoint: &Endpoint,
+    source: SocketAddr,
+    options: &TlsOptions,
+) -> Result<Connection, RequestError> {
+    let server_name = match &options.server_name {
+        Some(name) => name.clone(),
+        None => source.ip().to_string(),
+    };
+
+    Ok(endpoint
+        .connect_with(client_config(options)?, source, &server_name)
+        .map_err(|err| RequestError::Io(io::Error::new(io::ErrorKind::InvalidInput, err)))?
+        .await?)
+}
+
+/// Sends the query on a stream of its own on `connection`
+async fn exchange(connection: &Connection, query: &Query) -> Result<Message, RequestError> {
+    let (mut send, mut recv) = connection.open_bi().await?;
+    // Sending the end of the stream along with the query tells the server there's nothing
+    // more coming on it (RFC 9250 section 4.2)
+    send.write_all(&framed(&query.data))
+        .await
+        .map_err(|err| match err {
+            WriteError::ConnectionLost(err) => RequestError::from(err),
+            err => io::Error::from(err).into(),
+        })?;
+    send.finish().map_err(io::Error::from)?;
+
+    let response = recv
+        .read_to_end(MAX_RESPONSE_SIZE)
+        .await
+        .map_err(|err| match err {
+            ReadToEndError::Read(ReadError::ConnectionLost(err)) => RequestError::from(err),
+            ReadToEndError::Read(err) => io::Error::from(err).into(),
+            ReadToEndError::TooLong => {
+                io::Error::new(io::ErrorKind::InvalidData, "DoQ response is too big").into()
+            }
+        })?;
+
+    match response.split_first_chunk() {
+        Some((size, message)) if usize::from(u16::from_be_bytes(*size)) == message.len() => {
+            query.response(message.to_vec().into())
+        }
+        _ => Err(RequestError::Closed),
+    }
+}
+
+/// DNS over QUIC (RFC 9250), the query on a stream of its own in a new connection
+pub(crate) async fn make_quic_req(
+    query: &Query,
+    source: SocketAddr,
+    options: &TlsOptions,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    let limit = deadline.attempt(0)?;
+    let endpoint = Endpoint::client(local_bind(source))?;
+
+    let request = async {
+        let connection = connect(&endpoint, source, options).await?;
+        let response = exchange(&connection, query).await;
+        connection.close(DOQ_NO_ERROR, b"");
+
+        response
+    };
+
+    let res = limited(limit, request).await;
+
+    // Lets the server know the connection is done with rather than leaving it to time out.
+    // A connection that never got going would take a while to give up on, so it isn't waited for
+    if res.is_ok() {
+        if let Ok(remaining) = deadline.attempt(0) {
+            let _ = timeout(remaining, endpoint.wait_idle()).await;
+        }
+    }
+
+    res
+}
+
+/// Runtime and client endpoints shared by DNS over QUIC requests from blocking code. The runtime
+/// keeps connections going in between requests, and closes them once they're done with
+struct Shared {
+    runtime: Runtime,
+    /// For IPv4 and IPv6 servers, created when first needed
+    endpoints: Mutex<HashMap<bool, Endpoint>>,
+}
+
+impl Shared {
+    fn get() -> Result<&'static Shared, RequestError> {
+        static SHARED: OnceLock<Shared> = OnceLock::new();
+
+        if let Some(shared) = SHARED.get() {
+            return Ok(shared);
+        }
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("doq")
+            .enable_all()
+            .build()?;
+
+        // Another thread might have gotten there first, which is just as good
+        let _ = SHARED.set(Shared {
+            runtime,
+            endpoints: Mutex::new(HashMap::new()),
+        });
+
+        Ok(SHARED.get().expect("was just set"))
+    }
+
+    /// Endpoint for connecting to `source`, has to be called inside the runtime
+    fn endpoint(&self, source: SocketAddr) -> Result<Endpoint, RequestError> {
+        let mut endpoints = lock(&self.endpoints);
+        if let Some(endpoint) = endpoints.get(&source.is_ipv4()) {
+            return Ok(endpoint.clone());
+        }
+
+        let endpoint = Endpoint::client(local_bind(source))?;
+        endpoints.insert(source.is_ipv4(), endpoint.clone());
+
+        Ok(endpoint)
+    }
+}
+
+/// DNS over QUIC from blocking code, in a new connection that gets closed once the response is in
+pub(crate) fn make_blocking_quic_req(
+    query: &Query,
+    source: SocketAddr,
+    options: &TlsOptions,
+    deadline: Deadline,
+) -> Result<Message, RequestError> {
+    let shared = Shared::get()?;
+
+    shared
+        .runtime
+        .block_on(limited(deadline.attempt(0)?, async {
+            let connection = connect(&shared.endpoint(source)?, source, options).await?;
+            let response = exchange(&connection, query).await;
+            connection.close(DOQ_NO_ERROR, b"");
+
+            response
+        }))
+}
+
+/// Where the connection to one server is kept, locked while connecting so concurrent requests
+/// share a connection without holding up requests to other servers
+type Slot = Arc<tokio::sync::Mutex<Option<Connection>>>;
+
+/// DNS over QUIC connections kept open between queries (RFC 9250 section 5.5), each query
+/// going out on a stream of its own
+#[derive(Default)]
+pub(crate) struct QuicConnections {
+    connections: Mutex<HashMap<SocketAddr, Slot>>,
+}
+
+impl QuicConnections {
+    /// An open connection to `source`, and whether it was already around
+    async fn connection(
+        &self,
+        shared: &Shared,
+        source: SocketAddr,
+        options: &TlsOptions,
+    ) -> Result<(Connection, bool), RequestError> {
+        let slot = lock(&self.connections).entry(source).or_default().clone();
+        let mut slot = slot.lock().await;
+
+        if let Some(connection) = &*slot {
+            if connection.close_reason().is_none() {
+                return Ok((connection.clone(), true));
+            }
+        }
+
+        let connection = connect(&shared.endpoint(source)?, source, options).await?;
+        *slot = Some(connection.clone());
+
+        Ok((connection, false))
+    }
+
+    pub(crate) fn request(
+        &self,
+        question: Question,
+        source: SocketAddr,
+        options: &RequestOptions,
+    ) -> Result<Message, RequestError> {
+        let shared = Shared::get()?;
+        let deadline = Deadline::new(options);
+        let query = query_for(question, Transport::Quic, options)?;
+
+        shared.runtime.block_on(async {
+            let (connection, reused) = limited(
+                deadline.attempt(0)?,
+                self.connection(shared, source, &options.tls),
+            )
+            .await?;
+
+            match limited(deadline.attempt(0)?, exchange(&connection, &query)).await {
+                // The server can close connections that have been sitting around, so a new one
+                // gets a chance before giving up
+                Err(_) if reused && connection.close_reason().is_some() => {
+                    let (connection, _) = limited(
+                        deadline.attempt(0)?,
+                        self.connection(shared, source, &options.tls),
+                    )
+                    .await?;
+                    limited(deadline.attempt(0)?, exchange(&connection, &query)).await
+                }
+                response => response,
+            }
+        })
+    }
+
+    /// How many connections are open
+    pub(crate) fn open(&self) -> usize {
+        lock(&self.connections)
+            .values()
+            .filter(|slot| {
+                slot.try_lock()
+                    .is_ok_and(|slot| slot.as_ref().is_some_and(|c| c.close_reason().is_none()))
+            })
+            .count()
+    }
+
+    pub(crate) fn close(&self) {
+        for slot in lock(&self.connections).values() {
+            if let Ok(slot) = slot.try_lock() {
+                if let Some(connection) = &*slot {
+                    connection.close(DOQ_NO_ERROR, b"");
+                }
+            }
+        }
+    }
+}
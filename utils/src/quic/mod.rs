@@ -0,0 +1,7 @@
+mod implementation;
+
+#[cfg(test)]
+mod tests;
+
+pub use implementation::DOQ_ALPN;
+pub(crate) use implementation::*;
@@ -0,0 +1,138 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use bytes::BytesMut;
+use quinn::{crypto::rustls::QuicServerConfig, Connection, Endpoint, ServerConfig, VarInt};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use types::{
+    parser::{BytesBuf, Parsable},
+    serializer::Serializable,
+    Domain, Message, Question, RecordClass, RecordType,
+};
+
+use crate::{Pool, RequestOptions, TlsOptions, Transport};
+
+const CA: &[u8] = include_bytes!("../../stream/tests/ca.crt.der");
+const CERTIFICATE: &[u8] = include_bytes!("../../stream/tests/localhost.crt.der");
+const KEY: &[u8] = include_bytes!("../../stream/tests/localhost.key.der");
+
+fn question(name: &str) -> Question {
+    Question {
+        name: Domain(vec![name.into(), "example".into()]),
+        qtype: RecordType::A,
+        qclass: RecordClass::IN,
+    }
+}
+
+/// Answers one query on the next stream of `connection` with the query itself
+async fn answer(connection: &Connection) {
+    let (mut send, mut recv) = connection.accept_bi().await.unwrap();
+    let data = recv.read_to_end(usize::from(u16::MAX) + 2).await.unwrap();
+
+    let mut message = Message::parse(&mut BytesBuf::from_bytes(data[2..].to_vec().into())).unwrap();
+    message.header.is_response = true;
+
+    let mut buf = BytesMut::new();
+    message.serialize(&mut buf).unwrap();
+
+    let mut framed = u16::try_from(buf.len()).unwrap().to_be_bytes().to_vec();
+    framed.extend_from_slice(&buf);
+    send.write_all(&framed).await.unwrap();
+    send.finish().unwrap();
+    // Waits for the response to get there before anything closes the connection
+    let _ = send.stopped().await;
+}
+
+/// DoQ server answering `per_connection` queries on each connection before closing it,
+/// and counting the connections
+fn server(per_connection: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+    let mut tls = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![CertificateDer::from(CERTIFICATE)],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(KEY)),
+        )
+        .unwrap();
+    tls.alpn_protocols = vec![b"doq".to_vec()];
+    let config = ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls).unwrap()));
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let endpoint = Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+            sender.send(endpoint.local_addr().unwrap()).unwrap();
+
+            while let Some(incoming) = endpoint.accept().await {
+                let connection = incoming.await.unwrap();
+                counter.fetch_add(1, Ordering::Relaxed);
+
+                tokio::spawn(async move {
+                    for _ in 0..per_connection {
+                        answer(&connection).await;
+                    }
+                    connection.close(VarInt::from_u32(0), b"");
+                });
+            }
+        });
+    });
+
+    (receiver.recv().unwrap(), connections)
+}
+
+fn pool() -> Pool {
+    Pool::new(RequestOptions {
+        tls: TlsOptions {
+            server_name: Some("localhost".into()),
+            ca_certificates: vec![CertificateDer::from(CA)],
+            ..TlsOptions::default()
+        },
+        ..RequestOptions::default()
+    })
+}
+
+#[test]
+fn reuses_connection() {
+    let (addr, connections) = server(3);
+    let pool = pool();
+
+    thread::scope(|scope| {
+        for name in ["a", "b"] {
+            let pool = &pool;
+            scope.spawn(move || {
+                let response = pool.request(question(name), addr, Transport::Quic).unwrap();
+                assert_eq!(response.questions, vec![question(name)]);
+                assert_eq!(response.header.id, 0);
+            });
+        }
+    });
+
+    let response = pool.request(question("c"), addr, Transport::Quic).unwrap();
+    assert_eq!(response.questions, vec![question("c")]);
+    assert_eq!(connections.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn reconnects_after_close() {
+    let (addr, connections) = server(1);
+    let pool = pool();
+
+    for name in ["a", "b"] {
+        let response = pool.request(question(name), addr, Transport::Quic).unwrap();
+        assert_eq!(response.questions, vec![question(name)]);
+    }
+    assert_eq!(connections.load(Ordering::Relaxed), 2);
+}
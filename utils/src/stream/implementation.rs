@@ -0,0 +1,150 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpStream,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// A connection that one thread can read from while others write whole messages to it,
+/// for pipelining queries over TCP and TLS (RFC 7766 section 6.2.1.1)
+pub trait Duplex: Send + Sync {
+    /// The TCP connection underneath, for timeouts and shutting it down
+    fn socket(&self) -> &TcpStream;
+
+    /// Reads what's available, 0 meaning the other side closed the connection.
+    /// Only one thread should be reading at a time
+    fn read(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Writes all of `data` before anything else gets written
+    fn write_all(&self, data: &[u8]) -> std::io::Result<()>;
+
+    /// Lets the other side know nothing more is coming
+    fn close(&self) -> std::io::Result<()>;
+}
+
+/// [`Read`] for the reading half of a [`Duplex`]
+pub struct DuplexReader<'a, D: ?Sized>(pub &'a D);
+
+impl<D: Duplex + ?Sized> Read for DuplexReader<'_, D> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Whatever panicked didn't leave a half written message behind, since writes are all or nothing
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Debug)]
+pub struct TcpDuplex {
+    socket: TcpStream,
+    writing: Mutex<()>,
+}
+
+impl TcpDuplex {
+    pub fn new(socket: TcpStream) -> TcpDuplex {
+        TcpDuplex {
+            socket,
+            writing: Mutex::new(()),
+        }
+    }
+}
+
+impl Duplex for TcpDuplex {
+    fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+
+    fn read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        (&self.socket).read(buf)
+    }
+
+    fn write_all(&self, data: &[u8]) -> std::io::Result<()> {
+        let _writing = lock(&self.writing);
+        (&self.socket).write_all(data)
+    }
+
+    fn close(&self) -> std::io::Result<()> {
+        self.socket.shutdown(std::net::Shutdown::Write)
+    }
+}
+
+/// TLS over a TCP connection, the TLS state is only locked while handling data that's already
+/// arrived, never while waiting for more
+#[derive(Debug)]
+pub struct TlsDuplex {
+    socket: TcpStream,
+    conn: Mutex<rustls::Connection>,
+}
+
+impl TlsDuplex {
+    /// `conn` is fresh, the handshake happens with the first read or write
+    pub fn new(socket: TcpStream, conn: impl Into<rustls::Connection>) -> TlsDuplex {
+        TlsDuplex {
+            socket,
+            conn: Mutex::new(conn.into()),
+        }
+    }
+
+    /// Sends whatever TLS records are waiting, like handshake messages, alerts or encrypted data
+    fn flush(&self, conn: &mut rustls::Connection) -> std::io::Result<()> {
+        while conn.wants_write() {
+            conn.write_tls(&mut &self.socket)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Duplex for TlsDuplex {
+    fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+
+    fn read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut raw = vec![0; 16 * 1024];
+
+        loop {
+            {
+                let mut conn = lock(&self.conn);
+                match conn.reader().read(buf) {
+                    Ok(len) => return Ok(len),
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) => return Err(err),
+                }
+
+                // The handshake needs replies before any data shows up
+                self.flush(&mut conn)?;
+            }
+
+            let len = (&self.socket).read(&mut raw)?;
+            if len == 0 {
+                return Ok(0);
+            }
+
+            let mut conn = lock(&self.conn);
+            let mut data = &raw[..len];
+            while !data.is_empty() {
+                conn.read_tls(&mut data)?;
+
+                if let Err(err) = conn.process_new_packets() {
+                    // Lets the other side know why before giving up
+                    let _ = self.flush(&mut conn);
+                    return Err(std::io::Error::new(ErrorKind::InvalidData, err));
+                }
+            }
+        }
+    }
+
+    fn write_all(&self, data: &[u8]) -> std::io::Result<()> {
+        let mut conn = lock(&self.conn);
+        conn.writer().write_all(data)?;
+        self.flush(&mut conn)
+    }
+
+    fn close(&self) -> std::io::Result<()> {
+        let mut conn = lock(&self.conn);
+        conn.send_close_notify();
+        self.flush(&mut conn)
+    }
+}
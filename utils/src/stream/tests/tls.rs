@@ -0,0 +1,101 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection,
+};
+
+use crate::stream::*;
+
+const CA: &[u8] = include_bytes!("ca.crt.der");
+const CERTIFICATE: &[u8] = include_bytes!("localhost.crt.der");
+const KEY: &[u8] = include_bytes!("localhost.key.der");
+
+/// Both ends of a TLS connection over loopback
+fn connected() -> (TlsDuplex, TlsDuplex) {
+    let server_config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![CertificateDer::from(CERTIFICATE)],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(KEY)),
+        )
+        .unwrap();
+
+    let mut roots = RootCertStore::empty();
+    roots.add(CertificateDer::from(CA)).unwrap();
+    let client_config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    (
+        TlsDuplex::new(
+            client,
+            ClientConnection::new(
+                Arc::new(client_config),
+                ServerName::try_from("localhost").unwrap(),
+            )
+            .unwrap(),
+        ),
+        TlsDuplex::new(
+            server,
+            ServerConnection::new(Arc::new(server_config)).unwrap(),
+        ),
+    )
+}
+
+fn read_line<D: Duplex>(duplex: &D) -> String {
+    let mut line = String::new();
+    BufReader::new(DuplexReader(duplex))
+        .read_line(&mut line)
+        .unwrap();
+    line
+}
+
+#[test]
+fn writes_while_reading() {
+    let (client, server) = connected();
+
+    thread::scope(|scope| {
+        // Written before the handshake is done, so it has to wait for the reader to finish it
+        client.write_all(b"first\n").unwrap();
+
+        let reading = scope.spawn(|| read_line(&client));
+
+        assert_eq!(read_line(&server), "first\n");
+
+        // Goes through while the client is stuck reading
+        server.write_all(b"second\n").unwrap();
+        assert_eq!(reading.join().unwrap(), "second\n");
+    });
+}
+
+#[test]
+fn closing() {
+    let (client, server) = connected();
+
+    // Closing only works once the handshake is done, which takes someone reading on both ends
+    thread::scope(|scope| {
+        let reading = scope.spawn(|| read_line(&client));
+
+        client.write_all(b"hi\n").unwrap();
+        assert_eq!(read_line(&server), "hi\n");
+        server.write_all(b"hello\n").unwrap();
+        assert_eq!(reading.join().unwrap(), "hello\n");
+    });
+
+    client.write_all(b"bye").unwrap();
+    client.close().unwrap();
+
+    let mut data = vec![];
+    DuplexReader(&server).read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"bye");
+}
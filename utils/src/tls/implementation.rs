@@ -0,0 +1,198 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{format_err, Result};
+use aws_lc_rs::digest::{digest, SHA256};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        WebPkiServerVerifier,
+    },
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    CertificateError, ClientConfig, DigitallySignedStruct, KeyLogFile, RootCertStore,
+    SignatureScheme,
+};
+
+use crate::RequestError;
+
+/// SHA-256 hash of a certificate's SubjectPublicKeyInfo, the way RFC 7858 section 4.2 pins it
+pub type SpkiPin = [u8; 32];
+
+/// How DNS over TLS servers get authenticated (RFC 7858 section 4, RFC 8310)
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct TlsOptions {
+    /// Name the server's certificate has to be for, also sent as SNI.
+    /// The IP address is used when there isn't one
+    pub server_name: Option<String>,
+    /// CAs trusted instead of the web PKI roots, when there are any
+    pub ca_certificates: Vec<CertificateDer<'static>>,
+    /// When there are any, the server's certificate has to have one of these public keys,
+    /// and is trusted without checking who signed it
+    pub spki_pins: Vec<SpkiPin>,
+    /// Encrypts without authenticating the server at all, which only protects against passive
+    /// eavesdroppers (the opportunistic privacy profile of RFC 7858 section 4.1)
+    pub opportunistic: bool,
+    /// Writes session keys to the file in `SSLKEYLOGFILE`, so captured traffic can be decrypted
+    pub key_log: bool,
+}
+
+impl TlsOptions {
+    /// Name to send as SNI and check the server's certificate against
+    pub(crate) fn server_name(
+        &self,
+        source: SocketAddr,
+    ) -> Result<ServerName<'static>, RequestError> {
+        match &self.server_name {
+            Some(name) => ServerName::try_from(name.clone())
+                .map_err(|err| RequestError::Io(io::Error::new(io::ErrorKind::InvalidInput, err))),
+            None => Ok(ServerName::IpAddress(source.ip().into())),
+        }
+    }
+}
+
+/// Pin from its base64 encoding, as in `pin-sha256="..."` (RFC 7469 section 2.4)
+pub fn parse_spki_pin(pin: &str) -> Result<SpkiPin> {
+    let hash = STANDARD
+        .decode(pin)
+        .map_err(|err| format_err!("SPKI pin {pin} isn't base64: {err}"))?;
+
+    hash.try_into()
+        .map_err(|_| format_err!("SPKI pin {pin} isn't a SHA-256 hash"))
+}
+
+/// Pin of the public key in `certificate`
+pub fn spki_pin(certificate: &CertificateDer<'_>) -> Result<SpkiPin, rustls::Error> {
+    let certificate = webpki::EndEntityCert::try_from(certificate)
+        .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+    let spki = certificate.subject_public_key_info();
+
+    Ok(digest(&SHA256, spki.as_ref())
+        .as_ref()
+        .try_into()
+        .expect("SHA-256 hashes are 32 bytes"))
+}
+
+/// Every certificate in a PEM file, to trust as CAs
+pub fn read_ca_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).map_err(|err| format_err!("Couldn't read {path:?}: {err}"))?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format_err!("Couldn't read {path:?}: {err}"))?;
+
+    if certificates.is_empty() {
+        return Err(format_err!("{path:?} doesn't have any PEM certificates"));
+    }
+
+    Ok(certificates)
+}
+
+/// Checks certificates against the pins, or not at all when opportunistic. Handshake signatures
+/// are still checked either way, so the server has to hold the key it presented
+#[derive(Debug)]
+struct Verifier {
+    webpki: Arc<WebPkiServerVerifier>,
+    spki_pins: Vec<SpkiPin>,
+    opportunistic: bool,
+}
+
+impl ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.opportunistic {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        if self.spki_pins.is_empty() {
+            return self.webpki.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            );
+        }
+
+        if self.spki_pins.contains(&spki_pin(end_entity)?) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.webpki.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.webpki.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.webpki.supported_verify_schemes()
+    }
+}
+
+/// Client config authenticating servers the way `options` says, negotiating one of `alpn`
+/// if there are any
+pub(crate) fn tls_config(
+    options: &TlsOptions,
+    alpn: &[&[u8]],
+) -> Result<Arc<ClientConfig>, RequestError> {
+    let mut roots = RootCertStore::empty();
+    if options.ca_certificates.is_empty() {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    } else {
+        for certificate in &options.ca_certificates {
+            roots.add(certificate.clone())?;
+        }
+    }
+
+    let webpki = WebPkiServerVerifier::builder(Arc::new(roots))
+        .build()
+        .map_err(|err| rustls::Error::General(err.to_string()))?;
+
+    let builder = ClientConfig::builder();
+    let mut config = if options.opportunistic || !options.spki_pins.is_empty() {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(Verifier {
+                webpki,
+                spki_pins: options.spki_pins.clone(),
+                opportunistic: options.opportunistic,
+            }))
+            .with_no_client_auth()
+    } else {
+        builder.with_webpki_verifier(webpki).with_no_client_auth()
+    };
+
+    if options.key_log {
+        config.key_log = Arc::new(KeyLogFile::new());
+    }
+    config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+
+    Ok(Arc::new(config))
+}
@@ -0,0 +1,164 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+    thread,
+};
+
+use bytes::BytesMut;
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    ServerConfig, ServerConnection, StreamOwned,
+};
+use types::{
+    parser::{BytesBuf, Parsable},
+    serializer::Serializable,
+    Domain, Message, Question, RecordClass, RecordType,
+};
+
+use crate::{make_request_with_options, tls::*, RequestError, RequestOptions, Transport};
+
+const CA: &[u8] = include_bytes!("../../stream/tests/ca.crt.der");
+const CERTIFICATE: &[u8] = include_bytes!("../../stream/tests/localhost.crt.der");
+const KEY: &[u8] = include_bytes!("../../stream/tests/localhost.key.der");
+
+/// DNS over TLS server answering one query with the certificate for `localhost`
+fn server() -> SocketAddr {
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![CertificateDer::from(CERTIFICATE)],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(KEY)),
+        )
+        .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let (socket, _) = listener.accept().unwrap();
+        let conn = ServerConnection::new(Arc::new(config)).unwrap();
+        let mut stream = StreamOwned::new(conn, socket);
+
+        // Fails when the client doesn't like the certificate
+        let mut size = [0; 2];
+        if stream.read_exact(&mut size).is_err() {
+            return;
+        }
+        let mut data = vec![0; u16::from_be_bytes(size).into()];
+        stream.read_exact(&mut data).unwrap();
+
+        let mut response = Message::parse(&mut BytesBuf::from_bytes(data.into())).unwrap();
+        response.header.is_response = true;
+
+        let mut buf = BytesMut::new();
+        response.serialize(&mut buf).unwrap();
+        stream
+            .write_all(&u16::try_from(buf.len()).unwrap().to_be_bytes())
+            .unwrap();
+        stream.write_all(&buf).unwrap();
+        stream.flush().unwrap();
+    });
+
+    addr
+}
+
+fn request(tls: TlsOptions) -> Result<Message, RequestError> {
+    let question = Question {
+        name: Domain(vec!["www".into(), "example".into()]),
+        qtype: RecordType::A,
+        qclass: RecordClass::IN,
+    };
+    let options = RequestOptions {
+        tls,
+        ..RequestOptions::default()
+    };
+
+    make_request_with_options(question, server(), Transport::Tls, &options)
+}
+
+fn localhost() -> Option<String> {
+    Some("localhost".into())
+}
+
+#[test]
+fn custom_ca() {
+    let response = request(TlsOptions {
+        server_name: localhost(),
+        ca_certificates: vec![CertificateDer::from(CA)],
+        ..TlsOptions::default()
+    })
+    .unwrap();
+    assert!(response.header.is_response);
+
+    // Web PKI roots don't know about it
+    let err = request(TlsOptions {
+        server_name: localhost(),
+        ..TlsOptions::default()
+    })
+    .unwrap_err();
+    assert!(matches!(err, RequestError::TlsHandshake(_)));
+}
+
+#[test]
+fn server_name() {
+    // The certificate is for 127.0.0.1 too, which is checked when there isn't a name
+    let response = request(TlsOptions {
+        ca_certificates: vec![CertificateDer::from(CA)],
+        ..TlsOptions::default()
+    })
+    .unwrap();
+    assert!(response.header.is_response);
+
+    let err = request(TlsOptions {
+        server_name: Some("dns.example".into()),
+        ca_certificates: vec![CertificateDer::from(CA)],
+        ..TlsOptions::default()
+    })
+    .unwrap_err();
+    assert!(matches!(err, RequestError::TlsHandshake(_)));
+}
+
+#[test]
+fn spki_pins() {
+    let pin = spki_pin(&CertificateDer::from(CERTIFICATE)).unwrap();
+
+    // The pin is enough, without a CA or name
+    let response = request(TlsOptions {
+        spki_pins: vec![[0; 32], pin],
+        ..TlsOptions::default()
+    })
+    .unwrap();
+    assert!(response.header.is_response);
+
+    // A valid certificate doesn't make up for the wrong key
+    let err = request(TlsOptions {
+        server_name: localhost(),
+        ca_certificates: vec![CertificateDer::from(CA)],
+        spki_pins: vec![spki_pin(&CertificateDer::from(CA)).unwrap()],
+        ..TlsOptions::default()
+    })
+    .unwrap_err();
+    assert!(matches!(err, RequestError::TlsHandshake(_)));
+}
+
+#[test]
+fn opportunistic() {
+    let response = request(TlsOptions {
+        opportunistic: true,
+        ..TlsOptions::default()
+    })
+    .unwrap();
+    assert!(response.header.is_response);
+}
+
+#[test]
+fn parses_pins() {
+    let pin = spki_pin(&CertificateDer::from(CERTIFICATE)).unwrap();
+    let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, pin);
+    assert_eq!(parse_spki_pin(&encoded).unwrap(), pin);
+
+    for invalid in ["", "not base64!", "AAAA"] {
+        assert!(parse_spki_pin(invalid).is_err(), "{invalid}");
+    }
+}